@@ -0,0 +1,87 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use image::{ImageBuffer, Rgba};
+use msdf_atlas::internals::{RawImage, RawImageView};
+use rayon::prelude::*;
+use std::sync::{Arc, Mutex};
+
+const ATLAS_SIZE: u32 = 4096;
+const GLYPH_SIZE: u32 = 64;
+
+type GlyphImage = ImageBuffer<Rgba<f32>, Vec<f32>>;
+
+/// The x, y, width and height of a glyph in the atlas.
+type GlyphRect = (u32, u32, u32, u32);
+
+fn quantize_u8(channel: f32) -> u8 {
+    (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Tiles the atlas with glyphs, returning every glyph and the rect it is copied to.
+fn create_glyphs() -> (Vec<GlyphImage>, Vec<GlyphRect>) {
+    let per_row = ATLAS_SIZE / GLYPH_SIZE;
+    let glyph = ImageBuffer::from_fn(GLYPH_SIZE, GLYPH_SIZE, |x, y| {
+        Rgba([
+            x as f32 / GLYPH_SIZE as f32,
+            y as f32 / GLYPH_SIZE as f32,
+            0.5,
+            1.0,
+        ])
+    });
+    let rects = (0..per_row * per_row)
+        .map(|i| {
+            let (x, y) = ((i % per_row) * GLYPH_SIZE, (i / per_row) * GLYPH_SIZE);
+            (x, y, GLYPH_SIZE, GLYPH_SIZE)
+        })
+        .collect::<Vec<_>>();
+    (vec![glyph; rects.len()], rects)
+}
+
+/// The previous strategy, which locked the view of every glyph and wrote each pixel through a
+/// dynamic quantize function.
+fn copy_with_locks(glyphs: &[GlyphImage], rects: &[GlyphRect], pixels: &mut [[u8; 4]]) {
+    let mut raw_img = RawImage::new(pixels, ATLAS_SIZE, ATLAS_SIZE);
+    let views: Vec<Arc<Mutex<RawImageView<u8>>>> = raw_img
+        .split_disjoint(rects)
+        .unwrap()
+        .into_iter()
+        .map(|view| Arc::new(Mutex::new(view)))
+        .collect();
+    let quantize: &(dyn Fn(f32) -> u8 + Sync) = &quantize_u8;
+
+    glyphs.par_iter().zip(&views).for_each(|(glyph, view)| {
+        let mut view = view.lock().unwrap();
+        for (x, y, pixel) in glyph.enumerate_pixels() {
+            let [r, g, b, a] = pixel.0;
+            let color = [quantize(r), quantize(g), quantize(b), quantize(a)];
+            // Each glyph is exactly the size of its view.
+            unsafe { view.set_pixel_unchecked(x, y, color) };
+        }
+    });
+}
+
+fn copy_disjoint(glyphs: &[GlyphImage], rects: &[GlyphRect], pixels: &mut [[u8; 4]]) {
+    let mut raw_img = RawImage::new(pixels, ATLAS_SIZE, ATLAS_SIZE);
+    let mut views = raw_img.split_disjoint(rects).unwrap();
+    views
+        .par_iter_mut()
+        .zip(glyphs)
+        .for_each(|(view, glyph)| view.write_from(glyph, quantize_u8));
+}
+
+fn bench_atlas_copy(c: &mut Criterion) {
+    let (glyphs, rects) = create_glyphs();
+    let mut pixels = vec![[0u8; 4]; (ATLAS_SIZE * ATLAS_SIZE) as usize];
+
+    let mut group = c.benchmark_group("atlas_copy");
+    group.sample_size(20);
+    group.bench_function(BenchmarkId::new("locked", glyphs.len()), |b| {
+        b.iter(|| copy_with_locks(&glyphs, &rects, &mut pixels))
+    });
+    group.bench_function(BenchmarkId::new("disjoint", glyphs.len()), |b| {
+        b.iter(|| copy_disjoint(&glyphs, &rects, &mut pixels))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_atlas_copy);
+criterion_main!(benches);
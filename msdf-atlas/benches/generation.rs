@@ -0,0 +1,191 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use msdf_atlas::internals::{
+    calculate_minimum_atlas_dimensions, generate_glyph_image, resolve_glyph_indices,
+    store_and_sort_by_area, GlyphBoundingBoxData,
+};
+use msdf_atlas::{Args, Builder, GlyphOverrides};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::ffi::OsStr;
+use std::fs::{read, remove_file};
+use ttf_parser::{Face, GlyphId};
+
+const FONT_PATH: &str = "testing-resources/Roboto-Medium.ttf";
+
+/// The scales the glyphs are generated at, from small to large glyphs.
+const SCALES: [f32; 3] = [1.0 / 64.0, 1.0 / 32.0, 1.0 / 16.0];
+
+/// The thread counts the fields are generated with, where 0 uses every available core.
+const THREAD_COUNTS: [usize; 3] = [1, 4, 0];
+
+/// The extensions of every format an atlas can be written as.
+const ATLAS_EXTENSIONS: [&str; 3] = ["png", "ktx2", "dds"];
+
+/// Returns the name and the characters of every charset.
+///
+/// # Arguments
+///
+/// * `face` - The font, whose mapped characters form the synthetic charset
+fn charsets(face: &Face) -> Vec<(&'static str, String)> {
+    let ascii: String = (0x20u8..0x7F).map(char::from).collect();
+    let latin_extended: String = ('\u{0100}'..='\u{024F}').collect();
+    // Every character the font maps in the basic multilingual plane.
+    let synthetic: String = ('\u{0020}'..='\u{FFFF}')
+        .filter(|c| face.glyph_index(*c).is_some())
+        .collect();
+    vec![
+        ("ascii", ascii),
+        ("latin_extended", latin_extended),
+        ("synthetic", synthetic),
+    ]
+}
+
+/// Creates the args for a scale, keeping the distance range at 8 pixels.
+///
+/// # Arguments
+///
+/// * `scale` - The uniform scale of the glyphs
+fn create_args(scale: f32) -> Args {
+    Args::default()
+        .with_uniform_scale(scale)
+        .with_range(8.0 / scale)
+        .with_padding(4)
+}
+
+/// Returns the bounding box of every glyph of the charset, sorted as they are packed.
+///
+/// # Arguments
+///
+/// * `face` - The font to load the glyphs from
+/// * `glyphs` - The unicode and glyph index of every glyph
+/// * `args` - Generator params
+fn create_bounding_boxes(
+    face: &Face,
+    glyphs: &[(char, GlyphId)],
+    args: &Args,
+) -> Vec<GlyphBoundingBoxData> {
+    let mut glyph_bounding_boxes = Vec::with_capacity(glyphs.len());
+    store_and_sort_by_area(
+        &mut glyph_bounding_boxes,
+        face,
+        glyphs,
+        args,
+        &GlyphOverrides::default(),
+    );
+    glyph_bounding_boxes
+}
+
+fn bench_font_loading(c: &mut Criterion) {
+    let font = read(FONT_PATH).unwrap();
+    let face = Face::parse(&font, 0).unwrap();
+
+    let mut group = c.benchmark_group("font_loading");
+    for (name, chars) in charsets(&face) {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                let font = read(FONT_PATH).unwrap();
+                let face = Face::parse(&font, 0).unwrap();
+                resolve_glyph_indices(&face, chars.chars())
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_packing(c: &mut Criterion) {
+    let font = read(FONT_PATH).unwrap();
+    let face = Face::parse(&font, 0).unwrap();
+
+    let mut group = c.benchmark_group("packing");
+    for (name, chars) in charsets(&face) {
+        let glyphs = resolve_glyph_indices(&face, chars.chars());
+        for scale in SCALES {
+            let args = create_args(scale);
+            group.bench_function(BenchmarkId::new(name, 1.0 / scale), |b| {
+                b.iter(|| {
+                    let glyph_bounding_boxes = create_bounding_boxes(&face, &glyphs, &args);
+                    calculate_minimum_atlas_dimensions(&glyph_bounding_boxes, &args)
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Times generating the fields alone, without composing them into an atlas.
+fn bench_field_generation(c: &mut Criterion) {
+    let font = read(FONT_PATH).unwrap();
+    let face = Face::parse(&font, 0).unwrap();
+
+    let mut group = c.benchmark_group("field_generation");
+    group.sample_size(10);
+    for (name, chars) in charsets(&face) {
+        let glyphs = resolve_glyph_indices(&face, chars.chars());
+        for scale in SCALES {
+            let args = create_args(scale);
+            let glyph_bounding_boxes = create_bounding_boxes(&face, &glyphs, &args);
+
+            for thread_count in THREAD_COUNTS {
+                let pool = ThreadPoolBuilder::new()
+                    .num_threads(thread_count)
+                    .build()
+                    .unwrap();
+
+                let id = format!("{}/{}/{}", name, 1.0 / scale, thread_count);
+                group.bench_function(BenchmarkId::from_parameter(id), |b| {
+                    b.iter(|| {
+                        pool.install(|| {
+                            glyph_bounding_boxes
+                                .par_iter()
+                                .for_each(|glyph_bounding_box| {
+                                    black_box(generate_glyph_image(
+                                        &face,
+                                        glyph_bounding_box,
+                                        &args,
+                                    ));
+                                })
+                        })
+                    })
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+/// Times generating, composing and writing an atlas in every format, the way the FFI builds
+/// one. The glyphs are loaded and packed outside of the timed part.
+fn bench_atlas_writing(c: &mut Criterion) {
+    let font = read(FONT_PATH).unwrap();
+    let face = Face::parse(&font, 0).unwrap();
+    let args = create_args(1.0 / 32.0);
+
+    let mut group = c.benchmark_group("atlas_writing");
+    group.sample_size(10);
+    for (name, chars) in charsets(&face) {
+        for extension in ATLAS_EXTENSIONS {
+            let atlas_path = std::env::temp_dir().join(format!("msdf-atlas-bench.{}", extension));
+            group.bench_function(BenchmarkId::new(name, extension), |b| {
+                b.iter_batched(
+                    || Builder::from_font_path(OsStr::new(FONT_PATH), chars.clone(), &args),
+                    |mut builder| {
+                        builder.prepare_workload(0).build_atlas(&atlas_path);
+                        assert!(builder.is_atlas_written(), "Failed to write the atlas.");
+                    },
+                    BatchSize::PerIteration,
+                )
+            });
+            let _ = remove_file(atlas_path);
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_font_loading,
+    bench_packing,
+    bench_field_generation,
+    bench_atlas_writing
+);
+criterion_main!(benches);
@@ -5,9 +5,17 @@ fn main() {
         .input_extern_file("src/lib.rs")
         .input_extern_file("src/msdf_impl/args.rs")
         .input_extern_file("src/msdf_impl/byte_buffer.rs")
+        .input_extern_file("src/msdf_impl/glyph_cache.rs")
         .input_extern_file("src/msdf_impl/glyph_data.rs")
         .input_extern_file("src/msdf_impl/enums.rs")
         .input_extern_file("src/msdf_impl/font_data.rs")
+        .input_extern_file("src/msdf_impl/jobs.rs")
+        .input_extern_file("src/msdf_impl/kerning.rs")
+        .input_extern_file("src/msdf_impl/layout.rs")
+        .input_extern_file("src/msdf_impl/logging.rs")
+        .input_extern_file("src/msdf_impl/options.rs")
+        .input_extern_file("src/msdf_impl/overrides.rs")
+        .input_extern_file("src/msdf_impl/progress.rs")
         .csharp_namespace("InitialPrefabs.Msdf.EditorExtensions")
         .csharp_dll_name("msdf_atlas")
         .generate_csharp_file("MsdfAtlas.cs")
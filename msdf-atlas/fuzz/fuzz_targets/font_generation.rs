@@ -0,0 +1,76 @@
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use msdf_atlas::{Args, AtlasPrecision, Builder, ColorType, UVSpace};
+
+/// The largest atlas composed per input, so large but valid fonts do not exhaust the fuzzer's
+/// memory or time limits. Larger atlases are still loaded and packed.
+const MAX_COMPOSED_PIXELS: u64 = 1 << 20;
+
+/// Builds the args from the start of the input, including values the generator must reject.
+///
+/// # Arguments
+///
+/// * `u` - The unstructured input
+fn arbitrary_args(u: &mut Unstructured) -> Result<Args> {
+    let precision = *u.choose(&[
+        AtlasPrecision::Bits8,
+        AtlasPrecision::Bits16,
+        AtlasPrecision::Float32,
+    ])?;
+    let color_type = *u.choose(&[ColorType::Simple, ColorType::InkTrap, ColorType::Distance])?;
+    let uv_space = *u.choose(&[
+        UVSpace::Default,
+        UVSpace::OneMinusU,
+        UVSpace::OneMinusV,
+        UVSpace::OneMinusU | UVSpace::OneMinusV,
+    ])?;
+
+    Ok(Args::default()
+        .with_uniform_scale(u.arbitrary()?)
+        .with_range(u.arbitrary()?)
+        .with_padding(u.int_in_range(0..=64)?)
+        .with_max_atlas(u.arbitrary()?)
+        .with_angle(u.arbitrary()?)
+        .with_scaled_texture(u.arbitrary()?)
+        .with_thread_count(u.int_in_range(0..=4)?)
+        .with_coloring_seed(u.arbitrary()?)
+        .with_color_type(color_type)
+        .with_uv_space(uv_space)
+        .with_precision(precision))
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let (args, chars): (Args, String) = match (arbitrary_args(&mut u), u.arbitrary()) {
+        (Ok(args), Ok(chars)) => (args, chars),
+        _ => return,
+    };
+    let font = u.take_rest();
+
+    // Every malformed font or arg must be reported as an error rather than a panic.
+    let mut builder = match Builder::from_font_bytes(font, &chars, &args) {
+        Ok(builder) => builder,
+        Err(_) => return,
+    };
+
+    let (width, height) = builder.atlas_dimensions;
+    if width as u64 * height as u64 > MAX_COMPOSED_PIXELS {
+        return;
+    }
+
+    let asset = builder
+        .prepare_workload(args.thread_count as usize)
+        .package_font_asset();
+    let channel_size = match args.precision {
+        AtlasPrecision::Bits8 => 1,
+        AtlasPrecision::Bits16 => 2,
+        AtlasPrecision::Float32 => 4,
+    };
+    assert_eq!(
+        asset.atlas.data.len(),
+        width as usize * height as usize * 4 * channel_size,
+        "The atlas should hold every pixel of its dimensions."
+    );
+});
@@ -0,0 +1,116 @@
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use msdf_atlas::internals::RawImage;
+use std::collections::HashSet;
+
+/// The value of every pixel before the views are written.
+const SENTINEL: [u8; 4] = [0xAB; 4];
+
+/// The value written to the pixels of the views.
+const WRITTEN: [u8; 4] = [0xCD; 4];
+
+/// Builds the rects from the input, allowing them to reach past the image so out of bounds
+/// rects are exercised alongside overlapping ones.
+///
+/// # Arguments
+///
+/// * `u` - The unstructured input
+/// * `width` - The width of the image
+/// * `height` - The height of the image
+fn arbitrary_rects(
+    u: &mut Unstructured,
+    width: u32,
+    height: u32,
+) -> Result<Vec<(u32, u32, u32, u32)>> {
+    let count = u.int_in_range(0..=16)?;
+    (0..count)
+        .map(|_| {
+            Ok((
+                u.int_in_range(0..=width + 2)?,
+                u.int_in_range(0..=height + 2)?,
+                u.int_in_range(0..=width + 2)?,
+                u.int_in_range(0..=height + 2)?,
+            ))
+        })
+        .collect()
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let (width, height) = match (u.int_in_range(0..=64u32), u.int_in_range(0..=64u32)) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => return,
+    };
+    let rects = match arbitrary_rects(&mut u, width, height) {
+        Ok(rects) => rects,
+        Err(_) => return,
+    };
+
+    let mut pixels = vec![SENTINEL; width as usize * height as usize];
+    let mut image = RawImage::new(&mut pixels, width, height);
+    let views = match image.split_disjoint(&rects) {
+        Some(views) => views,
+        None => return,
+    };
+
+    // Writes coordinates inside each view through the unchecked path, which is what the
+    // atlas uses, and coordinates reaching past every edge through the checked path, which the
+    // view must refuse instead of writing into its neighbours or past the image. Running the
+    // target with cargo fuzz builds it with AddressSanitizer, which catches any unchecked write
+    // that lands outside of the pixels.
+    let mut written = HashSet::new();
+    for (mut view, &(rect_x, rect_y, _, _)) in views.into_iter().zip(&rects) {
+        let write_count = u.int_in_range(0..=64).unwrap_or(0);
+        for _ in 0..write_count {
+            let (x, y, unchecked) = match (
+                u.arbitrary::<u32>(),
+                u.arbitrary::<u32>(),
+                u.arbitrary::<bool>(),
+            ) {
+                (Ok(x), Ok(y), Ok(unchecked)) => (x, y, unchecked),
+                _ => break,
+            };
+
+            if unchecked && view.width > 0 && view.height > 0 {
+                let (x, y) = (x % view.width, y % view.height);
+                unsafe { view.set_pixel_unchecked(x, y, WRITTEN) };
+                written.insert((rect_x + x, rect_y + y));
+                continue;
+            }
+
+            let (x, y) = (x % (view.width + 4), y % (view.height + 4));
+            let in_view = x < view.width && y < view.height;
+            assert_eq!(
+                view.set_pixel(x, y, WRITTEN),
+                in_view,
+                "Pixel ({}, {}) of a {}x{} view was handled incorrectly.",
+                x,
+                y,
+                view.width,
+                view.height
+            );
+            if in_view {
+                written.insert((rect_x + x, rect_y + y));
+            }
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let expected = if written.contains(&(x, y)) {
+                WRITTEN
+            } else {
+                SENTINEL
+            };
+            assert_eq!(
+                pixels[(y * width + x) as usize],
+                expected,
+                "Pixel ({}, {}) was written by the wrong view.",
+                x,
+                y
+            );
+        }
+    }
+});
@@ -1,14 +1,45 @@
 use msdf_impl::{
-    args::Args,
     byte_buffer::ByteBuffer,
+    utils::{convert_u16_to_os_string, convert_u16_to_string},
+};
+use std::{ffi::c_void, path::Path};
+
+mod msdf_impl;
+
+pub use msdf_impl::{
+    args::Args,
+    enums::{AtlasPrecision, ColorType, UVSpace},
+    font_asset::{AtlasPixels, FieldSettings, FontAsset, GenerationSettings, FONT_ASSET_VERSION},
     font_data::FontData,
+    glyph_cache::{DirtyRect, GlyphCache},
     glyph_data::GlyphData,
-    utils::{convert_u16_to_os_string, convert_u16_to_string},
+    incremental::{add_glyphs, AtlasUpdate, MovedGlyph},
+    jobs::{CancellationToken, GenerationJob, JobStatus},
+    kerning::KerningPair,
+    layout::{layout_text, GlyphQuad, LayoutArgs, TextAlignment},
+    logging::{LogCallback, LogFallback, LogLevel},
+    options::{BuildOptions, GenerationOptions},
+    overrides::{GlyphOverride, GlyphOverrides},
+    preview::{render_preview, write_preview, PreviewArgs},
+    progress::{ProgressCallback, ProgressPhase, ProgressReporter},
+    quality::{analyze_font, GlyphQuality, QualityArgs, QualityReport},
+    validation::{validate_atlas_image, validate_font_asset, ValidationIssue, ValidationReport},
     Builder,
 };
-use std::path::Path;
 
-mod msdf_impl;
+#[cfg(feature = "shaping")]
+pub use msdf_impl::shaping::{layout_shaped_runs, shape_text, ShapedGlyph, ShapedRun};
+
+/// Internals exposed only for the benchmarks and fuzz targets. They are not part of the public
+/// API and may change at any time.
+#[doc(hidden)]
+pub mod internals {
+    pub use crate::msdf_impl::raw_img::{RawImage, RawImageView};
+    pub use crate::msdf_impl::{
+        calculate_minimum_atlas_dimensions, generate_glyph_image, resolve_glyph_indices,
+        store_and_sort_by_area, GlyphBoundingBoxData,
+    };
+}
 
 /// Returns packed glyph data parsed from msdf.
 ///
@@ -42,6 +73,194 @@ pub unsafe extern "C" fn get_glyph_data_utf16(
         .package_font_data()
 }
 
+/// Returns packed glyph data parsed from msdf like get_glyph_data_utf16, with optional per glyph
+/// overrides, a field cache and progress reports. Fields are cached per font, glyph and the
+/// settings which change the field, so changing the padding or atlas width does not generate the
+/// glyphs again.
+///
+/// # Arguments
+///
+/// * `font_path` - The absolute path to the font
+/// * `atlas_path` - The absolute path to the texture atlas to generate
+/// * `chars_to_generate` - A UTF16 encoded series of characters to generate the characters for
+/// * `args` - Parameters to set for the atlas generation
+/// * `options` - The optional settings of the build
+///
+/// # Safety
+///
+/// This function relies on a C lib, msdfgen. Because of how we represent data, any bad data will
+/// cause this function to panic and crash Unity. The pointers in `options` must be valid or null,
+/// and the progress callback is invoked from worker threads while the glyphs are copied, so it
+/// must be safe to call from any thread.
+#[no_mangle]
+pub unsafe extern "C" fn get_glyph_data_utf16_with_options(
+    font_path: *const u16,
+    atlas_path: *const u16,
+    chars_to_generate: *const u16,
+    args: Args,
+    options: GenerationOptions,
+) -> FontData {
+    let font_path = convert_u16_to_os_string(font_path);
+    let atlas_path = convert_u16_to_string(atlas_path);
+    let chars = convert_u16_to_string(chars_to_generate);
+
+    let atlas_path_buffer = Path::new(&atlas_path);
+
+    Builder::from_font_path_with_options(&font_path, chars, &args, options.to_build_options())
+        .prepare_workload(args.thread_count as usize)
+        .build_atlas(atlas_path_buffer)
+        .package_font_data()
+}
+
+/// Starts generating the atlas on a background thread and returns a handle to the job, so the
+/// editor stays responsive while the glyphs are generated.
+///
+/// # Arguments
+///
+/// * `font_path` - The absolute path to the font
+/// * `atlas_path` - The absolute path to the texture atlas to generate
+/// * `chars_to_generate` - A UTF16 encoded series of characters to generate the characters for
+/// * `args` - Parameters to set for the atlas generation
+/// * `options` - The optional settings of the build
+///
+/// # Safety
+///
+/// The strings must be null terminated UTF16 strings and the pointers in `options` must be valid
+/// or null. The progress callback is invoked from worker threads. The returned job must be
+/// dropped with drop_job.
+#[no_mangle]
+pub unsafe extern "C" fn start_generation(
+    font_path: *const u16,
+    atlas_path: *const u16,
+    chars_to_generate: *const u16,
+    args: Args,
+    options: GenerationOptions,
+) -> *mut GenerationJob {
+    let font_path = convert_u16_to_os_string(font_path);
+    let atlas_path = convert_u16_to_string(atlas_path);
+    let chars = convert_u16_to_string(chars_to_generate);
+
+    let job = GenerationJob::start(
+        font_path,
+        atlas_path.into(),
+        chars,
+        args,
+        options.to_build_options(),
+    );
+    Box::into_raw(Box::new(job))
+}
+
+/// Returns the state of the job without blocking.
+///
+/// # Arguments
+///
+/// * `job` - The job returned by start_generation
+///
+/// # Safety
+///
+/// `job` must be a valid job returned by start_generation.
+#[no_mangle]
+pub unsafe extern "C" fn poll_job(job: &GenerationJob) -> JobStatus {
+    job.status()
+}
+
+/// Requests the job to stop. Poll the job until it is no longer running before starting another
+/// job which writes to the same atlas path.
+///
+/// # Arguments
+///
+/// * `job` - The job returned by start_generation
+///
+/// # Safety
+///
+/// `job` must be a valid job returned by start_generation.
+#[no_mangle]
+pub unsafe extern "C" fn cancel_job(job: &GenerationJob) {
+    job.cancel();
+}
+
+/// Returns the font data of a completed job. Returns font data without any glyphs if the job
+/// did not complete or the result was already taken.
+///
+/// # Arguments
+///
+/// * `job` - The job returned by start_generation
+///
+/// # Safety
+///
+/// `job` must be a valid job returned by start_generation.
+#[no_mangle]
+pub unsafe extern "C" fn take_job_result(job: &GenerationJob) -> FontData {
+    job.take_result()
+        .unwrap_or_else(|| FontData::new(0, 0, 0, 0, Vec::new(), Vec::new()))
+}
+
+/// Drops the job safely from C#. A running job is cancelled.
+///
+/// # Arguments
+///
+/// * `job` - The job returned by start_generation
+///
+/// # Safety
+///
+/// The job must not be used after it is dropped.
+#[no_mangle]
+pub unsafe extern "C" fn drop_job(job: *mut GenerationJob) {
+    if !job.is_null() {
+        drop(Box::from_raw(job));
+    }
+}
+
+/// Forwards log records up to the max level to the callback. Passing a null callback sends the
+/// records to the fallback instead.
+///
+/// # Arguments
+///
+/// * `callback` - Receives the level, target and message of each record
+/// * `user_data` - A pointer passed back to every invocation of the callback
+/// * `max_level` - The most verbose level to forward
+///
+/// # Safety
+///
+/// The callback is invoked from worker threads, so it must be safe to call from any thread. It
+/// must stay valid until another callback is registered.
+#[no_mangle]
+pub unsafe extern "C" fn register_log_callback(
+    callback: Option<LogCallback>,
+    user_data: *mut c_void,
+    max_level: LogLevel,
+) {
+    msdf_impl::logging::register_callback(callback, user_data, max_level);
+}
+
+/// Changes the most verbose level of the log records which are forwarded.
+///
+/// # Arguments
+///
+/// * `max_level` - The most verbose level to forward
+///
+/// # Safety
+///
+/// Only changes the global log level.
+#[no_mangle]
+pub unsafe extern "C" fn set_log_max_level(max_level: LogLevel) {
+    msdf_impl::logging::set_max_level(max_level);
+}
+
+/// Changes where log records go when no callback is registered.
+///
+/// # Arguments
+///
+/// * `fallback` - The destination of the records without a callback
+///
+/// # Safety
+///
+/// Only changes the global log destination.
+#[no_mangle]
+pub unsafe extern "C" fn set_log_fallback(fallback: LogFallback) {
+    msdf_impl::logging::set_fallback(fallback);
+}
+
 /// Drops the byte_buffer safely from C#.
 ///
 /// # Arguments
@@ -75,17 +294,232 @@ pub unsafe extern "C" fn reinterpret_as_glyph_data(byte_buffer: &ByteBuffer, i:
     byte_buffer.element_at::<GlyphData>(i as usize)
 }
 
+/// Reinterprets an element in the ByteBuffer as a KerningPair.
+///
+/// # Arguments
+///
+/// * `byte_buffer` - The byte buffer to reinterpret as an array of KerningPair.
+/// * `i` - The index to access
+///
+/// # Safety
+///
+/// Uses a rust function to convert an element in a continuous array as a KerningPair.
+#[no_mangle]
+pub unsafe extern "C" fn reinterpret_as_kerning_pair(
+    byte_buffer: &ByteBuffer,
+    i: u32,
+) -> KerningPair {
+    byte_buffer.element_at::<KerningPair>(i as usize)
+}
+
+/// Lays out the text into positioned quads, returned as a byte buffer of GlyphQuad.
+///
+/// # Arguments
+///
+/// * `font_data` - The font data returned when the atlas was generated
+/// * `text` - A UTF16 encoded string to lay out
+/// * `args` - Parameters to set for the layout
+///
+/// # Safety
+///
+/// `text` must be a null terminated UTF16 string. The contents of the returned byte buffer must
+/// be dropped by passing a pointer to it to drop_byte_buffer.
+#[no_mangle]
+pub unsafe extern "C" fn layout_text_utf16(
+    font_data: &FontData,
+    text: *const u16,
+    args: LayoutArgs,
+) -> ByteBuffer {
+    let text = convert_u16_to_string(text);
+    let quads = layout_text(font_data, &text, &args);
+    ByteBuffer::from_vec_struct(quads)
+}
+
+/// Reinterprets an element in the ByteBuffer as a GlyphQuad.
+///
+/// # Arguments
+///
+/// * `byte_buffer` - The byte buffer to reinterpret as an array of GlyphQuad.
+/// * `i` - The index to access
+///
+/// # Safety
+///
+/// Uses a rust function to convert an element in a continuous array as a GlyphQuad.
+#[no_mangle]
+pub unsafe extern "C" fn reinterpret_as_glyph_quad(byte_buffer: &ByteBuffer, i: u32) -> GlyphQuad {
+    byte_buffer.element_at::<GlyphQuad>(i as usize)
+}
+
+/// Creates a glyph cache with a fixed size atlas, which generates glyphs as they are requested.
+/// Returns null if the font could not be read.
+///
+/// # Arguments
+///
+/// * `font_path` - The absolute path to the font
+/// * `args` - Parameters to set for the glyph generation
+/// * `width` - The width of the atlas in pixels
+/// * `height` - The height of the atlas in pixels
+///
+/// # Safety
+///
+/// `font_path` must be a null terminated UTF16 string. The returned cache must be dropped with
+/// drop_glyph_cache.
+#[no_mangle]
+pub unsafe extern "C" fn create_glyph_cache_utf16(
+    font_path: *const u16,
+    args: Args,
+    width: u32,
+    height: u32,
+) -> *mut GlyphCache {
+    let font_path = convert_u16_to_os_string(font_path);
+    match GlyphCache::new(&font_path, args, width, height) {
+        Ok(cache) => Box::into_raw(Box::new(cache)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Drops the glyph cache safely from C#.
+///
+/// # Arguments
+///
+/// * `cache` - The cache returned by create_glyph_cache_utf16
+///
+/// # Safety
+///
+/// The cache must not be used after it is dropped.
+#[no_mangle]
+pub unsafe extern "C" fn drop_glyph_cache(cache: *mut GlyphCache) {
+    if !cache.is_null() {
+        drop(Box::from_raw(cache));
+    }
+}
+
+/// Starts a new frame, allowing glyphs which were not requested since the last frame to be
+/// evicted.
+///
+/// # Arguments
+///
+/// * `cache` - The glyph cache
+///
+/// # Safety
+///
+/// `cache` must be a valid cache returned by create_glyph_cache_utf16.
+#[no_mangle]
+pub unsafe extern "C" fn glyph_cache_begin_frame(cache: &mut GlyphCache) {
+    cache.begin_frame();
+}
+
+/// Generates every char of the text which is not cached yet, returning the number of chars which
+/// could not be cached.
+///
+/// # Arguments
+///
+/// * `cache` - The glyph cache
+/// * `text` - A UTF16 encoded string which will be rendered this frame
+///
+/// # Safety
+///
+/// `text` must be a null terminated UTF16 string.
+#[no_mangle]
+pub unsafe extern "C" fn glyph_cache_request_text_utf16(
+    cache: &mut GlyphCache,
+    text: *const u16,
+) -> u32 {
+    let text = convert_u16_to_string(text);
+    cache.request_text(&text)
+}
+
+/// Returns the cached glyphs and their kerning, which can be passed to layout_text_utf16.
+///
+/// # Arguments
+///
+/// * `cache` - The glyph cache
+///
+/// # Safety
+///
+/// The glyph uvs are only valid until the next frame begins.
+#[no_mangle]
+pub unsafe extern "C" fn glyph_cache_font_data(cache: &GlyphCache) -> FontData {
+    cache.font_data()
+}
+
+/// Returns the regions of the atlas which changed since the last call, as a byte buffer of
+/// DirtyRect.
+///
+/// # Arguments
+///
+/// * `cache` - The glyph cache
+///
+/// # Safety
+///
+/// The contents of the returned byte buffer must be dropped by passing a pointer to it to
+/// drop_byte_buffer.
+#[no_mangle]
+pub unsafe extern "C" fn glyph_cache_take_dirty_rects(cache: &mut GlyphCache) -> ByteBuffer {
+    let dirty_rects = cache.take_dirty_rects();
+    ByteBuffer::from_vec_struct(dirty_rects)
+}
+
+/// Returns the atlas pixels, stored row by row from the top with 4 channels per pixel in the
+/// precision from Args.
+///
+/// # Arguments
+///
+/// * `cache` - The glyph cache
+///
+/// # Safety
+///
+/// The pointer is only valid until the cache generates another glyph or is dropped.
+#[no_mangle]
+pub unsafe extern "C" fn glyph_cache_pixels(cache: &GlyphCache) -> *const u8 {
+    cache.pixels().data.as_ptr()
+}
+
+/// Reinterprets an element in the ByteBuffer as a DirtyRect.
+///
+/// # Arguments
+///
+/// * `byte_buffer` - The byte buffer to reinterpret as an array of DirtyRect.
+/// * `i` - The index to access
+///
+/// # Safety
+///
+/// Uses a rust function to convert an element in a continuous array as a DirtyRect.
+#[no_mangle]
+pub unsafe extern "C" fn reinterpret_as_dirty_rect(byte_buffer: &ByteBuffer, i: u32) -> DirtyRect {
+    byte_buffer.element_at::<DirtyRect>(i as usize)
+}
+
 #[cfg(test)]
 mod tests {
     const FONT_PATH: &str = "testing-resources/Roboto-Medium.ttf";
     const DEFAULT_CHAR_SET: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ ";
-    use image::DynamicImage;
+    use image::{ColorType as ImageColorType, DynamicImage};
 
     use crate::msdf_impl::{
-        args::Args, enums::UVSpace, font_data::FontData, get_next_power_of_2,
-        glyph_data::GlyphData, Builder,
+        args::Args,
+        enums::{
+            AtlasPrecision, ColorType, DistanceCheckMode, ErrorCorrectionMode, FillRule,
+            TextureFormat, UVSpace,
+        },
+        font_asset::{FontAsset, GenerationSettings, FONT_ASSET_VERSION},
+        font_data::FontData,
+        get_next_power_of_2,
+        glyph_data::GlyphData,
+        incremental::add_glyphs,
+        options::BuildOptions,
+        overrides::{GlyphOverride, GlyphOverrides},
+        preview::{render_preview, PreviewArgs},
+        progress::{ProgressPhase, ProgressReporter},
+        validation::{validate_atlas_image, validate_font_asset, ValidationIssue},
+        Builder,
+    };
+    use std::{
+        ffi::{c_void, OsStr},
+        fs::{read, remove_file},
+        path::Path,
+        sync::Mutex,
     };
-    use std::{ffi::OsStr, fs::remove_file, path::Path};
 
     #[test]
     fn get_raw_file_works() {
@@ -100,8 +534,8 @@ mod tests {
         let builder = Builder::from_font_path(p, "ABC".to_string(), &args);
         assert_eq!(builder.atlas_offsets.len(), 3);
         assert_eq!(builder.glyph_buffer.capacity(), 3);
-        assert_eq!(builder.glyph_images.capacity(), 3);
-        assert_eq!(builder.thread_metadata.capacity(), 8);
+        assert_eq!(builder.glyph_bounding_boxes.len(), 3);
+        assert!(builder.thread_metadata.is_empty());
     }
 
     #[test]
@@ -116,8 +550,8 @@ mod tests {
         let builder = Builder::from_font_path(OsStr::new(""), "ABC".to_string(), &args);
         assert!(builder.glyph_buffer.is_empty());
         assert!(builder.atlas_offsets.is_empty());
-        assert_eq!(builder.thread_metadata.capacity(), 8);
-        assert!(builder.glyph_images.is_empty());
+        assert!(builder.thread_metadata.is_empty());
+        assert!(builder.glyph_bounding_boxes.is_empty());
     }
 
     #[test]
@@ -147,29 +581,40 @@ mod tests {
             3,
             "Should have prepared 3 metadata"
         );
-        for i in 0..2 {
-            let metadata = &builder.thread_metadata[i];
-            assert_eq!(metadata.work_unit, 3, "The unit of work should only be 3");
-            let expected = i as u32 * 3;
+
+        let area = |start: u32, end: u32| -> u64 {
+            builder.glyph_bounding_boxes[start as usize..end as usize]
+                .iter()
+                .map(|glyph| {
+                    let (width, height) = glyph.get_scaled_glyph_dimensions_no_padding();
+                    width as u64 * height as u64
+                })
+                .sum()
+        };
+        let total_area = area(0, 10);
+        let largest_area = (0..10).map(|i| area(i, i + 1)).max().unwrap();
+
+        let mut expected_start = 0;
+        for metadata in &builder.thread_metadata {
             assert_eq!(
-                metadata.start,
-                expected,
-                "This thread should start with element: {} / {}",
-                expected,
-                builder.thread_metadata.len()
+                metadata.start, expected_start,
+                "The slices should be contiguous."
             );
-        }
-
-        let last = builder.thread_metadata.last().unwrap();
-        assert_eq!(last.work_unit, 4, "The last work unit should be 4");
-        assert_eq!(last.start, 6, "This thread should start with element 6");
+            assert!(metadata.work_unit > 0, "Every thread should get a glyph.");
 
-        // Check what happens if we push too many threads
-        builder.prepare_workload(10);
-        assert_eq!(builder.thread_metadata.len(), 8);
+            let end = metadata.start + metadata.work_unit;
+            assert!(
+                area(metadata.start, end) <= total_area / 3 + largest_area,
+                "The slices should be split by area."
+            );
+            expected_start = end;
+        }
+        assert_eq!(expected_start, 10, "Every glyph should be in a slice.");
 
-        for i in 0..7 {
-            let metadata = &builder.thread_metadata[i];
+        // Check that more than 8 threads can be used, but never more than the glyphs
+        builder.prepare_workload(12);
+        assert_eq!(builder.thread_metadata.len(), 10);
+        for (i, metadata) in builder.thread_metadata.iter().enumerate() {
             assert_eq!(
                 metadata.work_unit, 1,
                 "Each thread should only process 1 element."
@@ -179,13 +624,121 @@ mod tests {
                 "Each thread is responsible for it's current element's index."
             );
         }
-
-        let last = builder.thread_metadata.last().unwrap();
-        assert_eq!(
-            last.work_unit, 3,
-            "The last thread should process the total amt of work - max_capacity."
-        );
-        assert_eq!(last.start, 7, "The last thread should at 7.");
+
+        // Check that 0 uses every available core
+        builder.prepare_workload(0);
+        let available = std::thread::available_parallelism().unwrap().get();
+        assert_eq!(builder.thread_metadata.len(), available.min(10));
+    }
+
+    #[test]
+    fn msdf_config_forwarded_from_args() {
+        let args = Args::default()
+            .with_error_correction(ErrorCorrectionMode::Indiscriminate)
+            .with_distance_check(DistanceCheckMode::AlwaysCheckDistance)
+            .with_error_ratios(2.0, 1.5)
+            .with_overlap_support(false);
+
+        let config = args.get_msdf_config();
+        assert!(
+            !config.overlap_support,
+            "Overlap support was not forwarded."
+        );
+
+        let error_config = config.error_correction_config;
+        assert!(
+            error_config.mode == msdf::ErrorCorrectionMode::Indiscriminate,
+            "Error correction mode was not forwarded."
+        );
+        assert!(
+            error_config.distance_check_mode == msdf::DistanceCheckMode::AlwaysCheckDistance,
+            "Distance check mode was not forwarded."
+        );
+        assert_eq!(error_config.min_deviation_ratio, 2.0);
+        assert_eq!(error_config.min_improve_ratio, 1.5);
+    }
+
+    #[test]
+    fn overrides_replace_args() {
+        let args = Args::default()
+            .with_color_type(ColorType::Simple)
+            .with_coloring_seed(7)
+            .with_angle(3.0)
+            .with_range(4.0)
+            .with_uniform_scale(1.0 / 32.0);
+
+        let overrides = GlyphOverrides::from_slice(&[
+            GlyphOverride::from_range('A', 'Z')
+                .with_range(8.0)
+                .with_uniform_scale(1.0 / 16.0),
+            GlyphOverride::from_char('A').with_coloring_seed(42),
+            GlyphOverride::from_char('B')
+                .with_color_type(ColorType::InkTrap)
+                .with_angle(90.0)
+                .with_range(2.0),
+        ]);
+
+        let a = overrides.settings_for('A', &args);
+        assert_eq!(a.coloring_seed, 42, "The seed was not overridden.");
+        assert_eq!(a.color_type, ColorType::Simple);
+        assert_eq!(a.radians, args.get_radians());
+        assert_eq!(
+            a.range, 8.0,
+            "The range should come from the range override."
+        );
+        assert_eq!(a.uniform_scale, 1.0 / 16.0);
+
+        let b = overrides.settings_for('B', &args);
+        assert_eq!(b.coloring_seed, 7, "The seed should fall back to Args.");
+        assert_eq!(
+            b.color_type,
+            ColorType::InkTrap,
+            "The color type was not overridden."
+        );
+        assert_eq!(b.radians, 90.0_f32.to_radians() as f64);
+        assert_eq!(b.range, 2.0, "A single override should win over a range.");
+        assert_eq!(b.uniform_scale, 1.0 / 16.0);
+
+        let c = overrides.settings_for('c', &args);
+        assert_eq!(c.coloring_seed, 7);
+        assert_eq!(c.color_type, ColorType::Simple);
+        assert_eq!(c.range, 4.0);
+        assert_eq!(c.uniform_scale, 1.0 / 32.0);
+    }
+
+    #[test]
+    fn overridden_scale_grows_glyph_in_atlas() {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10)
+            .with_uv_space(UVSpace::Default);
+
+        let p = OsStr::new(FONT_PATH);
+        let overrides = GlyphOverrides::from_slice(&[
+            GlyphOverride::from_char('B').with_uniform_scale(1.0 / 16.0)
+        ]);
+        let options = BuildOptions::default().with_overrides(overrides);
+        let builder = Builder::from_font_path_with_options(p, "AB".to_string(), &args, options);
+
+        for glyph in &builder.glyph_buffer {
+            let scale = if glyph.unicode == 'B' as i32 {
+                1.0 / 16.0
+            } else {
+                1.0 / 32.0
+            };
+            let (min_x, _, max_x, _) = glyph.uvs();
+            let (metrics_x, _) = glyph.metrics();
+
+            let texels = (max_x - min_x) * builder.atlas_dimensions.0 as f32;
+            assert!(
+                (texels - metrics_x * scale).abs() <= 1.0,
+                "Glyph {} should take {} texels in the atlas, but took {}",
+                glyph.unicode,
+                metrics_x * scale,
+                texels
+            );
+        }
     }
 
     #[test]
@@ -374,4 +927,654 @@ mod tests {
             remove_file_and_wait(atlas_path);
         }
     }
+
+    #[test]
+    fn generates_16_bit_atlas() {
+        unsafe {
+            let args = Args::default()
+                .with_uniform_scale(1.0 / 32.0)
+                .with_range(640.0)
+                .with_padding(10)
+                .with_uv_space(UVSpace::OneMinusV)
+                .with_precision(AtlasPrecision::Bits16);
+
+            let atlas_path = Path::new("atlas3.png");
+            let (_, actual_img) = common_setup(DEFAULT_CHAR_SET, atlas_path, args, true);
+
+            assert_eq!(
+                actual_img.unwrap().color(),
+                ImageColorType::Rgba16,
+                "The atlas was not written with 16 bits per channel."
+            );
+            remove_file_and_wait(atlas_path);
+        }
+    }
+
+    #[test]
+    fn generates_float_atlas() {
+        unsafe {
+            let args = Args::default()
+                .with_uniform_scale(1.0 / 32.0)
+                .with_range(640.0)
+                .with_padding(10)
+                .with_uv_space(UVSpace::OneMinusV)
+                .with_precision(AtlasPrecision::Float32);
+
+            let atlas_path = Path::new("atlas4.exr");
+            let (_, actual_img) = common_setup(DEFAULT_CHAR_SET, atlas_path, args, true);
+
+            assert_eq!(
+                actual_img.unwrap().color(),
+                ImageColorType::Rgba32F,
+                "The atlas was not written with 32 bit floats."
+            );
+            remove_file_and_wait(atlas_path);
+        }
+    }
+
+    #[test]
+    fn composes_without_prepared_workload() {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10);
+        let mut builder =
+            Builder::from_font_path(OsStr::new(FONT_PATH), DEFAULT_CHAR_SET.to_string(), &args);
+
+        let unprepared = builder.package_font_asset();
+        assert!(
+            unprepared.atlas.data.iter().any(|channel| *channel != 0),
+            "The glyphs should be generated without a prepared workload."
+        );
+
+        let prepared = builder.prepare_workload(2).package_font_asset();
+        assert!(unprepared.atlas.data == prepared.atlas.data);
+    }
+
+    #[test]
+    fn skips_unsupported_precision() {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10);
+
+        let font_path = OsStr::new(FONT_PATH);
+        for (precision, atlas_path) in [
+            (AtlasPrecision::Float32, "atlas_float.png"),
+            (AtlasPrecision::Bits16, "atlas_16_bit.jpg"),
+            (AtlasPrecision::Bits8, "atlas_8_bit.unknown"),
+        ] {
+            let mut builder = Builder::from_font_path(
+                font_path,
+                "abc".to_string(),
+                &args.with_precision(precision),
+            );
+            builder
+                .prepare_workload(args.thread_count as usize)
+                .build_atlas(Path::new(atlas_path));
+            assert!(
+                !Path::new(atlas_path).exists(),
+                "{:?} should not be written to {}.",
+                precision,
+                atlas_path
+            );
+        }
+    }
+
+    #[test]
+    fn generates_texture_containers() {
+        unsafe {
+            let args = Args::default()
+                .with_uniform_scale(1.0 / 32.0)
+                .with_range(640.0)
+                .with_padding(10)
+                .with_uv_space(UVSpace::OneMinusV)
+                .with_block_alignment(true)
+                .with_texture_format(TextureFormat::Rg8);
+
+            let ktx2_path = Path::new("atlas5.ktx2");
+            let (font_data, _) = common_setup(DEFAULT_CHAR_SET, ktx2_path, args, false);
+            let bytes = read(ktx2_path).unwrap();
+            let read_u32 =
+                |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+            assert_eq!(&bytes[1..4], b"KTX", "The KTX2 identifier was not written.");
+            assert_eq!(read_u32(12), 16, "The vkFormat should be R8G8_UNORM.");
+            let (width, height) = (read_u32(20), read_u32(24));
+            assert!(
+                width % 4 == 0 && height % 4 == 0,
+                "The atlas should be aligned to 4x4 blocks, but was {}x{}",
+                width,
+                height
+            );
+
+            let level_offset = u64::from_le_bytes(bytes[80..88].try_into().unwrap()) as usize;
+            assert_eq!(
+                bytes.len() - level_offset,
+                (width * height * 2) as usize,
+                "The level data should store 2 bytes per pixel."
+            );
+            drop(font_data);
+            remove_file_and_wait(ktx2_path);
+
+            let args = Args::default()
+                .with_uniform_scale(1.0 / 32.0)
+                .with_range(640.0)
+                .with_padding(10)
+                .with_uv_space(UVSpace::OneMinusV)
+                .with_texture_format(TextureFormat::Rgba16F);
+
+            let dds_path = Path::new("atlas6.dds");
+            let _ = common_setup(DEFAULT_CHAR_SET, dds_path, args, false);
+            let bytes = read(dds_path).unwrap();
+
+            assert_eq!(&bytes[0..4], b"DDS ", "The DDS magic was not written.");
+            assert_eq!(&bytes[84..88], b"DX10", "The DX10 header was not flagged.");
+            let dxgi_format = u32::from_le_bytes(bytes[128..132].try_into().unwrap());
+            assert_eq!(dxgi_format, 10, "The format should be R16G16B16A16_FLOAT.");
+            remove_file_and_wait(dds_path);
+
+            // 8 bit containers should round each channel like 8 bit images.
+            let args = args.with_texture_format(TextureFormat::Rgba8);
+            let (png_path, ktx2_path) = (Path::new("atlas7.png"), Path::new("atlas7.ktx2"));
+            let mut builder =
+                Builder::from_font_path(OsStr::new(FONT_PATH), DEFAULT_CHAR_SET.to_string(), &args);
+            builder
+                .prepare_workload(args.thread_count as usize)
+                .build_atlas(png_path)
+                .build_atlas(ktx2_path);
+
+            let png = image::open(png_path).unwrap().into_rgba8().into_raw();
+            let bytes = read(ktx2_path).unwrap();
+            assert_eq!(
+                &bytes[bytes.len() - png.len()..],
+                &png[..],
+                "The KTX2 and PNG atlases should store the same pixels."
+            );
+            remove_file_and_wait(png_path);
+            remove_file_and_wait(ktx2_path);
+        }
+    }
+    #[test]
+    fn generates_font_asset() {
+        unsafe {
+            let args = Args::default()
+                .with_uniform_scale(1.0 / 32.0)
+                .with_range(640.0)
+                .with_padding(10)
+                .with_uv_space(UVSpace::OneMinusV);
+
+            let asset_path = Path::new("atlas7.msdffont");
+            let (font_data, _) = common_setup(DEFAULT_CHAR_SET, asset_path, args, false);
+            let asset = FontAsset::read(asset_path).expect("Failed to read the font asset.");
+
+            assert_eq!(asset.line_height, font_data.line_height);
+            assert_eq!(asset.units_per_em, font_data.units_per_em);
+            assert_eq!(asset.ascender, font_data.ascender);
+            assert_eq!(asset.descender, font_data.descender);
+            assert_eq!(asset.settings.range, 640.0, "The settings were not stored.");
+            assert_eq!(
+                asset.settings,
+                GenerationSettings::from_args(&args),
+                "The field settings were not stored."
+            );
+
+            let glyph_data = *font_data.glyph_data;
+            assert_eq!(asset.glyphs.len() as i32, glyph_data.element_len());
+            for (i, glyph) in asset.glyphs.iter().enumerate() {
+                assert!(
+                    *glyph == glyph_data.element_at::<GlyphData>(i),
+                    "Glyph {} did not match the packaged font data.",
+                    glyph.unicode
+                );
+            }
+
+            assert!(
+                asset.get_kerning('A' as i32, 'V' as i32) < 0.0,
+                "A and V should be kerned closer together."
+            );
+
+            let atlas = &asset.atlas;
+            assert_eq!(
+                atlas.data.len(),
+                (atlas.width * atlas.height * 4) as usize,
+                "The atlas should store 4 bytes per pixel."
+            );
+
+            let bytes = read(asset_path).unwrap();
+            assert!(
+                FontAsset::from_bytes(&bytes[..bytes.len() - 1]).is_err(),
+                "A truncated font asset should not be read."
+            );
+
+            let mut other_version = bytes.clone();
+            other_version[8..12].copy_from_slice(&(FONT_ASSET_VERSION + 1).to_le_bytes());
+            assert!(
+                FontAsset::from_bytes(&other_version).is_err(),
+                "An asset written with another version should not be read."
+            );
+            remove_file_and_wait(asset_path);
+        }
+    }
+    #[test]
+    fn validates_generated_atlas() {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10)
+            .with_uv_space(UVSpace::OneMinusV);
+
+        let atlas_path = Path::new("atlas8.png");
+        let font_path = OsStr::new(FONT_PATH);
+        let mut builder = Builder::from_font_path(font_path, DEFAULT_CHAR_SET.to_string(), &args);
+        builder
+            .prepare_workload(args.thread_count as usize)
+            .build_atlas(atlas_path);
+        let mut asset = builder.package_font_asset();
+
+        let report = validate_font_asset(&asset);
+        assert!(
+            report.is_valid(),
+            "The generated asset was invalid: {}",
+            report
+        );
+        assert_eq!(report.glyph_count, 53);
+
+        let report = validate_atlas_image(&asset, atlas_path).unwrap();
+        assert!(
+            report.is_valid(),
+            "The exported atlas was invalid: {}",
+            report
+        );
+
+        // Both entry points should report an asset without a hash the same way.
+        let atlas_hash = asset.atlas_hash.take();
+        let missing_hash = [ValidationIssue::MissingAtlasHash];
+        assert_eq!(validate_font_asset(&asset).issues, missing_hash);
+        assert_eq!(
+            validate_atlas_image(&asset, atlas_path).unwrap().issues,
+            missing_hash
+        );
+        asset.atlas_hash = atlas_hash;
+        remove_file_and_wait(atlas_path);
+
+        // Move B on top of A and push C outside of the atlas.
+        let index = |c: char| {
+            asset
+                .glyphs
+                .iter()
+                .position(|glyph| glyph.unicode == c as i32)
+                .unwrap()
+        };
+        let (a, b, c) = (index('A'), index('B'), index('C'));
+        let glyph_a = asset.glyphs[a];
+        asset.glyphs[b] = GlyphData::from_char('B').with_layout(
+            glyph_a.metrics(),
+            glyph_a.bearings(),
+            glyph_a.uvs(),
+        );
+        asset.glyphs[c] =
+            GlyphData::from_char('C').with_layout((0.0, 0.0), (0.0, 0.0), (0.5, 0.5, 1.5, 0.75));
+        asset.atlas.data[0] ^= 0xFF;
+
+        let report = validate_font_asset(&asset);
+        assert!(!report.is_valid());
+        assert!(
+            report.issues.iter().any(|issue| match issue {
+                ValidationIssue::OverlappingGlyphs { first, second } => {
+                    first + second == 'A' as i32 + 'B' as i32
+                }
+                _ => false,
+            }),
+            "A and B should overlap: {}",
+            report
+        );
+        assert!(report.issues.contains(&ValidationIssue::UvOutOfBounds {
+            unicode: 'C' as i32
+        }));
+        assert!(
+            report
+                .issues
+                .iter()
+                .any(|issue| matches!(issue, ValidationIssue::AtlasHashMismatch { .. })),
+            "The modified pixels should not match the stored hash."
+        );
+    }
+    #[test]
+    fn renders_preview() {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10)
+            .with_uv_space(UVSpace::OneMinusV);
+
+        let font_path = OsStr::new(FONT_PATH);
+        let mut builder = Builder::from_font_path(font_path, DEFAULT_CHAR_SET.to_string(), &args);
+        builder.prepare_workload(args.thread_count as usize);
+        let font_data = builder.package_font_data();
+        let asset = builder.package_font_asset();
+
+        let preview_args = PreviewArgs {
+            font_size: 64.0,
+            screen_px_range: 4.0,
+            uv_space: args.uv_space,
+        };
+        let single_line = render_preview(&font_data, &asset.atlas, "Hello", &preview_args);
+        let two_lines = render_preview(&font_data, &asset.atlas, "Hello\nWorld", &preview_args);
+
+        assert!(
+            two_lines.height() > single_line.height(),
+            "A newline should add another line to the preview."
+        );
+        assert_eq!(
+            single_line.get_pixel(0, 0)[0],
+            0,
+            "The margin should not be covered."
+        );
+
+        let covered = single_line.pixels().filter(|pixel| pixel[0] == 255).count();
+        let total = (single_line.width() * single_line.height()) as usize;
+        assert!(
+            covered > 0 && covered < total / 2,
+            "The text should cover some, but not most of the preview. Covered {} of {} pixels.",
+            covered,
+            total
+        );
+    }
+
+    #[test]
+    fn reports_progress() {
+        extern "C" fn record(
+            user_data: *mut c_void,
+            phase: ProgressPhase,
+            completed: u32,
+            total: u32,
+        ) {
+            let reports = unsafe { &*(user_data as *const Mutex<Vec<(ProgressPhase, u32, u32)>>) };
+            reports.lock().unwrap().push((phase, completed, total));
+        }
+
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10);
+        let reports: Mutex<Vec<(ProgressPhase, u32, u32)>> = Mutex::new(Vec::new());
+        let progress = ProgressReporter::new(Some(record), &reports as *const _ as *mut c_void);
+
+        Builder::from_font_path_with_options(
+            OsStr::new(FONT_PATH),
+            "ABCD".to_string(),
+            &args,
+            BuildOptions::default().with_progress(progress),
+        )
+        .prepare_workload(2)
+        .package_font_asset();
+
+        let reports = reports.into_inner().unwrap();
+        for phase in [
+            ProgressPhase::Measuring,
+            ProgressPhase::Generating,
+            ProgressPhase::Copying,
+        ] {
+            let phase_reports: Vec<&(ProgressPhase, u32, u32)> =
+                reports.iter().filter(|report| report.0 == phase).collect();
+            assert_eq!(
+                phase_reports.first().map(|report| report.1),
+                Some(0),
+                "{:?} should start at 0.",
+                phase
+            );
+            assert!(
+                phase_reports
+                    .iter()
+                    .any(|report| report.1 == 4 && report.2 == 4),
+                "{:?} should complete every glyph.",
+                phase
+            );
+        }
+
+        let generating = reports
+            .iter()
+            .filter(|report| report.0 == ProgressPhase::Generating)
+            .count();
+        assert_eq!(generating, 5, "Each glyph should be reported once.");
+    }
+
+    #[test]
+    fn adds_glyphs_to_existing_atlas() {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10)
+            .with_uv_space(UVSpace::OneMinusV);
+
+        let font_path = OsStr::new(FONT_PATH);
+        let mut builder = Builder::from_font_path(font_path, "abc".to_string(), &args);
+        builder.prepare_workload(args.thread_count as usize);
+        let mut asset = builder.package_font_asset();
+
+        let center = |asset: &FontAsset, unicode: char| {
+            let glyph = asset.get_glyph(unicode as i32).unwrap();
+            let (x_min, y_min, x_max, y_max) = asset.settings.uv_space.to_texture_rect(glyph.uvs());
+            let x = (x_min + x_max) * 0.5 * asset.atlas.width as f32;
+            let y = (y_min + y_max) * 0.5 * asset.atlas.height as f32;
+            asset.atlas.get_pixel(x as u32, y as u32)
+        };
+        let original_pixel = center(&asset, 'a');
+
+        let overrides = GlyphOverrides::from_slice(&[
+            GlyphOverride::from_char('d').with_uniform_scale(1.0 / 16.0)
+        ]);
+        let update = add_glyphs(&mut asset, font_path, "cd", &args, &overrides).unwrap();
+        assert_eq!(update.added, vec!['d' as i32], "c was already generated.");
+
+        let d = asset.get_glyph('d' as i32).unwrap();
+        let (min_x, _, max_x, _) = d.uvs();
+        let texels = (max_x - min_x) * asset.atlas.width as f32;
+        assert!(
+            (texels - d.metrics().0 / 16.0).abs() <= 1.0,
+            "The override should scale the new glyph."
+        );
+        assert!(
+            validate_font_asset(&asset).is_valid(),
+            "{}",
+            validate_font_asset(&asset)
+        );
+
+        let update = add_glyphs(
+            &mut asset,
+            font_path,
+            DEFAULT_CHAR_SET,
+            &args,
+            &GlyphOverrides::default(),
+        )
+        .unwrap();
+        assert!(update.resized, "The atlas should grow to fit the alphabet.");
+        assert_eq!(asset.glyphs.len(), 53);
+        assert_eq!(
+            update.moved.len(),
+            4,
+            "The uvs of the existing glyphs should change with the atlas height."
+        );
+        for moved in &update.moved {
+            let glyph = asset.get_glyph(moved.unicode).unwrap();
+            assert_eq!(glyph.uvs(), moved.new_uvs);
+        }
+        assert_eq!(
+            center(&asset, 'a'),
+            original_pixel,
+            "The existing glyphs should not be regenerated."
+        );
+
+        let report = validate_font_asset(&asset);
+        assert!(report.is_valid(), "{}", report);
+        assert!(
+            asset.get_kerning('A' as i32, 'V' as i32) < 0.0,
+            "The kerning should include the new glyphs."
+        );
+
+        let overrides = GlyphOverrides::default();
+        for mismatched in [
+            args.with_range(320.0),
+            args.with_fill_rule(FillRule::NonZero),
+            args.with_shape_preprocessing(true),
+            args.with_error_correction(ErrorCorrectionMode::Disabled),
+            args.with_error_ratios(1.5, 1.5),
+        ] {
+            assert!(add_glyphs(&mut asset, font_path, "!", &mismatched, &overrides).is_err());
+        }
+    }
+
+    #[test]
+    fn adds_glyphs_below_shorter_glyphs() {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10);
+
+        let font_path = OsStr::new(FONT_PATH);
+        let mut builder =
+            Builder::from_font_path(font_path, "abcdefghijklmnopqrstuvwxyz".to_string(), &args);
+        builder.prepare_workload(args.thread_count as usize);
+        let mut asset = builder.package_font_asset();
+        let dimensions = (asset.atlas.width, asset.atlas.height);
+
+        // The punctuation only fits in the space left below the shorter letters.
+        let update = add_glyphs(
+            &mut asset,
+            font_path,
+            ".,-'`:;_~",
+            &args,
+            &GlyphOverrides::default(),
+        )
+        .unwrap();
+        assert!(!update.resized && update.moved.is_empty());
+        assert_eq!((asset.atlas.width, asset.atlas.height), dimensions);
+        assert!(
+            validate_font_asset(&asset).is_valid(),
+            "{}",
+            validate_font_asset(&asset)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_font_bytes() {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10);
+        let font = std::fs::read(FONT_PATH).unwrap();
+
+        assert!(Builder::from_font_bytes(&font[..font.len() / 8], "abc", &args).is_err());
+        assert!(Builder::from_font_bytes(b"not a font", "abc", &args).is_err());
+        assert!(Builder::from_font_bytes(&font, "", &args).is_err());
+        assert!(
+            Builder::from_font_bytes(&font, "abc", &args.with_uniform_scale(f32::NAN)).is_err()
+        );
+        assert!(Builder::from_font_bytes(&font, "abc", &args.with_range(0.0)).is_err());
+        assert!(Builder::from_font_bytes(&font, "abc", &args.with_max_atlas(0)).is_err());
+        assert!(
+            Builder::from_font_bytes(&font, "abc", &args.with_uniform_scale(1000.0)).is_err(),
+            "Glyphs larger than any atlas should be rejected."
+        );
+
+        let mut builder = Builder::from_font_bytes(&font, "abc", &args).unwrap();
+        builder.prepare_workload(args.thread_count as usize);
+        let asset = builder.package_font_asset();
+        assert_eq!(asset.glyphs.len(), 3);
+    }
+
+    #[test]
+    fn loads_glyphs_without_side_bearings() {
+        let mut font = std::fs::read(FONT_PATH).unwrap();
+        let table_record = |font: &[u8], tag: &[u8]| {
+            let table_count = u16::from_be_bytes([font[4], font[5]]) as usize;
+            (0..table_count)
+                .map(|i| 12 + i * 16)
+                .find(|record| &font[*record..*record + 4] == tag)
+                .unwrap()
+        };
+
+        // Keeps a single metric and drops the side bearings which should follow it, so every
+        // glyph still has an advance but no side bearing.
+        let hhea = table_record(&font, b"hhea");
+        let hhea = u32::from_be_bytes(font[hhea + 8..hhea + 12].try_into().unwrap()) as usize;
+        font[hhea + 34..hhea + 36].copy_from_slice(&1u16.to_be_bytes());
+        let hmtx = table_record(&font, b"hmtx");
+        font[hmtx + 12..hmtx + 16].copy_from_slice(&4u32.to_be_bytes());
+
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0);
+        let builder = Builder::from_font_bytes(&font, "abc", &args).unwrap();
+        let font_data = builder.package_font_data();
+        assert_eq!(font_data.glyphs().len(), 3);
+        assert!(font_data
+            .glyphs()
+            .iter()
+            .all(|glyph| glyph.bearings().0 == 0.0));
+    }
+    #[test]
+    #[cfg(feature = "shaping")]
+    fn generates_shaped_glyphs() {
+        use crate::msdf_impl::{layout::LayoutArgs, shaping::layout_shaped_runs};
+
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10);
+
+        let font_path = OsStr::new(FONT_PATH);
+        let (builder, runs) = Builder::from_font_path_with_shaping(
+            font_path,
+            String::new(),
+            "AVA\nfit",
+            &args,
+            BuildOptions::default(),
+        );
+        assert_eq!(runs.len(), 2, "Each line should be shaped as a run.");
+
+        let font_data = builder.package_font_data();
+        for shaped in runs.iter().flat_map(|run| &run.glyphs) {
+            assert!(
+                font_data.get_glyph_by_id(shaped.glyph_id).is_some(),
+                "Glyph {} was shaped but not generated.",
+                shaped.glyph_id
+            );
+        }
+        assert!(
+            font_data.get_glyph('V' as i32).is_some(),
+            "Glyphs mapped to a unicode should keep it."
+        );
+
+        let (a, v) = (runs[0].glyphs[0], runs[0].glyphs[1]);
+        let advance = font_data.get_glyph_by_id(a.glyph_id).unwrap().advance;
+        assert!(
+            (a.x_advance as f32) < advance,
+            "The shaper should kern A and V closer together."
+        );
+        assert!(v.cluster == 1 && runs[1].glyphs[0].cluster == 4);
+
+        let quads = layout_shaped_runs(&font_data, &runs, &LayoutArgs::default());
+        let below_first_line = quads
+            .iter()
+            .filter(|quad| quad.y_min > quads[0].y_max)
+            .count();
+        assert!(
+            below_first_line > 0,
+            "The second run should be laid out below the first."
+        );
+
+        let malformed_path = std::env::temp_dir().join("msdf-atlas-malformed.ttf");
+        std::fs::write(&malformed_path, b"not a font").unwrap();
+        let (builder, runs) = Builder::from_font_path_with_shaping(
+            malformed_path.as_os_str(),
+            "A".to_string(),
+            "A",
+            &args,
+            BuildOptions::default(),
+        );
+        assert!(builder.glyph_buffer.is_empty() && runs.is_empty());
+        remove_file_and_wait(&malformed_path);
+    }
 }
@@ -1,7 +1,12 @@
-use crate::msdf_impl::enums::{ColorType, UVSpace};
+use crate::msdf_impl::enums::{
+    AtlasPrecision, ColorType, DistanceCheckMode, ErrorCorrectionMode, FillRule, TextureFormat,
+    UVSpace,
+};
 use mint::Vector2;
+use msdf::{ErrorCorrectionConfig, MSDFConfig};
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct Args {
     /// Stores the angle in degrees for coloring the shape
     /// Scale of the generated glyphs. Recommended to use powers of 1 / 2^n.
@@ -13,13 +18,41 @@ pub struct Args {
     pub color_type: ColorType,
     pub degrees: f32,
     pub scale_texture_to_po2: bool,
-    pub thread_count: u32
+    /// The number of threads the glyphs are copied into the atlas with, where 0 uses every
+    /// available core.
+    pub thread_count: u32,
+    pub error_correction_mode: ErrorCorrectionMode,
+    pub distance_check_mode: DistanceCheckMode,
+    /// The minimum ratio between the actual and maximum expected distance delta to be considered
+    /// an error.
+    pub min_deviation_ratio: f32,
+    /// The minimum ratio between the pre-correction and post-correction distance error for a
+    /// correction to be applied.
+    pub min_improve_ratio: f32,
+    /// Should msdfgen account for overlapping contours? Required for most variable fonts.
+    pub overlap_support: bool,
+    /// The fill rule used to correct the sign of each glyph after generation.
+    pub fill_rule: FillRule,
+    /// Should degenerate segments be removed, overlapping contours be merged and contours be
+    /// reoriented before coloring?
+    pub preprocess_shapes: bool,
+    /// The seed for the edge coloring algorithm. Changing the seed changes which channels each
+    /// edge is assigned to, which can resolve artifacts on a glyph.
+    pub coloring_seed: u64,
+    /// The number of bits each channel of the atlas is stored with.
+    pub precision: AtlasPrecision,
+    /// The pixel layout used when the atlas path ends with .ktx2 or .dds.
+    pub texture_format: TextureFormat,
+    /// Should the atlas dimensions be rounded up to a multiple of 4, so that the atlas can be
+    /// block compressed after it is written? The texture format picks the channels, such as R8
+    /// for BC4 or Rgba8 for BC7.
+    pub block_align: bool,
 }
 
 impl Args {
     /// Generates arguments with default settings with angle of
     /// 3 degrees and no adjustments to the scale.
-    #[allow(dead_code)]
+    #[allow(dead_code, clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             uniform_scale: 1.0 / 64.0,
@@ -30,7 +63,18 @@ impl Args {
             degrees: 3.0,
             color_type: ColorType::Simple,
             scale_texture_to_po2: false,
-            thread_count: 8
+            thread_count: 8,
+            error_correction_mode: ErrorCorrectionMode::EdgePriority,
+            distance_check_mode: DistanceCheckMode::CheckDistanceAtEdge,
+            min_deviation_ratio: 10.0 / 9.0,
+            min_improve_ratio: 10.0 / 9.0,
+            overlap_support: true,
+            fill_rule: FillRule::Disabled,
+            preprocess_shapes: false,
+            coloring_seed: 0,
+            precision: AtlasPrecision::Bits8,
+            texture_format: TextureFormat::Rgba8,
+            block_align: false,
         }
     }
 
@@ -45,6 +89,16 @@ impl Args {
         self
     }
 
+    /// Builder to adjust the number of threads the glyphs are copied into the atlas with.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_count` - The number of threads, where 0 uses every available core
+    pub fn with_thread_count(mut self, thread_count: u32) -> Args {
+        self.thread_count = thread_count;
+        self
+    }
+
     /// Defines the UV Space for the generate glyphs
     ///
     /// # Arguments
@@ -108,6 +162,126 @@ impl Args {
         self
     }
 
+    /// Builder to adjust the seed for the edge coloring algorithm. The same seed always
+    /// produces the same coloring.
+    ///
+    /// # Arguments
+    ///
+    /// * `coloring_seed` - The seed for the coloring algorithm.
+    #[allow(dead_code)]
+    pub fn with_coloring_seed(mut self, coloring_seed: u64) -> Args {
+        self.coloring_seed = coloring_seed;
+        self
+    }
+
+    /// Determines how msdfgen resolves artifacts after generating each glyph.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The error correction mode to use.
+    #[allow(dead_code)]
+    pub fn with_error_correction(mut self, mode: ErrorCorrectionMode) -> Args {
+        self.error_correction_mode = mode;
+        self
+    }
+
+    /// Determines when msdfgen computes the exact distance to confirm an artifact.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The distance check mode to use.
+    #[allow(dead_code)]
+    pub fn with_distance_check(mut self, mode: DistanceCheckMode) -> Args {
+        self.distance_check_mode = mode;
+        self
+    }
+
+    /// Builder to adjust the error correction thresholds.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_deviation_ratio` - The minimum deviation for a texel to be considered an error.
+    /// * `min_improve_ratio` - The minimum improvement for a correction to be applied.
+    #[allow(dead_code)]
+    pub fn with_error_ratios(mut self, min_deviation_ratio: f32, min_improve_ratio: f32) -> Args {
+        self.min_deviation_ratio = min_deviation_ratio;
+        self.min_improve_ratio = min_improve_ratio;
+        self
+    }
+
+    /// Should msdfgen support overlapping contours?
+    ///
+    /// # Arguments
+    ///
+    /// * `overlap_support` - bool
+    #[allow(dead_code)]
+    pub fn with_overlap_support(mut self, overlap_support: bool) -> Args {
+        self.overlap_support = overlap_support;
+        self
+    }
+
+    /// Runs a scanline pass over each glyph, inverting pixels whose sign disagrees with the fill
+    /// rule. Use this for fonts with contours that have the wrong winding.
+    ///
+    /// # Arguments
+    ///
+    /// * `fill_rule` - The fill rule to correct the sign with, or Disabled to skip the pass.
+    #[allow(dead_code)]
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Args {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    /// Should each glyph's outline be cleaned up before its edges are colored? This removes
+    /// degenerate segments, merges self intersecting and overlapping contours using the fill
+    /// rule, and orients contours consistently, which fixes fonts with reversed or overlapping
+    /// contours.
+    ///
+    /// # Arguments
+    ///
+    /// * `preprocess` - bool
+    #[allow(dead_code)]
+    pub fn with_shape_preprocessing(mut self, preprocess: bool) -> Args {
+        self.preprocess_shapes = preprocess;
+        self
+    }
+
+    /// Builder to adjust the precision of the atlas. The extension of the atlas path must
+    /// support the precision, e.g. .png for 8 or 16 bits and .exr for 32 bit floats.
+    ///
+    /// # Arguments
+    ///
+    /// * `precision` - The number of bits each channel is stored with.
+    #[allow(dead_code)]
+    pub fn with_precision(mut self, precision: AtlasPrecision) -> Args {
+        self.precision = precision;
+        self
+    }
+
+    /// Builder to adjust the pixel layout of KTX2 and DDS atlases. The precision is ignored for
+    /// these containers.
+    ///
+    /// # Arguments
+    ///
+    /// * `texture_format` - The layout of each pixel in the container.
+    #[allow(dead_code)]
+    pub fn with_texture_format(mut self, texture_format: TextureFormat) -> Args {
+        self.texture_format = texture_format;
+        self
+    }
+
+    /// Should the generator round the atlas dimensions up to a multiple of 4, which block
+    /// compressed formats like BC4 and BC7 require?
+    ///
+    /// # Arguments
+    ///
+    /// * `block_align` - bool
+    #[allow(dead_code)]
+    pub fn with_block_alignment(mut self, block_align: bool) -> Args {
+        self.block_align = block_align;
+        self
+    }
+
     /// Builder to just the max atlas width.
     ///
     /// # Arguments
@@ -153,4 +327,17 @@ impl Args {
     pub fn get_radians(&self) -> f64 {
         self.degrees.to_radians() as f64
     }
+
+    /// Returns the msdfgen configuration used when generating each glyph.
+    pub fn get_msdf_config(&self) -> MSDFConfig {
+        MSDFConfig {
+            overlap_support: self.overlap_support,
+            error_correction_config: ErrorCorrectionConfig {
+                mode: self.error_correction_mode.into(),
+                distance_check_mode: self.distance_check_mode.into(),
+                min_deviation_ratio: self.min_deviation_ratio as f64,
+                min_improve_ratio: self.min_improve_ratio as f64,
+            },
+        }
+    }
 }
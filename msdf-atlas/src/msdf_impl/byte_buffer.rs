@@ -81,6 +81,16 @@ impl ByteBuffer {
         }
     }
 
+    /// Reinterprets the buffer as a slice of T.
+    pub fn as_slice<T>(&self) -> &[T]
+        where T: Sized + Copy {
+        if self.ptr.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.ptr as *const T, self.element_len() as usize) }
+        }
+    }
+
     pub fn destroy_into_vec(self) -> Vec<u8> {
         if self.ptr.is_null() {
             vec![]
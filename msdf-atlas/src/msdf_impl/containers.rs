@@ -0,0 +1,262 @@
+use super::enums::TextureFormat;
+use super::quantize_u8;
+use super::sampling::median;
+use half::f16;
+use std::fs::File;
+use std::io::{BufWriter, Result, Write};
+use std::path::Path;
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// The data format descriptor follows the KTX2 header, index and a single level index entry.
+const KTX2_DFD_OFFSET: u32 = 12 + 9 * 4 + 4 * 4 + 2 * 8 + 3 * 8;
+
+// Channel ids of the KHR_DF_MODEL_RGBSDA color model.
+const KHR_DF_CHANNEL_RED: u8 = 0;
+const KHR_DF_CHANNEL_GREEN: u8 = 1;
+const KHR_DF_CHANNEL_BLUE: u8 = 2;
+const KHR_DF_CHANNEL_ALPHA: u8 = 15;
+
+// Sample qualifiers for float channels.
+const KHR_DF_SAMPLE_DATATYPE_SIGNED: u8 = 0x40;
+const KHR_DF_SAMPLE_DATATYPE_FLOAT: u8 = 0x80;
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: u32 = 124;
+const DDS_PIXEL_FORMAT_SIZE: u32 = 32;
+// DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT
+const DDS_HEADER_FLAGS: u32 = 0x1 | 0x2 | 0x4 | 0x8 | 0x1000;
+const DDS_PIXEL_FORMAT_FOURCC: u32 = 0x4;
+const DDS_CAPS_TEXTURE: u32 = 0x1000;
+const DDS_DIMENSION_TEXTURE_2D: u32 = 3;
+
+/// Writes an atlas with the given format, width, height and pixels to a path.
+pub type ContainerWriter = fn(&Path, TextureFormat, u32, u32, &[[f32; 4]]) -> Result<()>;
+
+impl TextureFormat {
+    /// Returns the number of bytes a single pixel is stored with.
+    pub fn bytes_per_pixel(&self) -> u32 {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::Rg8 => 2,
+            TextureFormat::Rgba8 => 4,
+            TextureFormat::Rgba16F => 8,
+        }
+    }
+
+    /// Returns the number of bytes a single channel is stored with.
+    fn channel_size(&self) -> u32 {
+        match self {
+            TextureFormat::Rgba16F => 2,
+            _ => 1,
+        }
+    }
+
+    fn vk_format(&self) -> u32 {
+        match self {
+            TextureFormat::R8 => 9,
+            TextureFormat::Rg8 => 16,
+            TextureFormat::Rgba8 => 37,
+            TextureFormat::Rgba16F => 97,
+        }
+    }
+
+    fn dxgi_format(&self) -> u32 {
+        match self {
+            TextureFormat::R8 => 61,
+            TextureFormat::Rg8 => 49,
+            TextureFormat::Rgba8 => 28,
+            TextureFormat::Rgba16F => 10,
+        }
+    }
+
+    fn channels(&self) -> &'static [u8] {
+        match self {
+            TextureFormat::R8 => &[KHR_DF_CHANNEL_RED],
+            TextureFormat::Rg8 => &[KHR_DF_CHANNEL_RED, KHR_DF_CHANNEL_GREEN],
+            TextureFormat::Rgba8 | TextureFormat::Rgba16F => &[
+                KHR_DF_CHANNEL_RED,
+                KHR_DF_CHANNEL_GREEN,
+                KHR_DF_CHANNEL_BLUE,
+                KHR_DF_CHANNEL_ALPHA,
+            ],
+        }
+    }
+}
+
+/// Converts the generated pixels into the tightly packed layout of the texture format.
+///
+/// # Arguments
+///
+/// * `format` - The layout to convert the pixels into
+/// * `pixels` - The atlas pixels, as generated by msdfgen
+pub fn encode_pixels(format: TextureFormat, pixels: &[[f32; 4]]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(pixels.len() * format.bytes_per_pixel() as usize);
+    for pixel in pixels {
+        match format {
+            TextureFormat::R8 => bytes.push(quantize_u8(pixel[3])),
+            TextureFormat::Rg8 => {
+                bytes.push(quantize_u8(median(pixel[0], pixel[1], pixel[2])));
+                bytes.push(quantize_u8(pixel[3]));
+            }
+            TextureFormat::Rgba8 => bytes.extend(pixel.iter().map(|channel| quantize_u8(*channel))),
+            TextureFormat::Rgba16F => pixel.iter().for_each(|channel| {
+                bytes.extend_from_slice(&f16::from_f32(*channel).to_bits().to_le_bytes())
+            }),
+        }
+    }
+    bytes
+}
+
+/// Writes the atlas as a single level, uncompressed KTX2 texture.
+///
+/// # Arguments
+///
+/// * `path` - The path to write the texture to
+/// * `format` - The layout of the pixels in the texture
+/// * `width` - The width of the atlas
+/// * `height` - The height of the atlas
+/// * `pixels` - The atlas pixels, as generated by msdfgen
+pub fn write_ktx2(
+    path: &Path,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    pixels: &[[f32; 4]],
+) -> Result<()> {
+    let data = encode_pixels(format, pixels);
+    let dfd = ktx2_data_format_descriptor(format);
+
+    let dfd_offset = KTX2_DFD_OFFSET;
+    // Level data must be aligned to the least common multiple of the texel size and 4.
+    let alignment = match format.bytes_per_pixel() {
+        8 => 8,
+        _ => 4,
+    };
+    let level_offset = (dfd_offset + dfd.len() as u32).div_ceil(alignment) * alignment;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&KTX2_IDENTIFIER)?;
+    for value in [
+        format.vk_format(),
+        format.channel_size(),
+        width,
+        height,
+        0, // pixelDepth
+        0, // layerCount
+        1, // faceCount
+        1, // levelCount
+        0, // supercompressionScheme
+        dfd_offset,
+        dfd.len() as u32,
+        0, // kvdByteOffset
+        0, // kvdByteLength
+    ] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    for value in [
+        0, // sgdByteOffset
+        0, // sgdByteLength
+        level_offset as u64,
+        data.len() as u64,
+        data.len() as u64,
+    ] {
+        writer.write_all(&u64::to_le_bytes(value))?;
+    }
+
+    writer.write_all(&dfd)?;
+    let padding = level_offset as usize - (dfd_offset as usize + dfd.len());
+    writer.write_all(&vec![0; padding])?;
+    writer.write_all(&data)?;
+    writer.flush()
+}
+
+/// Builds the basic data format descriptor for an uncompressed, linear texture.
+fn ktx2_data_format_descriptor(format: TextureFormat) -> Vec<u8> {
+    let channels = format.channels();
+    let block_size = 24 + 16 * channels.len() as u32;
+    let channel_bits = format.channel_size() * 8;
+
+    let mut dfd = Vec::with_capacity(4 + block_size as usize);
+    dfd.extend_from_slice(&(4 + block_size).to_le_bytes());
+    // Khronos vendor id and basic descriptor type
+    dfd.extend_from_slice(&0_u32.to_le_bytes());
+    // Version 2 of the data format descriptor
+    dfd.extend_from_slice(&2_u16.to_le_bytes());
+    dfd.extend_from_slice(&(block_size as u16).to_le_bytes());
+    // RGBSDA color model, BT709 primaries, linear transfer and straight alpha
+    dfd.extend_from_slice(&[1, 1, 1, 0]);
+    // Texel block dimensions are stored minus one
+    dfd.extend_from_slice(&[0, 0, 0, 0]);
+    dfd.extend_from_slice(&[format.bytes_per_pixel() as u8, 0, 0, 0, 0, 0, 0, 0]);
+
+    for (i, channel) in channels.iter().enumerate() {
+        let (qualifiers, lower, upper) = match format {
+            TextureFormat::Rgba16F => (
+                KHR_DF_SAMPLE_DATATYPE_FLOAT | KHR_DF_SAMPLE_DATATYPE_SIGNED,
+                (-1.0_f32).to_bits(),
+                1.0_f32.to_bits(),
+            ),
+            _ => (0, 0, 255),
+        };
+
+        dfd.extend_from_slice(&((i as u32 * channel_bits) as u16).to_le_bytes());
+        dfd.push((channel_bits - 1) as u8);
+        dfd.push(channel | qualifiers);
+        dfd.extend_from_slice(&[0, 0, 0, 0]);
+        dfd.extend_from_slice(&lower.to_le_bytes());
+        dfd.extend_from_slice(&upper.to_le_bytes());
+    }
+    dfd
+}
+
+/// Writes the atlas as a single level, uncompressed DDS texture with a DX10 header.
+///
+/// # Arguments
+///
+/// * `path` - The path to write the texture to
+/// * `format` - The layout of the pixels in the texture
+/// * `width` - The width of the atlas
+/// * `height` - The height of the atlas
+/// * `pixels` - The atlas pixels, as generated by msdfgen
+pub fn write_dds(
+    path: &Path,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    pixels: &[[f32; 4]],
+) -> Result<()> {
+    let data = encode_pixels(format, pixels);
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(DDS_MAGIC)?;
+
+    let mut header = [0_u32; (DDS_HEADER_SIZE / 4) as usize];
+    header[0] = DDS_HEADER_SIZE;
+    header[1] = DDS_HEADER_FLAGS;
+    header[2] = height;
+    header[3] = width;
+    header[4] = width * format.bytes_per_pixel();
+    // dwDepth, dwMipMapCount and dwReserved1 are left as 0
+    header[18] = DDS_PIXEL_FORMAT_SIZE;
+    header[19] = DDS_PIXEL_FORMAT_FOURCC;
+    header[20] = u32::from_le_bytes(*b"DX10");
+    header[26] = DDS_CAPS_TEXTURE;
+
+    let dx10_header = [
+        format.dxgi_format(),
+        DDS_DIMENSION_TEXTURE_2D,
+        0, // miscFlag
+        1, // arraySize
+        0, // miscFlags2
+    ];
+
+    for value in header.iter().chain(dx10_header.iter()) {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.write_all(&data)?;
+    writer.flush()
+}
@@ -1,15 +1,132 @@
 use bitflags::bitflags;
+use image::ImageFormat;
+use msdf::{
+    DistanceCheckMode as MsdfDistanceCheckMode, ErrorCorrectionMode as MsdfErrorCorrectionMode,
+};
 use std::fmt::Display;
 
 const UV_SPACE_NAMES: [&str; 2] = ["1 - u", "1 - v"];
 
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorType {
     Simple,
     InkTrap,
     Distance,
 }
 
+/// Mirrors msdfgen's error correction modes, which determine how artifacts are resolved after
+/// the distance field is generated.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCorrectionMode {
+    /// Skips error correction entirely.
+    Disabled,
+    /// Corrects all discontinuities regardless of whether they are edges or artifacts.
+    Indiscriminate,
+    /// Corrects artifacts while attempting to preserve edges and corners.
+    EdgePriority,
+    /// Only corrects artifacts at the edges.
+    EdgeOnly,
+}
+
+impl From<ErrorCorrectionMode> for MsdfErrorCorrectionMode {
+    fn from(mode: ErrorCorrectionMode) -> Self {
+        match mode {
+            ErrorCorrectionMode::Disabled => MsdfErrorCorrectionMode::Disabled,
+            ErrorCorrectionMode::Indiscriminate => MsdfErrorCorrectionMode::Indiscriminate,
+            ErrorCorrectionMode::EdgePriority => MsdfErrorCorrectionMode::EdgePriority,
+            ErrorCorrectionMode::EdgeOnly => MsdfErrorCorrectionMode::EdgeOnly,
+        }
+    }
+}
+
+/// Mirrors msdfgen's distance check modes, which determine whether the exact shape distance is
+/// computed to confirm an artifact before correcting it.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceCheckMode {
+    /// Never computes the exact distance, which is the fastest option.
+    DoNotCheckDistance,
+    /// Only computes the exact distance at edges.
+    CheckDistanceAtEdge,
+    /// Always computes the exact distance, which is the slowest option.
+    AlwaysCheckDistance,
+}
+
+impl From<DistanceCheckMode> for MsdfDistanceCheckMode {
+    fn from(mode: DistanceCheckMode) -> Self {
+        match mode {
+            DistanceCheckMode::DoNotCheckDistance => MsdfDistanceCheckMode::DoNotCheckDistance,
+            DistanceCheckMode::CheckDistanceAtEdge => MsdfDistanceCheckMode::CheckDistanceAtEdge,
+            DistanceCheckMode::AlwaysCheckDistance => MsdfDistanceCheckMode::AlwaysCheckDistance,
+        }
+    }
+}
+
+/// Determines how many bits each channel of the atlas is stored with. The container is chosen by
+/// the extension of the atlas path, which must support the precision. PNG and TIFF support 8 and
+/// 16 bits, while OpenEXR supports 32 bit floats.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasPrecision {
+    /// Quantizes each channel to a u8.
+    Bits8,
+    /// Quantizes each channel to a u16.
+    Bits16,
+    /// Stores each channel as the f32 generated by msdfgen without clamping.
+    Float32,
+}
+
+impl AtlasPrecision {
+    /// Returns whether the image format can store channels with this precision.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format of the atlas, picked from the extension of its path
+    pub fn is_supported_by(&self, format: ImageFormat) -> bool {
+        match self {
+            AtlasPrecision::Bits8 => !matches!(format, ImageFormat::OpenExr | ImageFormat::Hdr),
+            AtlasPrecision::Bits16 => matches!(format, ImageFormat::Png | ImageFormat::Tiff),
+            AtlasPrecision::Float32 => format == ImageFormat::OpenExr,
+        }
+    }
+}
+
+/// Determines the pixel layout of atlases written to KTX2 or DDS containers. Single and dual
+/// channel layouts keep the distances needed to render an MTSDF without its color channels.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// Stores the true signed distance from the alpha channel, which is the layout to compress
+    /// as BC4.
+    R8,
+    /// Stores the median of the color channels followed by the true signed distance, which is
+    /// the layout to compress as BC5.
+    Rg8,
+    /// Stores every channel as a u8, which is the layout to compress as BC7.
+    Rgba8,
+    /// Stores every channel as a half precision float without clamping.
+    Rgba16F,
+}
+
+/// Determines which pixels are considered inside a glyph when correcting the sign of the
+/// generated distance field. Useful for fonts whose contours have the wrong winding.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    /// Skips the sign correction pass.
+    Disabled,
+    /// Pixels with a non-zero winding number are filled.
+    NonZero,
+    /// Pixels with an odd winding number are filled.
+    EvenOdd,
+    /// Pixels with a positive winding number are filled.
+    Positive,
+    /// Pixels with a negative winding number are filled.
+    Negative,
+}
+
 bitflags! {
     #[repr(C)]
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -20,6 +137,29 @@ bitflags! {
     }
 }
 
+impl UVSpace {
+    /// Undoes the uv space, returning the min and max uvs where 0, 0 is the top left of the
+    /// texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `uvs` - The uvs as stored in a GlyphData
+    pub fn to_texture_rect(&self, uvs: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+        let (uv_x, uv_y, uv_z, uv_w) = uvs;
+        let (x_min, x_max) = if self.contains(UVSpace::OneMinusU) {
+            (1.0 - uv_x, 1.0 - uv_z)
+        } else {
+            (uv_x, uv_z)
+        };
+        let (y_min, y_max) = if self.contains(UVSpace::OneMinusV) {
+            (1.0 - uv_w, 1.0 - uv_y)
+        } else {
+            (uv_y, uv_w)
+        };
+        (x_min, y_min, x_max, y_max)
+    }
+}
+
 impl Display for UVSpace {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut sb = String::with_capacity(64);
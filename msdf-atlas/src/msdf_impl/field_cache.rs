@@ -0,0 +1,319 @@
+use super::args::Args;
+use super::font_asset::{FNV_OFFSET_BASIS, FNV_PRIME};
+use super::{generate_glyph_image, GlyphBoundingBoxData};
+use image::{ImageBuffer, Rgba};
+use log::debug;
+use std::fs::{create_dir_all, read, remove_file, rename, File};
+use std::io::{BufWriter, Error, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use ttf_parser::Face;
+
+/// Identifies the file as a cached glyph field.
+const FIELD_MAGIC: &[u8; 8] = b"MSDFFELD";
+
+/// The version of the file layout and of the key. Bumping it invalidates every cached field.
+const FIELD_VERSION: u32 = 1;
+
+/// The extension of every cached field in the cache directory.
+const FIELD_EXTENSION: &str = "field";
+
+/// Numbers every temporary file written by this process, so threads storing the same key never
+/// write to the same file.
+static TEMPORARY_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// A directory of generated glyph fields which persists between builds. Each field is stored
+/// under a key derived from the font file, the glyph index and every setting which changes the
+/// generated field, so changing the padding, the atlas width or the uv space reuses the cached
+/// fields instead of generating them again.
+///
+/// Each field is stored in its own file as:
+///
+/// * The 8 byte magic, MSDFFELD
+/// * The u32 version
+/// * The u64 key, u32 width and u32 height of the field
+/// * The 4 f32 channels of every pixel, row by row from the top
+///
+/// Every value is little endian. Files which cannot be read are treated as a cache miss.
+pub struct FieldCache {
+    directory: PathBuf,
+    font_hash: u64,
+}
+
+impl FieldCache {
+    /// Creates a cache for a single font. The directory is created when the first field is
+    /// stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory the fields are stored in, which can be shared between fonts
+    /// * `font` - The contents of the font file
+    pub fn new(directory: &Path, font: &[u8]) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            font_hash: hash_bytes(FNV_OFFSET_BASIS, font),
+        }
+    }
+
+    /// Returns the cached field of the glyph, generating and storing it if it is not cached.
+    /// Returns None if the glyph has no outline to generate.
+    ///
+    /// # Arguments
+    ///
+    /// * `face` - The font face to load the glyph from
+    /// * `glyph_bounding_box` - The glyph and the settings to generate it with
+    /// * `args` - Generator params
+    pub fn get_or_generate(
+        &self,
+        face: &Face,
+        glyph_bounding_box: &GlyphBoundingBoxData,
+        args: &Args,
+    ) -> Option<ImageBuffer<Rgba<f32>, Vec<f32>>> {
+        let key = self.key(glyph_bounding_box, args);
+        if let Some(image) = self.load(key) {
+            return Some(image);
+        }
+
+        let image = generate_glyph_image(face, glyph_bounding_box, args)?;
+        if let Err(err) = self.store(key, &image) {
+            debug!(
+                "Failed to cache the field of {}: {}",
+                glyph_bounding_box.unicode, err
+            );
+        }
+        Some(image)
+    }
+
+    /// Derives the key from the font, the glyph index and every setting which changes the
+    /// field. The padding and the uv space are left out since they only affect the placement.
+    ///
+    /// # Arguments
+    ///
+    /// * `glyph_bounding_box` - The glyph and the settings to generate it with
+    /// * `args` - Generator params
+    pub fn key(&self, glyph_bounding_box: &GlyphBoundingBoxData, args: &Args) -> u64 {
+        let settings = glyph_bounding_box.settings;
+        let mut bytes: Vec<u8> = Vec::with_capacity(64);
+        bytes.extend_from_slice(&FIELD_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.font_hash.to_le_bytes());
+        bytes.extend_from_slice(&glyph_bounding_box.glyph_index.0.to_le_bytes());
+        bytes.extend_from_slice(&settings.uniform_scale.to_le_bytes());
+        bytes.extend_from_slice(&settings.range.to_le_bytes());
+        bytes.extend_from_slice(&settings.radians.to_le_bytes());
+        bytes.extend_from_slice(&(settings.color_type as u32).to_le_bytes());
+        bytes.extend_from_slice(&settings.coloring_seed.to_le_bytes());
+        bytes.extend_from_slice(&(args.error_correction_mode as u32).to_le_bytes());
+        bytes.extend_from_slice(&(args.distance_check_mode as u32).to_le_bytes());
+        bytes.extend_from_slice(&args.min_deviation_ratio.to_le_bytes());
+        bytes.extend_from_slice(&args.min_improve_ratio.to_le_bytes());
+        bytes.push(args.overlap_support as u8);
+        bytes.extend_from_slice(&(args.fill_rule as u32).to_le_bytes());
+        bytes.push(args.preprocess_shapes as u8);
+        hash_bytes(FNV_OFFSET_BASIS, &bytes)
+    }
+
+    /// Reads the cached field, returning None if it is missing or cannot be read.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the field
+    pub fn load(&self, key: u64) -> Option<ImageBuffer<Rgba<f32>, Vec<f32>>> {
+        let bytes = read(self.path(key)).ok()?;
+        match decode_field(&bytes, key) {
+            Ok(image) => Some(image),
+            Err(err) => {
+                debug!("Ignored the cached field {:016x}: {}", key, err);
+                None
+            }
+        }
+    }
+
+    /// Writes the field to the cache. The field is written to a temporary file first, so builds
+    /// sharing the directory never read a partially written field. The temporary file is
+    /// removed if the field cannot be written or renamed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the field
+    /// * `image` - The generated field
+    pub fn store(&self, key: u64, image: &ImageBuffer<Rgba<f32>, Vec<f32>>) -> Result<()> {
+        create_dir_all(&self.directory)?;
+        let path = self.path(key);
+        let count = TEMPORARY_COUNT.fetch_add(1, Ordering::Relaxed);
+        let temporary_path = path.with_extension(format!("{}.{}.tmp", std::process::id(), count));
+
+        let result =
+            write_field(&temporary_path, key, image).and_then(|_| rename(&temporary_path, &path));
+        if result.is_err() {
+            let _ = remove_file(&temporary_path);
+        }
+        result
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.directory
+            .join(format!("{:016x}", key))
+            .with_extension(FIELD_EXTENSION)
+    }
+}
+
+/// Writes the field to a new file at the path.
+fn write_field(path: &Path, key: u64, image: &ImageBuffer<Rgba<f32>, Vec<f32>>) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(FIELD_MAGIC)?;
+    writer.write_all(&FIELD_VERSION.to_le_bytes())?;
+    writer.write_all(&key.to_le_bytes())?;
+    writer.write_all(&image.width().to_le_bytes())?;
+    writer.write_all(&image.height().to_le_bytes())?;
+    for channel in image.as_raw() {
+        writer.write_all(&channel.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Continues a 64 bit FNV-1a hash with the bytes.
+fn hash_bytes(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Parses a cached field, checking that it was written for the key.
+fn decode_field(bytes: &[u8], key: u64) -> Result<ImageBuffer<Rgba<f32>, Vec<f32>>> {
+    const HEADER_SIZE: usize = 8 + 4 + 8 + 4 + 4;
+    let invalid_data = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+
+    if bytes.len() < HEADER_SIZE || &bytes[..8] != FIELD_MAGIC {
+        return Err(invalid_data("The file is not a cached field."));
+    }
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    if read_u32(8) != FIELD_VERSION {
+        return Err(invalid_data("The field was cached by another version."));
+    }
+    if u64::from_le_bytes(bytes[12..20].try_into().unwrap()) != key {
+        return Err(invalid_data("The field was cached under another key."));
+    }
+
+    let (width, height) = (read_u32(20), read_u32(24));
+    let channels: Vec<f32> = bytes[HEADER_SIZE..]
+        .chunks_exact(4)
+        .map(|channel| f32::from_le_bytes(channel.try_into().unwrap()))
+        .collect();
+    if channels.len() != width as usize * height as usize * 4 {
+        return Err(invalid_data("The field is truncated."));
+    }
+    ImageBuffer::from_raw(width, height, channels)
+        .ok_or_else(|| invalid_data("The field does not match its dimensions."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FieldCache;
+    use crate::msdf_impl::{
+        args::Args, enums::ErrorCorrectionMode, overrides::GlyphOverrides, store_and_sort_by_area,
+    };
+    use image::{ImageBuffer, Rgba};
+    use std::fs::{create_dir_all, read_dir, remove_dir_all, write};
+    use std::thread;
+    use ttf_parser::Face;
+
+    #[test]
+    fn reuses_cached_fields() {
+        let directory = std::env::temp_dir().join("msdf-atlas-reuses-cached-fields");
+        let _ = remove_dir_all(&directory);
+
+        let font = std::fs::read("testing-resources/Roboto-Medium.ttf").unwrap();
+        let face = Face::parse(&font, 0).unwrap();
+        let glyphs = [('A', face.glyph_index('A').unwrap())];
+        let cache = FieldCache::new(&directory, &font);
+
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 64.0)
+            .with_range(320.0);
+        let mut glyph_bounding_boxes = Vec::new();
+        store_and_sort_by_area(
+            &mut glyph_bounding_boxes,
+            &face,
+            &glyphs,
+            &args,
+            &GlyphOverrides::default(),
+        );
+        let glyph = &glyph_bounding_boxes[0];
+
+        let generated = cache.get_or_generate(&face, glyph, &args).unwrap();
+        let key = cache.key(glyph, &args);
+        assert_eq!(cache.load(key), Some(generated.clone()));
+        assert_eq!(read_dir(&directory).unwrap().count(), 1);
+
+        let padded = Args::default()
+            .with_uniform_scale(1.0 / 64.0)
+            .with_range(320.0)
+            .with_padding(16);
+        assert_eq!(
+            cache.key(glyph, &padded),
+            key,
+            "The padding does not change the field."
+        );
+        let corrected = Args::default()
+            .with_uniform_scale(1.0 / 64.0)
+            .with_range(320.0)
+            .with_error_correction(ErrorCorrectionMode::Indiscriminate);
+        assert_ne!(cache.key(glyph, &corrected), key);
+
+        // A damaged field is generated again.
+        write(cache.path(key), b"MSDFFELD").unwrap();
+        assert_eq!(cache.load(key), None);
+        assert_eq!(cache.get_or_generate(&face, glyph, &args), Some(generated));
+
+        let _ = remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn stores_the_same_key_from_many_threads() {
+        let directory = std::env::temp_dir().join("msdf-atlas-stores-the-same-key");
+        let _ = remove_dir_all(&directory);
+        let cache = FieldCache::new(&directory, b"font");
+
+        let image: ImageBuffer<Rgba<f32>, Vec<f32>> =
+            ImageBuffer::from_fn(32, 32, |x, y| Rgba([x as f32, y as f32, 0.5, 1.0]));
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..16 {
+                        cache.store(7, &image).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(cache.load(7), Some(image));
+        assert_eq!(
+            read_dir(&directory).unwrap().count(),
+            1,
+            "Every temporary file should be renamed."
+        );
+        let _ = remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn removes_the_temporary_file_when_storing_fails() {
+        let directory = std::env::temp_dir().join("msdf-atlas-removes-the-temporary-file");
+        let _ = remove_dir_all(&directory);
+        let cache = FieldCache::new(&directory, b"font");
+
+        // A directory in place of the field cannot be replaced by the rename.
+        create_dir_all(cache.path(9)).unwrap();
+        write(cache.path(9).join("reader"), b"").unwrap();
+
+        let image: ImageBuffer<Rgba<f32>, Vec<f32>> =
+            ImageBuffer::from_pixel(4, 4, Rgba([0.5, 0.5, 0.5, 1.0]));
+        assert!(cache.store(9, &image).is_err());
+        assert_eq!(
+            read_dir(&directory).unwrap().count(),
+            1,
+            "The temporary file should be removed."
+        );
+        let _ = remove_dir_all(&directory);
+    }
+}
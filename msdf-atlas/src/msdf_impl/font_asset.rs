@@ -0,0 +1,513 @@
+use super::args::Args;
+use super::enums::{
+    AtlasPrecision, ColorType, DistanceCheckMode, ErrorCorrectionMode, FillRule, UVSpace,
+};
+use super::font_data::FontData;
+use super::glyph_data::GlyphData;
+use super::kerning::KerningPair;
+use std::fs::{read, File};
+use std::io::{BufWriter, Error, ErrorKind, Result, Write};
+use std::path::Path;
+
+/// Identifies the file as a font asset.
+const FONT_ASSET_MAGIC: &[u8; 8] = b"MSDFFONT";
+
+/// The version of the layout. Readers reject any asset written with another version.
+pub const FONT_ASSET_VERSION: u32 = 1;
+
+/// Atlas paths with this extension are written as a font asset instead of an image.
+pub const FONT_ASSET_EXTENSION: &str = "msdffont";
+
+// FNV-1a parameters used to hash the atlas pixels and the keys of cached fields.
+pub(super) const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+pub(super) const FNV_PRIME: u64 = 0x100000001b3;
+
+// The size of each record in bytes, used to reject truncated assets before allocating.
+const GLYPH_RECORD_SIZE: usize = 4 * 11 + 1;
+const KERNING_RECORD_SIZE: usize = 4 * 3;
+
+/// The settings the atlas was generated with, which a runtime needs to render the distance field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GenerationSettings {
+    pub uniform_scale: f32,
+    pub padding: u32,
+    /// The msdf range in font units.
+    pub range: f32,
+    pub degrees: f32,
+    pub color_type: ColorType,
+    pub coloring_seed: u64,
+    pub uv_space: UVSpace,
+    pub field_settings: FieldSettings,
+}
+
+/// The settings which change the generated fields without changing how they are rendered.
+/// Glyphs added to an atlas must be generated with the same settings as the existing glyphs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldSettings {
+    pub fill_rule: FillRule,
+    pub preprocess_shapes: bool,
+    pub overlap_support: bool,
+    pub error_correction_mode: ErrorCorrectionMode,
+    pub distance_check_mode: DistanceCheckMode,
+    pub min_deviation_ratio: f32,
+    pub min_improve_ratio: f32,
+}
+
+impl GenerationSettings {
+    /// Copies the settings stored in a font asset from the generator params.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Generator params
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            uniform_scale: args.uniform_scale,
+            padding: args.padding,
+            range: args.range,
+            degrees: args.degrees,
+            color_type: args.color_type,
+            coloring_seed: args.coloring_seed,
+            uv_space: args.uv_space,
+            field_settings: FieldSettings {
+                fill_rule: args.fill_rule,
+                preprocess_shapes: args.preprocess_shapes,
+                overlap_support: args.overlap_support,
+                error_correction_mode: args.error_correction_mode,
+                distance_check_mode: args.distance_check_mode,
+                min_deviation_ratio: args.min_deviation_ratio,
+                min_improve_ratio: args.min_improve_ratio,
+            },
+        }
+    }
+}
+
+/// The atlas pixels, stored as 4 channels per pixel in little endian with the atlas' precision.
+pub struct AtlasPixels {
+    pub width: u32,
+    pub height: u32,
+    pub precision: AtlasPrecision,
+    pub data: Vec<u8>,
+}
+
+impl AtlasPixels {
+    /// Returns the number of bytes each channel is stored with.
+    pub fn channel_size(&self) -> usize {
+        channel_size(self.precision)
+    }
+
+    /// Returns the pixel at x, y with each channel converted back to a float, where 8 and 16 bit
+    /// channels are normalized between 0 and 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column of the pixel, starting from the left
+    /// * `y` - The row of the pixel, starting from the top
+    pub fn get_pixel(&self, x: u32, y: u32) -> [f32; 4] {
+        let channel_size = self.channel_size();
+        let start = (y as usize * self.width as usize + x as usize) * 4 * channel_size;
+
+        let mut pixel = [0.0; 4];
+        for (i, channel) in pixel.iter_mut().enumerate() {
+            let bytes = &self.data[start + i * channel_size..start + (i + 1) * channel_size];
+            *channel = match self.precision {
+                AtlasPrecision::Bits8 => bytes[0] as f32 / 255.0,
+                AtlasPrecision::Bits16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0,
+                AtlasPrecision::Float32 => {
+                    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                }
+            };
+        }
+        pixel
+    }
+}
+
+/// A single binary file bundling the font metrics, glyph table, kerning, generation settings
+/// and atlas pixels, so a runtime can load a font without decoding an image.
+///
+/// Every value is little endian and laid out in the following order:
+///
+/// * The magic `MSDFFONT` and the u32 version
+/// * The line height, units per em, ascender and descender
+/// * The uniform scale, padding, range, degrees, color type, coloring seed and uv space
+/// * The fill rule, a byte each for preprocessing shapes and overlap support, the error
+///   correction mode, the distance check mode and the minimum deviation and improvement ratios
+/// * The glyph count, followed by each glyph's unicode, glyph index, advance, metrics, bearings,
+///   uvs and a byte marking whether the glyph has a bitmap
+/// * The kerning pair count, followed by each pair's left unicode, right unicode and advance
+/// * The atlas width, height, precision and the u64 hash of the pixels, followed by the pixels
+pub struct FontAsset {
+    pub line_height: i32,
+    pub units_per_em: u32,
+    pub ascender: i32,
+    pub descender: i32,
+    pub settings: GenerationSettings,
+    /// The glyphs, sorted by unicode.
+    pub glyphs: Vec<GlyphData>,
+    /// The kerning pairs, sorted by the left and then the right unicode.
+    pub kerning: Vec<KerningPair>,
+    pub atlas: AtlasPixels,
+    /// The hash of the atlas pixels when the asset was written. None if the pixels were not
+    /// hashed yet.
+    pub atlas_hash: Option<u64>,
+}
+
+impl FontAsset {
+    /// Reads a font asset from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the font asset
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::from_bytes(&read(path)?)
+    }
+
+    /// Writes the font asset to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to write the font asset to
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&self.to_bytes())?;
+        writer.flush()
+    }
+
+    /// Returns the glyph for the unicode if it was generated.
+    ///
+    /// # Arguments
+    ///
+    /// * `unicode` - The unicode to look up
+    pub fn get_glyph(&self, unicode: i32) -> Option<&GlyphData> {
+        self.glyphs
+            .binary_search_by_key(&unicode, |glyph| glyph.unicode)
+            .ok()
+            .map(|i| &self.glyphs[i])
+    }
+
+    /// Returns the adjustment to the left glyph's advance in font units when it is followed by
+    /// the right glyph.
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - The unicode of the glyph on the left
+    /// * `right` - The unicode of the glyph on the right
+    pub fn get_kerning(&self, left: i32, right: i32) -> f32 {
+        self.kerning
+            .binary_search_by_key(&(left, right), |pair| (pair.left, pair.right))
+            .map_or(0.0, |i| self.kerning[i].advance)
+    }
+
+    /// Constructs a new font data to send through an FFI from the stored glyphs and metrics.
+    pub fn to_font_data(&self) -> FontData {
+        FontData::new(
+            self.line_height,
+            self.units_per_em,
+            self.ascender,
+            self.descender,
+            self.glyphs.to_vec(),
+            self.kerning.to_vec(),
+        )
+    }
+
+    /// Serializes the font asset. If the asset does not have a hash, the hash of the current
+    /// pixels is stored.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let glyph_size = self.glyphs.len() * GLYPH_RECORD_SIZE;
+        let kerning_size = self.kerning.len() * KERNING_RECORD_SIZE;
+        let mut bytes = Vec::with_capacity(128 + glyph_size + kerning_size + self.atlas.data.len());
+
+        bytes.extend_from_slice(FONT_ASSET_MAGIC);
+        bytes.extend_from_slice(&FONT_ASSET_VERSION.to_le_bytes());
+
+        bytes.extend_from_slice(&self.line_height.to_le_bytes());
+        bytes.extend_from_slice(&self.units_per_em.to_le_bytes());
+        bytes.extend_from_slice(&self.ascender.to_le_bytes());
+        bytes.extend_from_slice(&self.descender.to_le_bytes());
+
+        let settings = &self.settings;
+        bytes.extend_from_slice(&settings.uniform_scale.to_le_bytes());
+        bytes.extend_from_slice(&settings.padding.to_le_bytes());
+        bytes.extend_from_slice(&settings.range.to_le_bytes());
+        bytes.extend_from_slice(&settings.degrees.to_le_bytes());
+        bytes.extend_from_slice(&(settings.color_type as u32).to_le_bytes());
+        bytes.extend_from_slice(&settings.coloring_seed.to_le_bytes());
+        bytes.extend_from_slice(&settings.uv_space.bits().to_le_bytes());
+
+        let field_settings = &settings.field_settings;
+        bytes.extend_from_slice(&(field_settings.fill_rule as u32).to_le_bytes());
+        bytes.push(field_settings.preprocess_shapes as u8);
+        bytes.push(field_settings.overlap_support as u8);
+        bytes.extend_from_slice(&(field_settings.error_correction_mode as u32).to_le_bytes());
+        bytes.extend_from_slice(&(field_settings.distance_check_mode as u32).to_le_bytes());
+        bytes.extend_from_slice(&field_settings.min_deviation_ratio.to_le_bytes());
+        bytes.extend_from_slice(&field_settings.min_improve_ratio.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.glyphs.len() as u32).to_le_bytes());
+        for glyph in &self.glyphs {
+            let (metrics_x, metrics_y) = glyph.metrics();
+            let (bearings_x, bearings_y) = glyph.bearings();
+            let (uv_x, uv_y, uv_z, uv_w) = glyph.uvs();
+
+            bytes.extend_from_slice(&glyph.unicode.to_le_bytes());
+            bytes.extend_from_slice(&glyph.glyph_id.to_le_bytes());
+            for value in [
+                glyph.advance,
+                metrics_x,
+                metrics_y,
+                bearings_x,
+                bearings_y,
+                uv_x,
+                uv_y,
+                uv_z,
+                uv_w,
+            ] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.push(glyph.has_bitmap as u8);
+        }
+
+        bytes.extend_from_slice(&(self.kerning.len() as u32).to_le_bytes());
+        for pair in &self.kerning {
+            bytes.extend_from_slice(&pair.left.to_le_bytes());
+            bytes.extend_from_slice(&pair.right.to_le_bytes());
+            bytes.extend_from_slice(&pair.advance.to_le_bytes());
+        }
+
+        let atlas = &self.atlas;
+        bytes.extend_from_slice(&atlas.width.to_le_bytes());
+        bytes.extend_from_slice(&atlas.height.to_le_bytes());
+        bytes.extend_from_slice(&(atlas.precision as u32).to_le_bytes());
+        let atlas_hash = self.atlas_hash.unwrap_or_else(|| hash_atlas(&atlas.data));
+        bytes.extend_from_slice(&atlas_hash.to_le_bytes());
+        bytes.extend_from_slice(&atlas.data);
+        bytes
+    }
+
+    /// Deserializes a font asset, returning an error if the bytes are not a font asset, were
+    /// written with another version, or are truncated.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The serialized font asset
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = AssetReader { bytes, offset: 0 };
+
+        if reader.take(FONT_ASSET_MAGIC.len())? != FONT_ASSET_MAGIC {
+            return Err(invalid_data("The file is not a font asset."));
+        }
+
+        if reader.read_u32()? != FONT_ASSET_VERSION {
+            return Err(invalid_data("The font asset version is not supported."));
+        }
+
+        let line_height = reader.read_i32()?;
+        let units_per_em = reader.read_u32()?;
+        let ascender = reader.read_i32()?;
+        let descender = reader.read_i32()?;
+
+        let settings = GenerationSettings {
+            uniform_scale: reader.read_f32()?,
+            padding: reader.read_u32()?,
+            range: reader.read_f32()?,
+            degrees: reader.read_f32()?,
+            color_type: decode_color_type(reader.read_u32()?)?,
+            coloring_seed: reader.read_u64()?,
+            uv_space: UVSpace::from_bits(reader.read_u32()?)
+                .ok_or_else(|| invalid_data("The uv space is not supported."))?,
+            field_settings: FieldSettings {
+                fill_rule: decode_fill_rule(reader.read_u32()?)?,
+                preprocess_shapes: reader.take(1)?[0] != 0,
+                overlap_support: reader.take(1)?[0] != 0,
+                error_correction_mode: decode_error_correction_mode(reader.read_u32()?)?,
+                distance_check_mode: decode_distance_check_mode(reader.read_u32()?)?,
+                min_deviation_ratio: reader.read_f32()?,
+                min_improve_ratio: reader.read_f32()?,
+            },
+        };
+
+        let glyph_count = reader.read_count(GLYPH_RECORD_SIZE)?;
+        let mut glyphs = Vec::with_capacity(glyph_count);
+        for _ in 0..glyph_count {
+            let unicode = reader.read_i32()?;
+            let glyph_id = reader.read_u32()?;
+            let mut values = [0.0; 9];
+            for value in values.iter_mut() {
+                *value = reader.read_f32()?;
+            }
+            let has_bitmap = reader.take(1)?[0] != 0;
+
+            let mut glyph = GlyphData::from_unicode(unicode)
+                .with_glyph_id(glyph_id)
+                .with_bitmap(has_bitmap)
+                .with_layout(
+                    (values[1], values[2]),
+                    (values[3], values[4]),
+                    (values[5], values[6], values[7], values[8]),
+                );
+            glyph.advance = values[0];
+            glyphs.push(glyph);
+        }
+
+        let kerning_count = reader.read_count(KERNING_RECORD_SIZE)?;
+        let mut kerning = Vec::with_capacity(kerning_count);
+        for _ in 0..kerning_count {
+            kerning.push(KerningPair {
+                left: reader.read_i32()?,
+                right: reader.read_i32()?,
+                advance: reader.read_f32()?,
+            });
+        }
+
+        let width = reader.read_u32()?;
+        let height = reader.read_u32()?;
+        let precision = decode_precision(reader.read_u32()?)?;
+        let atlas_hash = Some(reader.read_u64()?);
+        let data_size = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(4 * channel_size(precision)))
+            .ok_or_else(|| invalid_data("The atlas dimensions are too large."))?;
+        let data = reader.take(data_size)?.to_vec();
+
+        Ok(Self {
+            line_height,
+            units_per_em,
+            ascender,
+            descender,
+            settings,
+            glyphs,
+            kerning,
+            atlas: AtlasPixels {
+                width,
+                height,
+                precision,
+                data,
+            },
+            atlas_hash,
+        })
+    }
+}
+
+/// Reads little endian values from the front of a byte slice.
+struct AssetReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> AssetReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "The font asset is truncated."))?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.read_array()?))
+    }
+
+    /// Reads the number of records in a table, checking that the table fits in the remaining
+    /// bytes before anything is allocated for it.
+    fn read_count(&mut self, record_size: usize) -> Result<usize> {
+        let count = self.read_u32()? as usize;
+        if count * record_size > self.bytes.len() - self.offset {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "The font asset is truncated.",
+            ));
+        }
+        Ok(count)
+    }
+}
+
+/// Hashes the atlas pixels with 64 bit FNV-1a, which is stable across platforms and versions.
+///
+/// # Arguments
+///
+/// * `data` - The atlas pixels, laid out like AtlasPixels
+pub fn hash_atlas(data: &[u8]) -> u64 {
+    data.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[inline(always)]
+fn channel_size(precision: AtlasPrecision) -> usize {
+    match precision {
+        AtlasPrecision::Bits8 => 1,
+        AtlasPrecision::Bits16 => 2,
+        AtlasPrecision::Float32 => 4,
+    }
+}
+
+fn decode_color_type(value: u32) -> Result<ColorType> {
+    match value {
+        0 => Ok(ColorType::Simple),
+        1 => Ok(ColorType::InkTrap),
+        2 => Ok(ColorType::Distance),
+        _ => Err(invalid_data("The color type is not supported.")),
+    }
+}
+
+fn decode_fill_rule(value: u32) -> Result<FillRule> {
+    match value {
+        0 => Ok(FillRule::Disabled),
+        1 => Ok(FillRule::NonZero),
+        2 => Ok(FillRule::EvenOdd),
+        3 => Ok(FillRule::Positive),
+        4 => Ok(FillRule::Negative),
+        _ => Err(invalid_data("The fill rule is not supported.")),
+    }
+}
+
+fn decode_error_correction_mode(value: u32) -> Result<ErrorCorrectionMode> {
+    match value {
+        0 => Ok(ErrorCorrectionMode::Disabled),
+        1 => Ok(ErrorCorrectionMode::Indiscriminate),
+        2 => Ok(ErrorCorrectionMode::EdgePriority),
+        3 => Ok(ErrorCorrectionMode::EdgeOnly),
+        _ => Err(invalid_data("The error correction mode is not supported.")),
+    }
+}
+
+fn decode_distance_check_mode(value: u32) -> Result<DistanceCheckMode> {
+    match value {
+        0 => Ok(DistanceCheckMode::DoNotCheckDistance),
+        1 => Ok(DistanceCheckMode::CheckDistanceAtEdge),
+        2 => Ok(DistanceCheckMode::AlwaysCheckDistance),
+        _ => Err(invalid_data("The distance check mode is not supported.")),
+    }
+}
+
+fn decode_precision(value: u32) -> Result<AtlasPrecision> {
+    match value {
+        0 => Ok(AtlasPrecision::Bits8),
+        1 => Ok(AtlasPrecision::Bits16),
+        2 => Ok(AtlasPrecision::Float32),
+        _ => Err(invalid_data("The atlas precision is not supported.")),
+    }
+}
+
+#[inline(always)]
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
@@ -1,4 +1,7 @@
 use super::byte_buffer::ByteBuffer;
+use super::glyph_data::GlyphData;
+use super::kerning::KerningPair;
+use std::collections::HashMap;
 
 #[repr(C)]
 #[derive(Clone)]
@@ -10,6 +13,115 @@ pub struct FontData {
     pub ascender: i32,
     pub descender: i32,
     pub glyph_data: *mut ByteBuffer,
+    /// The kerning pairs between the generated glyphs, sorted by the left and then the right
+    /// unicode.
+    pub kerning_data: *mut ByteBuffer,
+}
+
+impl FontData {
+    /// Constructs a new font data to send through an FFI.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_height` - The line height in font units
+    /// * `units_per_em` - The number of font units in an em
+    /// * `ascender` - The ascender in font units
+    /// * `descender` - The descender in font units
+    /// * `glyphs` - The glyphs sorted by their unicode
+    /// * `kerning` - The kerning pairs sorted by the left and then the right unicode
+    pub fn new(
+        line_height: i32,
+        units_per_em: u32,
+        ascender: i32,
+        descender: i32,
+        glyphs: Vec<GlyphData>,
+        kerning: Vec<KerningPair>,
+    ) -> Self {
+        let glyph_data = ByteBuffer::from_vec_struct(glyphs);
+        let kerning_data = ByteBuffer::from_vec_struct(kerning);
+
+        Self {
+            line_height,
+            units_per_em,
+            ascender,
+            descender,
+            glyph_data: Box::into_raw(Box::new(glyph_data)),
+            kerning_data: Box::into_raw(Box::new(kerning_data)),
+        }
+    }
+
+    /// Returns the glyphs sorted by their unicode.
+    pub fn glyphs(&self) -> &[GlyphData] {
+        if self.glyph_data.is_null() {
+            &[]
+        } else {
+            unsafe { (*self.glyph_data).as_slice::<GlyphData>() }
+        }
+    }
+
+    /// Returns the kerning pairs sorted by the left and then the right unicode.
+    pub fn kerning(&self) -> &[KerningPair] {
+        if self.kerning_data.is_null() {
+            &[]
+        } else {
+            unsafe { (*self.kerning_data).as_slice::<KerningPair>() }
+        }
+    }
+
+    /// Returns the glyph for the unicode if it was generated.
+    ///
+    /// # Arguments
+    ///
+    /// * `unicode` - The unicode to look up
+    pub fn get_glyph(&self, unicode: i32) -> Option<&GlyphData> {
+        let glyphs = self.glyphs();
+        glyphs
+            .binary_search_by_key(&unicode, |glyph| glyph.unicode)
+            .ok()
+            .map(|i| &glyphs[i])
+    }
+
+    /// Returns the glyph generated for the glyph index in the font, such as the glyphs returned
+    /// by the shaper. Every glyph is searched, so use glyphs_by_id to look up many glyphs.
+    ///
+    /// Returns None for the glyph index 0, which is the missing glyph and is never generated,
+    /// but is the glyph index of every glyph read from assets which did not store it.
+    ///
+    /// # Arguments
+    ///
+    /// * `glyph_id` - The index of the glyph in the font
+    pub fn get_glyph_by_id(&self, glyph_id: u32) -> Option<&GlyphData> {
+        if glyph_id == 0 {
+            return None;
+        }
+        self.glyphs()
+            .iter()
+            .find(|glyph| glyph.glyph_id == glyph_id)
+    }
+
+    /// Maps the glyph index in the font of every glyph to the glyph. Glyphs without a glyph
+    /// index, like those read from assets which did not store it, are left out.
+    pub fn glyphs_by_id(&self) -> HashMap<u32, &GlyphData> {
+        self.glyphs()
+            .iter()
+            .filter(|glyph| glyph.glyph_id != 0)
+            .map(|glyph| (glyph.glyph_id, glyph))
+            .collect()
+    }
+
+    /// Returns the adjustment to the left glyph's advance in font units when it is followed by
+    /// the right glyph.
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - The unicode of the glyph on the left
+    /// * `right` - The unicode of the glyph on the right
+    pub fn get_kerning(&self, left: i32, right: i32) -> f32 {
+        let kerning = self.kerning();
+        kerning
+            .binary_search_by_key(&(left, right), |pair| (pair.left, pair.right))
+            .map_or(0.0, |i| kerning[i].advance)
+    }
 }
 
 impl Drop for FontData {
@@ -18,6 +130,45 @@ impl Drop for FontData {
             if !self.glyph_data.is_null() {
                 (*self.glyph_data).destroy();
             }
+            if !self.kerning_data.is_null() {
+                (*self.kerning_data).destroy();
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::FontData;
+    use crate::msdf_impl::glyph_data::GlyphData;
+
+    #[test]
+    fn looks_up_glyphs_by_id() {
+        let glyphs = vec![
+            GlyphData::from_unicode('a' as i32).with_glyph_id(68),
+            GlyphData::from_unicode('b' as i32).with_glyph_id(69),
+        ];
+        let font_data = FontData::new(60, 100, 80, -20, glyphs, Vec::new());
+        assert_eq!(font_data.get_glyph_by_id(69).unwrap().unicode, 'b' as i32);
+        assert!(font_data.get_glyph_by_id(70).is_none());
+
+        let glyphs_by_id = font_data.glyphs_by_id();
+        assert_eq!(glyphs_by_id.len(), 2);
+        assert_eq!(glyphs_by_id[&68].unicode, 'a' as i32);
+    }
+
+    #[test]
+    fn ignores_glyphs_without_ids() {
+        // Assets written before the glyph index was stored load every glyph with index 0.
+        let glyphs = vec![
+            GlyphData::from_unicode('a' as i32),
+            GlyphData::from_unicode('b' as i32),
+        ];
+        let font_data = FontData::new(60, 100, 80, -20, glyphs, Vec::new());
+        assert!(
+            font_data.get_glyph_by_id(0).is_none(),
+            "The missing glyph should not match a generated glyph."
+        );
+        assert!(font_data.glyphs_by_id().is_empty());
+    }
+}
@@ -0,0 +1,371 @@
+use super::args::Args;
+use super::font_asset::AtlasPixels;
+use super::font_data::FontData;
+use super::glyph_data::GlyphData;
+use super::incremental::{place, with_placement, write_image, Placement};
+use super::kerning::collect_kerning_pairs;
+use super::overrides::GlyphOverrides;
+use super::packer::MaxRectsPacker;
+use super::{generate_glyph_image, read_font_file, store_and_sort_by_area, GlyphBoundingBoxData};
+use log::debug;
+use mint::Vector2;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind, Result};
+use ttf_parser::{Face, GlyphId};
+
+/// A region of the atlas whose pixels changed and must be uploaded again.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirtyRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A glyph stored in the cache's atlas.
+struct CachedGlyph {
+    glyph_data: GlyphData,
+    placement: Placement,
+    /// The frame the glyph was last requested in.
+    last_used: u64,
+}
+
+/// A fixed size atlas which generates glyphs the first time they are requested, for text whose
+/// characters are not known ahead of time, such as chat messages and player names. When the
+/// atlas is full, the least recently used glyphs are evicted to make room.
+///
+/// Glyphs requested during the current frame are never evicted, so their uvs stay valid until
+/// begin_frame is called. Every region of the atlas which changed is recorded as a dirty rect,
+/// so only those regions need to be uploaded to the gpu.
+pub struct GlyphCache {
+    font: Vec<u8>,
+    args: Args,
+    pixels: AtlasPixels,
+    packer: MaxRectsPacker,
+    glyphs: HashMap<char, CachedGlyph>,
+    dirty_rects: Vec<DirtyRect>,
+    frame: u64,
+    ascender: i32,
+    descender: i32,
+    units_per_em: u32,
+}
+
+impl GlyphCache {
+    /// Creates an empty cache with an atlas of a fixed size.
+    ///
+    /// # Arguments
+    ///
+    /// * `font_path` - The path to the font
+    /// * `args` - Generator params, where the precision sets the format of the atlas
+    /// * `width` - The width of the atlas in pixels
+    /// * `height` - The height of the atlas in pixels
+    pub fn new(font_path: &OsStr, args: Args, width: u32, height: u32) -> Result<Self> {
+        let font = read_font_file(font_path).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "The font must be a ttf or otf file.",
+            )
+        })?;
+        let face = Face::parse(&font, 0)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        let (ascender, descender) = (face.ascender() as i32, face.descender() as i32);
+        let units_per_em = face.units_per_em() as u32;
+
+        let mut pixels = AtlasPixels {
+            width,
+            height,
+            precision: args.precision,
+            data: Vec::new(),
+        };
+        pixels.data = vec![0; width as usize * height as usize * 4 * pixels.channel_size()];
+
+        Ok(Self {
+            font,
+            args,
+            pixels,
+            packer: MaxRectsPacker::new(width, height),
+            glyphs: HashMap::new(),
+            dirty_rects: Vec::new(),
+            frame: 0,
+            ascender,
+            descender,
+            units_per_em,
+        })
+    }
+
+    /// Starts a new frame. Glyphs which were not requested since the last frame can be evicted
+    /// from now on.
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Returns the glyph for the char, generating it if it is not cached. Returns None if the
+    /// font does not have the char, or if the glyph does not fit even after evicting every glyph
+    /// which was not used in this frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The char to look up
+    pub fn get_glyph(&mut self, c: char) -> Option<GlyphData> {
+        let frame = self.frame;
+        if let Some(cached) = self.glyphs.get_mut(&c) {
+            cached.last_used = frame;
+            return Some(cached.glyph_data);
+        }
+        self.insert(c)
+    }
+
+    /// Requests every char of the text, returning the number of chars which could not be
+    /// cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text which will be rendered this frame
+    pub fn request_text(&mut self, text: &str) -> u32 {
+        text.chars()
+            .filter(|c| !c.is_control())
+            .filter(|c| self.get_glyph(*c).is_none())
+            .count() as u32
+    }
+
+    /// Returns the number of glyphs in the cache.
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    /// Returns true if no glyphs are cached.
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// Returns the atlas, where each pixel has 4 channels in the precision from Args.
+    pub fn pixels(&self) -> &AtlasPixels {
+        &self.pixels
+    }
+
+    /// Returns the regions changed since the last call and clears them.
+    pub fn take_dirty_rects(&mut self) -> Vec<DirtyRect> {
+        std::mem::take(&mut self.dirty_rects)
+    }
+
+    /// Packages the cached glyphs and their kerning, so the text can be laid out with
+    /// layout_text.
+    pub fn font_data(&self) -> FontData {
+        let mut glyphs: Vec<GlyphData> = self.glyphs.values().map(|c| c.glyph_data).collect();
+        glyphs.sort_unstable_by_key(|glyph| glyph.unicode);
+
+        let kerning = match Face::parse(&self.font, 0) {
+            Ok(face) => {
+                let glyph_indices: Vec<(char, GlyphId)> = self
+                    .glyphs
+                    .keys()
+                    .filter_map(|c| face.glyph_index(*c).map(|glyph_index| (*c, glyph_index)))
+                    .collect();
+                collect_kerning_pairs(&face, &glyph_indices)
+            }
+            Err(_) => Vec::new(),
+        };
+
+        FontData::new(
+            self.ascender + self.descender,
+            self.units_per_em,
+            self.ascender,
+            self.descender,
+            glyphs,
+            kerning,
+        )
+    }
+
+    /// Generates the glyph and writes it into the atlas.
+    fn insert(&mut self, c: char) -> Option<GlyphData> {
+        let face = Face::parse(&self.font, 0).ok()?;
+        let glyph_index = face.glyph_index(c)?;
+
+        let mut glyph_bounding_boxes: Vec<GlyphBoundingBoxData> = Vec::with_capacity(1);
+        store_and_sort_by_area(
+            &mut glyph_bounding_boxes,
+            &face,
+            &[(c, glyph_index)],
+            &self.args,
+            &GlyphOverrides::default(),
+        );
+        let glyph_bounding_box = glyph_bounding_boxes.first()?;
+
+        let (width, height) = glyph_bounding_box.get_scaled_glyph_dimensions_no_padding();
+        let (width, height) = (width.max(0) as u32, height.max(0) as u32);
+        let glyph_data = glyph_bounding_box.create_glyph_data(&face, self.ascender);
+        let image = generate_glyph_image(&face, glyph_bounding_box, &self.args);
+
+        let placement = self.allocate(width, height)?;
+        if let Some(image) = image {
+            let padding = self.args.add_padding(0) as u32;
+            self.clear(&placement, padding);
+            let (precision, channel_size) = (self.pixels.precision, self.pixels.channel_size());
+            write_image(
+                &mut self.pixels.data,
+                self.pixels.width,
+                &placement,
+                &image,
+                precision,
+                channel_size,
+            );
+        }
+
+        let dimensions = Vector2 {
+            x: self.pixels.width as i32,
+            y: self.pixels.height as i32,
+        };
+        let glyph_data = with_placement(glyph_data, &placement, dimensions, self.args.uv_space);
+        self.glyphs.insert(
+            c,
+            CachedGlyph {
+                glyph_data,
+                placement,
+                last_used: self.frame,
+            },
+        );
+        Some(glyph_data)
+    }
+
+    /// Finds space for a glyph, evicting the least recently used glyphs until it fits.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<Placement> {
+        let padding = self.args.add_padding(0) as u32;
+        if width + padding > self.pixels.width || height + padding > self.pixels.height {
+            debug!("The glyph is larger than the cache's atlas.");
+            return None;
+        }
+
+        loop {
+            if let Some(placement) = place(&mut self.packer, width, height, padding) {
+                return Some(placement);
+            }
+
+            let frame = self.frame;
+            let evicted = self
+                .glyphs
+                .iter()
+                .filter(|(_, cached)| cached.last_used < frame)
+                .filter(|(_, cached)| cached.placement.width > 0 && cached.placement.height > 0)
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(c, _)| *c)?;
+            debug!("Evicted {} from the glyph cache.", evicted);
+            self.glyphs.remove(&evicted);
+
+            // Rebuilding the packer from the remaining glyphs frees the evicted glyph's rect,
+            // even when other glyphs surround it.
+            let occupied: Vec<(u32, u32, u32, u32)> = self
+                .glyphs
+                .values()
+                .map(|cached| &cached.placement)
+                .filter(|placement| placement.width > 0 && placement.height > 0)
+                .map(|placement| {
+                    let (x, y) = (placement.x, placement.y);
+                    (x, y, placement.width + padding, placement.height + padding)
+                })
+                .collect();
+            self.packer =
+                MaxRectsPacker::from_rects(self.pixels.width, self.pixels.height, &occupied);
+        }
+    }
+
+    /// Clears the glyph's rect and its padding, which may still hold an evicted glyph, and
+    /// marks it as dirty.
+    fn clear(&mut self, placement: &Placement, padding: u32) {
+        let width = (placement.width + padding).min(self.pixels.width - placement.x);
+        let height = (placement.height + padding).min(self.pixels.height - placement.y);
+        let pixel_size = 4 * self.pixels.channel_size();
+
+        for row in placement.y..placement.y + height {
+            let start =
+                (row as usize * self.pixels.width as usize + placement.x as usize) * pixel_size;
+            self.pixels.data[start..start + width as usize * pixel_size].fill(0);
+        }
+
+        self.dirty_rects.push(DirtyRect {
+            x: placement.x,
+            y: placement.y,
+            width,
+            height,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GlyphCache;
+    use crate::msdf_impl::args::Args;
+    use std::ffi::OsStr;
+
+    const FONT_PATH: &str = "testing-resources/Roboto-Medium.ttf";
+
+    fn create_cache(width: u32, height: u32) -> GlyphCache {
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 128.0)
+            .with_range(320.0)
+            .with_padding(2);
+        GlyphCache::new(OsStr::new(FONT_PATH), args, width, height).unwrap()
+    }
+
+    #[test]
+    fn caches_glyphs_on_demand() {
+        let mut cache = create_cache(256, 256);
+        assert_eq!(cache.request_text("abca"), 0);
+        assert_eq!(cache.len(), 3);
+
+        let dirty_rects = cache.take_dirty_rects();
+        assert_eq!(dirty_rects.len(), 3, "Each new glyph should be uploaded.");
+        assert!(cache.take_dirty_rects().is_empty());
+
+        let a = cache.get_glyph('a').unwrap();
+        assert!(
+            cache.take_dirty_rects().is_empty(),
+            "Cached glyphs should not be written again."
+        );
+
+        let font_data = cache.font_data();
+        assert_eq!(font_data.get_glyph('a' as i32).unwrap().uvs(), a.uvs());
+        assert_eq!(font_data.glyphs().len(), 3);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_glyphs() {
+        // Fits a few rows of glyphs, so evicted glyphs leave holes between the others.
+        let mut cache = create_cache(64, 64);
+        let chars: Vec<char> = "BCDEFGHIJKLMNOPQRSTUVWXYZ".chars().collect();
+
+        // Each frame uses A and one new glyph, so every other glyph is older than the next.
+        let mut capacity = 0;
+        for &c in &chars {
+            cache.begin_frame();
+            assert_eq!(cache.request_text(&format!("A{}", c)), 0);
+            capacity = capacity.max(cache.len());
+        }
+        assert!(capacity <= chars.len(), "The atlas should fill up.");
+
+        assert!(
+            cache.glyphs.contains_key(&'A'),
+            "A was used in every frame."
+        );
+        let oldest_cached = chars
+            .iter()
+            .position(|c| cache.glyphs.contains_key(c))
+            .unwrap();
+        for (i, c) in chars.iter().enumerate() {
+            assert_eq!(
+                cache.glyphs.contains_key(c),
+                i >= oldest_cached,
+                "Only the glyphs used before {} should be evicted.",
+                chars[oldest_cached]
+            );
+        }
+        assert!(
+            cache.len() + 2 >= capacity,
+            "Evicting a glyph should free its space, so only a few glyphs are evicted at once. \
+             {} of {} glyphs are cached.",
+            cache.len(),
+            capacity
+        );
+    }
+}
@@ -8,6 +8,9 @@ use super::enums::UVSpace;
 #[derive(Clone, Copy, Debug)]
 pub struct GlyphData {
     pub unicode: i32,
+    /// The index of the glyph in the font. Glyphs which are only reachable through shaping,
+    /// such as ligatures and contextual forms, have a unicode of 0.
+    pub glyph_id: u32,
     pub advance: f32,
     pub has_bitmap: bool,
     // The width of the rectangle for the font
@@ -34,6 +37,7 @@ impl GlyphData {
         let bounding_box = face.glyph_bounding_box(glyph_id).unwrap();
 
         glyph_data
+            .with_glyph_id(glyph_id.0 as u32)
             .with_advance(face.glyph_hor_advance(glyph_id).unwrap())
             .with_metrics(bounding_box.width(), bounding_box.height())
             .with_bearings(face.glyph_hor_side_bearing(glyph_id).unwrap(), 0)
@@ -42,6 +46,7 @@ impl GlyphData {
     pub fn from_char(c: char) -> Self {
         Self {
             unicode: c as i32,
+            glyph_id: 0,
             advance: 0.0,
             metrics_x: 0.0,
             metrics_y: 0.0,
@@ -58,6 +63,7 @@ impl GlyphData {
     pub fn from_unicode(unicode: i32) -> Self {
         Self {
             unicode,
+            glyph_id: 0,
             advance: 0.0,
             metrics_x: 0.0,
             metrics_y: 0.0,
@@ -71,6 +77,11 @@ impl GlyphData {
         }
     }
 
+    pub fn with_glyph_id(mut self, glyph_id: u32) -> GlyphData {
+        self.glyph_id = glyph_id;
+        self
+    }
+
     pub fn with_advance(mut self, advance: u16) -> GlyphData {
         self.advance = advance as f32;
         self
@@ -115,6 +126,15 @@ impl GlyphData {
         self
     }
 
+    /// Sets the metrics, bearings and uvs as they were previously generated, such as when
+    /// reading the glyph back from a font asset.
+    pub fn with_layout(mut self, metrics: (f32, f32), bearings: (f32, f32), uvs: (f32, f32, f32, f32)) -> GlyphData {
+        (self.metrics_x, self.metrics_y) = metrics;
+        (self.bearings_x, self.bearings_y) = bearings;
+        (self.uv_x, self.uv_y, self.uv_z, self.uv_w) = uvs;
+        self
+    }
+
     /// Returns the size of the recntangle for the character in the font
     pub fn metrics(&self) -> (f32, f32) {
         (self.metrics_x, self.metrics_y)
@@ -155,6 +175,7 @@ impl PartialEq for GlyphData {
         let (bearings_x, bearings_y) = self.bearings();
 
         self.unicode == other.unicode
+            && self.glyph_id == other.glyph_id
             && self.advance == other.advance
             && self.metrics_x == metrics_x
             && self.metrics_y == metrics_y
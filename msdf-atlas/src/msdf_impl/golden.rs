@@ -0,0 +1,334 @@
+use super::args::Args;
+use super::enums::{ColorType, UVSpace};
+use super::Builder;
+use image::{ImageBuffer, Rgba, RgbaImage};
+use std::ffi::OsStr;
+use std::fs::{copy, create_dir_all, read_to_string, remove_file, write};
+use std::path::{Path, PathBuf};
+
+const FONT_PATH: &str = "testing-resources/Roboto-Medium.ttf";
+
+/// The reference of every case, stored as a PNG of the atlas and a text file of the metrics,
+/// glyphs and kerning.
+const GOLDEN_DIRECTORY: &str = "testing-resources/golden";
+
+/// Set to record the references, either for a new case or after an intended change. Without
+/// it, a missing reference fails the case.
+const UPDATE_ENV: &str = "MSDF_ATLAS_UPDATE_GOLDEN";
+
+/// Set to a directory to write the generated atlas and a diff image of every case which does
+/// not match its reference.
+const REPORT_ENV: &str = "MSDF_ATLAS_GOLDEN_REPORT";
+
+/// How far a generated atlas may drift from its reference.
+#[derive(Clone, Copy)]
+struct Tolerance {
+    /// The largest difference allowed in any channel before a pixel counts as mismatched.
+    channel: u8,
+    /// The fraction of the pixels which may be mismatched.
+    mismatched_ratio: f64,
+    /// The largest difference allowed in any metric, bearing, advance or uv.
+    metadata: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            channel: 2,
+            mismatched_ratio: 0.001,
+            metadata: 1e-4,
+        }
+    }
+}
+
+/// Returns the largest difference between the channels of two pixels.
+fn channel_difference(expected: &Rgba<u8>, actual: &Rgba<u8>) -> u8 {
+    expected
+        .0
+        .iter()
+        .zip(actual.0)
+        .map(|(expected, actual)| expected.abs_diff(actual))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Compares two atlases, returning why they do not match.
+///
+/// # Arguments
+///
+/// * `expected` - The reference atlas
+/// * `actual` - The generated atlas
+/// * `tolerance` - How far the generated atlas may drift
+fn compare_images(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    tolerance: Tolerance,
+) -> Result<(), String> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err(format!(
+            "The atlas is {:?}, but the reference is {:?}.",
+            actual.dimensions(),
+            expected.dimensions()
+        ));
+    }
+
+    let differences: Vec<u8> = expected
+        .pixels()
+        .zip(actual.pixels())
+        .map(|(expected, actual)| channel_difference(expected, actual))
+        .collect();
+    let mismatched = differences
+        .iter()
+        .filter(|difference| **difference > tolerance.channel)
+        .count();
+    let allowed = (differences.len() as f64 * tolerance.mismatched_ratio) as usize;
+    if mismatched > allowed {
+        return Err(format!(
+            "{} pixels differ by more than {}, but only {} may, with a largest difference of {}.",
+            mismatched,
+            tolerance.channel,
+            allowed,
+            differences.iter().max().unwrap_or(&0)
+        ));
+    }
+    Ok(())
+}
+
+/// Renders where two atlases of the same size differ. Mismatched pixels are red, pixels within
+/// the tolerance are a dim grey scaled by their difference, and matching pixels are black.
+///
+/// # Arguments
+///
+/// * `expected` - The reference atlas
+/// * `actual` - The generated atlas
+/// * `tolerance` - How far the generated atlas may drift
+fn diff_image(expected: &RgbaImage, actual: &RgbaImage, tolerance: Tolerance) -> RgbaImage {
+    ImageBuffer::from_fn(expected.width(), expected.height(), |x, y| {
+        let difference = channel_difference(expected.get_pixel(x, y), actual.get_pixel(x, y));
+        if difference > tolerance.channel {
+            Rgba([255, 0, 0, 255])
+        } else {
+            let grey = difference.saturating_mul(32);
+            Rgba([grey, grey, grey, 255])
+        }
+    })
+}
+
+/// Describes the metrics, glyphs and kerning of the builder, with one line per glyph and
+/// kerning pair so changes are easy to review.
+///
+/// # Arguments
+///
+/// * `builder` - The builder which generated the atlas
+fn describe_metadata(builder: &Builder) -> String {
+    let font_data = builder.package_font_data();
+    let mut lines = vec![format!(
+        "font {} {} {} {}",
+        font_data.line_height, font_data.units_per_em, font_data.ascender, font_data.descender
+    )];
+
+    for glyph in font_data.glyphs() {
+        let (metrics_x, metrics_y) = glyph.metrics();
+        let (bearings_x, bearings_y) = glyph.bearings();
+        let (min_x, min_y, max_x, max_y) = glyph.uvs();
+        lines.push(format!(
+            "glyph {} {} {} {} {} {} {} {} {} {} {}",
+            glyph.unicode,
+            glyph.glyph_id,
+            glyph.advance,
+            metrics_x,
+            metrics_y,
+            bearings_x,
+            bearings_y,
+            min_x,
+            min_y,
+            max_x,
+            max_y
+        ));
+    }
+    for pair in font_data.kerning() {
+        lines.push(format!(
+            "kerning {} {} {}",
+            pair.left, pair.right, pair.advance
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Checks if two values of the metadata match, allowing numbers to drift by the tolerance.
+fn values_match(expected: &str, actual: &str, tolerance: Tolerance) -> bool {
+    match (expected.parse::<f32>(), actual.parse::<f32>()) {
+        (Ok(expected), Ok(actual)) => (expected - actual).abs() <= tolerance.metadata,
+        _ => expected == actual,
+    }
+}
+
+/// Compares two metadata descriptions line by line, allowing numbers to drift by the
+/// tolerance.
+///
+/// # Arguments
+///
+/// * `expected` - The reference description
+/// * `actual` - The generated description
+/// * `tolerance` - How far the numbers may drift
+fn compare_metadata(expected: &str, actual: &str, tolerance: Tolerance) -> Result<(), String> {
+    let (expected_lines, actual_lines): (Vec<&str>, Vec<&str>) =
+        (expected.lines().collect(), actual.lines().collect());
+    if expected_lines.len() != actual_lines.len() {
+        return Err(format!(
+            "The metadata has {} lines, but the reference has {}.",
+            actual_lines.len(),
+            expected_lines.len()
+        ));
+    }
+
+    for (i, (expected_line, actual_line)) in expected_lines.iter().zip(&actual_lines).enumerate() {
+        let expected_values: Vec<&str> = expected_line.split_whitespace().collect();
+        let actual_values: Vec<&str> = actual_line.split_whitespace().collect();
+        let matches = expected_values.len() == actual_values.len()
+            && expected_values
+                .iter()
+                .zip(&actual_values)
+                .all(|(expected, actual)| values_match(expected, actual, tolerance));
+        if !matches {
+            return Err(format!(
+                "Line {} of the metadata is `{}`, but the reference is `{}`.",
+                i + 1,
+                actual_line,
+                expected_line
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Generates the atlas for a case and compares it against its reference, recording the
+/// reference instead if it is being updated.
+///
+/// # Arguments
+///
+/// * `name` - The name of the case, which names the reference files
+/// * `chars` - The characters to generate
+/// * `args` - Generator params
+fn check_golden(name: &str, chars: &str, args: Args) {
+    let atlas_path = std::env::temp_dir().join(format!("msdf-atlas-golden-{}.png", name));
+    let mut builder = Builder::from_font_path(OsStr::new(FONT_PATH), chars.to_string(), &args);
+    builder
+        .prepare_workload(args.thread_count as usize)
+        .build_atlas(&atlas_path);
+    let metadata = describe_metadata(&builder);
+
+    let directory = Path::new(GOLDEN_DIRECTORY);
+    let reference_atlas_path = directory.join(format!("{}.png", name));
+    let reference_metadata_path = directory.join(format!("{}.txt", name));
+
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        create_dir_all(directory).unwrap();
+        copy(&atlas_path, &reference_atlas_path).unwrap();
+        write(&reference_metadata_path, metadata).unwrap();
+        let _ = remove_file(&atlas_path);
+        eprintln!("Recorded the reference of {}.", name);
+        return;
+    }
+
+    if !reference_atlas_path.exists() || !reference_metadata_path.exists() {
+        let _ = remove_file(&atlas_path);
+        panic!(
+            "The {} atlas has no reference in {}. Record it by setting {}.",
+            name, GOLDEN_DIRECTORY, UPDATE_ENV
+        );
+    }
+
+    let tolerance = Tolerance::default();
+    let actual = image::open(&atlas_path).unwrap().to_rgba8();
+    let expected = image::open(&reference_atlas_path).unwrap().to_rgba8();
+    let atlas_result = compare_images(&expected, &actual, tolerance);
+    let metadata_result = compare_metadata(
+        &read_to_string(&reference_metadata_path).unwrap(),
+        &metadata,
+        tolerance,
+    );
+
+    if atlas_result.is_err() {
+        if let Some(report_directory) = std::env::var_os(REPORT_ENV).map(PathBuf::from) {
+            create_dir_all(&report_directory).unwrap();
+            copy(
+                &atlas_path,
+                report_directory.join(format!("{}.actual.png", name)),
+            )
+            .unwrap();
+            if expected.dimensions() == actual.dimensions() {
+                diff_image(&expected, &actual, tolerance)
+                    .save(report_directory.join(format!("{}.diff.png", name)))
+                    .unwrap();
+            }
+        }
+    }
+    let _ = remove_file(&atlas_path);
+
+    if let Err(err) = atlas_result.and(metadata_result) {
+        panic!("The {} atlas does not match its reference: {}", name, err);
+    }
+}
+
+#[test]
+fn golden_ascii() {
+    let chars: String = (0x21u8..0x7F).map(char::from).collect();
+    let args = Args::default()
+        .with_uniform_scale(1.0 / 32.0)
+        .with_range(640.0)
+        .with_padding(10);
+    check_golden("ascii", &chars, args);
+}
+
+#[test]
+fn golden_ink_trap_one_minus_v() {
+    let args = Args::default()
+        .with_uniform_scale(1.0 / 16.0)
+        .with_range(320.0)
+        .with_padding(4)
+        .with_color_type(ColorType::InkTrap)
+        .with_uv_space(UVSpace::OneMinusV)
+        .with_thread_count(2);
+    check_golden("ink_trap_one_minus_v", "AaBbGgQq&@", args);
+}
+
+#[test]
+fn golden_scaled_texture() {
+    let args = Args::default()
+        .with_uniform_scale(1.0 / 64.0)
+        .with_range(1280.0)
+        .with_padding(2)
+        .with_scaled_texture(true)
+        .with_max_atlas(128);
+    check_golden("scaled_texture", "0123456789", args);
+}
+
+#[test]
+fn compares_with_tolerance() {
+    let tolerance = Tolerance::default();
+    let expected: RgbaImage = ImageBuffer::from_pixel(100, 10, Rgba([100, 100, 100, 255]));
+
+    let mut actual = expected.clone();
+    actual.put_pixel(0, 0, Rgba([102, 98, 100, 255]));
+    assert!(compare_images(&expected, &actual, tolerance).is_ok());
+
+    actual.put_pixel(1, 0, Rgba([110, 100, 100, 255]));
+    assert!(
+        compare_images(&expected, &actual, tolerance).is_ok(),
+        "A single pixel in a thousand may be mismatched."
+    );
+    actual.put_pixel(2, 0, Rgba([100, 100, 90, 255]));
+    assert!(compare_images(&expected, &actual, tolerance).is_err());
+
+    let diff = diff_image(&expected, &actual, tolerance);
+    assert_eq!(diff.get_pixel(0, 0).0, [64, 64, 64, 255]);
+    assert_eq!(diff.get_pixel(2, 0).0, [255, 0, 0, 255]);
+    assert_eq!(diff.get_pixel(3, 0).0, [0, 0, 0, 255]);
+
+    let smaller: RgbaImage = ImageBuffer::from_pixel(10, 10, Rgba([100, 100, 100, 255]));
+    assert!(compare_images(&expected, &smaller, tolerance).is_err());
+
+    assert!(compare_metadata("glyph 65 1.0", "glyph 65 1.00001", tolerance).is_ok());
+    assert!(compare_metadata("glyph 65 1.0", "glyph 66 1.0", tolerance).is_err());
+}
@@ -0,0 +1,424 @@
+use super::args::Args;
+use super::enums::{AtlasPrecision, UVSpace};
+use super::font_asset::{hash_atlas, FontAsset, GenerationSettings};
+use super::glyph_data::GlyphData;
+use super::kerning::collect_kerning_pairs;
+use super::overrides::GlyphOverrides;
+use super::packer::{MaxRectsPacker, Packer, SkylinePacker};
+use super::{
+    align_to_block, generate_glyph_image, quantize_u16, quantize_u8, read_font_file,
+    resolve_glyph_indices, store_and_sort_by_area, GlyphBoundingBoxData,
+};
+use image::{ImageBuffer, Rgba};
+use log::debug;
+use mint::Vector2;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind, Result};
+use ttf_parser::{Face, GlyphId};
+
+/// A glyph whose uvs changed while glyphs were added to the atlas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MovedGlyph {
+    pub unicode: i32,
+    pub glyph_id: u32,
+    pub old_uvs: (f32, f32, f32, f32),
+    pub new_uvs: (f32, f32, f32, f32),
+}
+
+/// Describes how the atlas changed after glyphs were added.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AtlasUpdate {
+    /// The unicodes of the glyphs which were generated.
+    pub added: Vec<i32>,
+    /// The existing glyphs whose uvs changed, which must be updated wherever they were copied.
+    pub moved: Vec<MovedGlyph>,
+    /// Did the atlas change size? Resizing changes the uvs of every glyph.
+    pub resized: bool,
+    /// Were the existing glyphs moved to new positions in the atlas?
+    pub repacked: bool,
+}
+
+/// A glyph's rect in atlas pixels, without padding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Placement {
+    pub(super) x: u32,
+    pub(super) y: u32,
+    pub(super) width: u32,
+    pub(super) height: u32,
+}
+
+/// A glyph which was generated and still needs to be placed.
+struct NewGlyph {
+    glyph_data: GlyphData,
+    image: Option<ImageBuffer<Rgba<f32>, Vec<f32>>>,
+    width: u32,
+    height: u32,
+}
+
+/// Generates the chars which are not in the font asset yet and places them into the free space
+/// of the atlas, without regenerating the existing glyphs. When the glyphs do not fit, the atlas
+/// grows taller, and when a glyph is wider than the atlas every glyph is repacked into a wider
+/// atlas. Existing glyph pixels are copied over rather than generated again.
+///
+/// The args must match the settings the font asset was generated with, since the new glyphs
+/// have to share the scale, range and field settings of the existing glyphs.
+///
+/// # Arguments
+///
+/// * `asset` - The font asset to add the glyphs to
+/// * `font_path` - The path to the font the asset was generated from
+/// * `chars_to_add` - The characters to generate, where chars already in the asset are skipped
+/// * `args` - Generator params
+/// * `overrides` - The settings to generate specific ranges of the new glyphs with
+pub fn add_glyphs(
+    asset: &mut FontAsset,
+    font_path: &OsStr,
+    chars_to_add: &str,
+    args: &Args,
+    overrides: &GlyphOverrides,
+) -> Result<AtlasUpdate> {
+    if GenerationSettings::from_args(args) != asset.settings {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The args do not match the settings the font asset was generated with.",
+        ));
+    }
+
+    let buffer = read_font_file(font_path).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "The font must be a ttf or otf file.",
+        )
+    })?;
+    let face = Face::parse(&buffer, 0)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+
+    let mut seen: HashSet<char> = HashSet::new();
+    let glyphs: Vec<(char, GlyphId)> = resolve_glyph_indices(&face, chars_to_add.chars())
+        .into_iter()
+        .filter(|(c, _)| asset.get_glyph(*c as i32).is_none() && seen.insert(*c))
+        .collect();
+    if glyphs.is_empty() {
+        return Ok(AtlasUpdate::default());
+    }
+
+    let mut glyph_bounding_boxes: Vec<GlyphBoundingBoxData> = Vec::with_capacity(glyphs.len());
+    store_and_sort_by_area(&mut glyph_bounding_boxes, &face, &glyphs, args, overrides);
+
+    let new_glyphs: Vec<NewGlyph> = glyph_bounding_boxes
+        .iter()
+        .map(|glyph_bounding_box| {
+            let (width, height) = glyph_bounding_box.get_scaled_glyph_dimensions_no_padding();
+            NewGlyph {
+                glyph_data: glyph_bounding_box.create_glyph_data(&face, asset.ascender),
+                image: generate_glyph_image(&face, glyph_bounding_box, args),
+                width: width.max(0) as u32,
+                height: height.max(0) as u32,
+            }
+        })
+        .collect();
+
+    let (width, height) = (asset.atlas.width, asset.atlas.height);
+    let existing: Vec<Placement> = asset
+        .glyphs
+        .iter()
+        .map(|glyph| to_placement(glyph, asset))
+        .collect();
+
+    let padding = args.add_padding(0) as u32;
+    let occupied: Vec<(u32, u32, u32, u32)> = existing
+        .iter()
+        .filter(|placement| placement.width > 0 && placement.height > 0)
+        .map(|placement| {
+            let (x, y) = (placement.x, placement.y);
+            (x, y, placement.width + padding, placement.height + padding)
+        })
+        .collect();
+
+    // Try the free space first, then grow the atlas, and finally repack every glyph.
+    let widest = new_glyphs
+        .iter()
+        .map(|glyph| glyph.width + padding)
+        .max()
+        .unwrap_or(0);
+    // The free space includes the holes left below shorter glyphs, which a skyline cannot reach.
+    let mut update = AtlasUpdate::default();
+    let mut free_space = MaxRectsPacker::from_rects(width, height, &occupied);
+    let (positions, existing_positions, grown) =
+        match place_all(&mut free_space, &new_glyphs, padding) {
+            Some(positions) => (positions, existing.clone(), None),
+            None if widest <= width => {
+                debug!("The new glyphs did not fit, growing the atlas.");
+                let mut packer = SkylinePacker::from_rects(width, u32::MAX, &occupied);
+                let positions = place_all(&mut packer, &new_glyphs, padding)
+                    .expect("The glyphs should fit in an atlas without a max height.");
+                update.resized = true;
+                (positions, existing.clone(), Some(packer))
+            }
+            None => {
+                debug!("A new glyph is wider than the atlas, repacking every glyph.");
+                let mut packer =
+                    SkylinePacker::new(width.max(widest.next_power_of_two()), u32::MAX);
+                let existing_positions = repack_existing(&mut packer, &existing, padding);
+                let positions = place_all(&mut packer, &new_glyphs, padding)
+                    .expect("The glyphs should fit in an atlas without a max height.");
+                update.resized = true;
+                update.repacked = true;
+                (positions, existing_positions, Some(packer))
+            }
+        };
+
+    let (new_width, new_height) = if let Some(packer) = grown {
+        let mut new_height = packer.used_height().max(height);
+        if args.scale_texture_to_po2 {
+            new_height = new_height.next_power_of_two();
+        }
+        if args.block_align {
+            new_height = align_to_block(new_height as i32) as u32;
+        }
+
+        // Growing keeps the width, so the existing rows stay where they are.
+        let new_width = match (update.repacked, args.block_align) {
+            (true, true) => align_to_block(packer.width() as i32) as u32,
+            (true, false) => packer.width(),
+            (false, _) => width,
+        };
+        (new_width, new_height)
+    } else {
+        (width, height)
+    };
+
+    // Existing pixels only need to be copied when they move, since growing the height appends
+    // rows to the end of the atlas.
+    let pixel_size = 4 * asset.atlas.channel_size();
+    let new_size = new_width as usize * new_height as usize * pixel_size;
+    let mut data = if update.repacked {
+        let mut data = vec![0; new_size];
+        for (old, new) in existing.iter().zip(&existing_positions) {
+            copy_rect(
+                &asset.atlas.data,
+                width,
+                &mut data,
+                new_width,
+                old,
+                new,
+                pixel_size,
+            );
+        }
+        data
+    } else {
+        let mut data = std::mem::take(&mut asset.atlas.data);
+        data.resize(new_size, 0);
+        data
+    };
+
+    let dimensions = Vector2 {
+        x: new_width as i32,
+        y: new_height as i32,
+    };
+    let uv_space = asset.settings.uv_space;
+    for (glyph, placement) in asset.glyphs.iter_mut().zip(&existing_positions) {
+        let old_uvs = glyph.uvs();
+        *glyph = with_placement(*glyph, placement, dimensions, uv_space);
+        if glyph.uvs() != old_uvs {
+            update.moved.push(MovedGlyph {
+                unicode: glyph.unicode,
+                glyph_id: glyph.glyph_id,
+                old_uvs,
+                new_uvs: glyph.uvs(),
+            });
+        }
+    }
+
+    for (glyph, placement) in new_glyphs.iter().zip(&positions) {
+        if let Some(image) = &glyph.image {
+            let (precision, channel_size) = (asset.atlas.precision, asset.atlas.channel_size());
+            write_image(
+                &mut data,
+                new_width,
+                placement,
+                image,
+                precision,
+                channel_size,
+            );
+        }
+        update.added.push(glyph.glyph_data.unicode);
+        asset.glyphs.push(with_placement(
+            glyph.glyph_data,
+            placement,
+            dimensions,
+            uv_space,
+        ));
+    }
+    asset
+        .glyphs
+        .sort_unstable_by_key(|glyph| (glyph.unicode, glyph.glyph_id));
+
+    // The new glyphs may be kerned against the existing glyphs.
+    let kerning_glyphs: Vec<(char, GlyphId)> = asset
+        .glyphs
+        .iter()
+        .filter_map(|glyph| char::from_u32(glyph.unicode as u32))
+        .filter(|c| *c != '\0')
+        .filter_map(|c| face.glyph_index(c).map(|glyph_index| (c, glyph_index)))
+        .collect();
+    asset.kerning = collect_kerning_pairs(&face, &kerning_glyphs);
+
+    asset.atlas.width = new_width;
+    asset.atlas.height = new_height;
+    asset.atlas_hash = Some(hash_atlas(&data));
+    asset.atlas.data = data;
+
+    debug!(
+        "Added {} glyphs, moved {} glyphs.",
+        update.added.len(),
+        update.moved.len()
+    );
+    Ok(update)
+}
+
+/// Places every new glyph with padding, returning None if any glyph does not fit.
+fn place_all(
+    packer: &mut impl Packer,
+    glyphs: &[NewGlyph],
+    padding: u32,
+) -> Option<Vec<Placement>> {
+    glyphs
+        .iter()
+        .map(|glyph| place(packer, glyph.width, glyph.height, padding))
+        .collect()
+}
+
+/// Places the existing glyphs into an empty packer from the tallest to the shortest glyph,
+/// returning the new placement of each glyph in its original order.
+fn repack_existing(
+    packer: &mut SkylinePacker,
+    existing: &[Placement],
+    padding: u32,
+) -> Vec<Placement> {
+    let mut order: Vec<usize> = (0..existing.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse((existing[i].height, existing[i].width)));
+
+    let mut positions = existing.to_vec();
+    for i in order {
+        let Placement { width, height, .. } = existing[i];
+        positions[i] = place(packer, width, height, padding)
+            .expect("The glyphs should fit in an atlas without a max height.");
+    }
+    positions
+}
+
+/// Places a single glyph with padding. Glyphs without pixels do not take up any space.
+pub(super) fn place(
+    packer: &mut impl Packer,
+    width: u32,
+    height: u32,
+    padding: u32,
+) -> Option<Placement> {
+    if width == 0 || height == 0 {
+        return Some(Placement {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        });
+    }
+
+    packer
+        .insert(width + padding, height + padding)
+        .map(|(x, y)| Placement {
+            x,
+            y,
+            width,
+            height,
+        })
+}
+
+/// Converts the glyph's uvs back into its rect in atlas pixels.
+fn to_placement(glyph: &GlyphData, asset: &FontAsset) -> Placement {
+    let (x_min, y_min, x_max, y_max) = asset.settings.uv_space.to_texture_rect(glyph.uvs());
+    let (width, height) = (asset.atlas.width as f32, asset.atlas.height as f32);
+
+    let (x, y) = (
+        (x_min * width).round() as u32,
+        (y_min * height).round() as u32,
+    );
+    Placement {
+        x,
+        y,
+        width: ((x_max * width).round() as u32).saturating_sub(x),
+        height: ((y_max * height).round() as u32).saturating_sub(y),
+    }
+}
+
+/// Sets the glyph's uvs to its placement in an atlas with the dimensions.
+pub(super) fn with_placement(
+    glyph: GlyphData,
+    placement: &Placement,
+    dimensions: Vector2<i32>,
+    uv_space: UVSpace,
+) -> GlyphData {
+    let start = Vector2 {
+        x: placement.x as i32,
+        y: placement.y as i32,
+    };
+    let end = Vector2 {
+        x: (placement.x + placement.width) as i32,
+        y: (placement.y + placement.height) as i32,
+    };
+    glyph.with_uvs(start, end, dimensions, uv_space)
+}
+
+/// Copies the pixels of a glyph from one atlas to another, row by row.
+fn copy_rect(
+    src: &[u8],
+    src_width: u32,
+    dst: &mut [u8],
+    dst_width: u32,
+    from: &Placement,
+    to: &Placement,
+    pixel_size: usize,
+) {
+    let row_size = from.width as usize * pixel_size;
+    for row in 0..from.height as usize {
+        let src_start =
+            ((from.y as usize + row) * src_width as usize + from.x as usize) * pixel_size;
+        let dst_start = ((to.y as usize + row) * dst_width as usize + to.x as usize) * pixel_size;
+        dst[dst_start..dst_start + row_size].copy_from_slice(&src[src_start..src_start + row_size]);
+    }
+}
+
+/// Writes the generated glyph into the atlas, converting each channel to the atlas' precision.
+pub(super) fn write_image(
+    data: &mut [u8],
+    atlas_width: u32,
+    placement: &Placement,
+    image: &ImageBuffer<Rgba<f32>, Vec<f32>>,
+    precision: AtlasPrecision,
+    channel_size: usize,
+) {
+    let width = image.width().min(placement.width);
+    let height = image.height().min(placement.height);
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = image.get_pixel(x, y);
+            let start = ((placement.y + y) as usize * atlas_width as usize
+                + (placement.x + x) as usize)
+                * 4
+                * channel_size;
+
+            for (i, channel) in pixel.0.iter().enumerate() {
+                let offset = start + i * channel_size;
+                match precision {
+                    AtlasPrecision::Bits8 => data[offset] = quantize_u8(*channel),
+                    AtlasPrecision::Bits16 => data[offset..offset + 2]
+                        .copy_from_slice(&quantize_u16(*channel).to_le_bytes()),
+                    AtlasPrecision::Float32 => {
+                        data[offset..offset + 4].copy_from_slice(&channel.to_le_bytes())
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use super::args::Args;
+use super::font_data::FontData;
+use super::options::BuildOptions;
+use super::Builder;
+use log::error;
+use std::ffi::OsString;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// The state of a generation job.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    /// The glyphs are still being generated.
+    Running,
+    /// The atlas was written and the font data can be taken.
+    Completed,
+    /// The job stopped early because it was cancelled.
+    Cancelled,
+    /// The generation panicked, usually because the font could not be parsed.
+    Failed,
+}
+
+/// A flag shared between a job and the builder running it, which the builder checks between
+/// glyphs to stop early.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Requests the work holding this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Was cancel called on this token or any of its clones?
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The status of a job, and the builder once it completed, which is packaged into font data
+/// when the result is taken.
+struct JobState {
+    status: JobStatus,
+    builder: Option<Builder>,
+}
+
+/// Generates an atlas on the rayon thread pool, so the caller stays responsive while the glyphs
+/// are generated. Dropping the job cancels it, though it may keep running briefly.
+pub struct GenerationJob {
+    state: Arc<(Mutex<JobState>, Condvar)>,
+    cancellation: CancellationToken,
+}
+
+impl GenerationJob {
+    /// Starts generating the atlas in the background.
+    ///
+    /// # Arguments
+    ///
+    /// * `font_path` - The path to the font
+    /// * `atlas_path` - The path to the texture atlas to generate
+    /// * `chars_to_generate` - The characters to generate the glyphs for
+    /// * `args` - Generator params
+    /// * `options` - The optional settings of the build, whose progress is reported from the
+    ///   background threads. The job replaces the cancellation token with its own.
+    pub fn start(
+        font_path: OsString,
+        atlas_path: PathBuf,
+        chars_to_generate: String,
+        args: Args,
+        options: BuildOptions,
+    ) -> Self {
+        let state = Arc::new((
+            Mutex::new(JobState {
+                status: JobStatus::Running,
+                builder: None,
+            }),
+            Condvar::new(),
+        ));
+        let cancellation = CancellationToken::default();
+
+        let (job_state, job_cancellation) = (state.clone(), cancellation.clone());
+        let options = options.with_cancellation(cancellation.clone());
+        rayon::spawn(move || {
+            let generated = catch_unwind(AssertUnwindSafe(|| {
+                let mut builder = Builder::from_font_path_with_options(
+                    &font_path,
+                    chars_to_generate,
+                    &args,
+                    options,
+                );
+                builder
+                    .prepare_workload(args.thread_count as usize)
+                    .build_atlas(&atlas_path);
+                builder
+            }));
+
+            // A cancel which arrives after the atlas was written does not discard it.
+            let (lock, finished) = &*job_state;
+            let mut state = lock.lock().unwrap();
+            match generated {
+                Ok(builder) if builder.is_atlas_written() || !job_cancellation.is_cancelled() => {
+                    state.status = JobStatus::Completed;
+                    state.builder = Some(builder);
+                }
+                Ok(_) => state.status = JobStatus::Cancelled,
+                Err(_) => {
+                    error!("The generation job panicked.");
+                    state.status = JobStatus::Failed;
+                }
+            }
+            finished.notify_all();
+        });
+
+        Self {
+            state,
+            cancellation,
+        }
+    }
+
+    /// Returns the current state of the job without blocking.
+    pub fn status(&self) -> JobStatus {
+        self.state.0.lock().unwrap().status
+    }
+
+    /// Blocks until the job stops running and returns its final state.
+    pub fn wait(&self) -> JobStatus {
+        let (lock, finished) = &*self.state;
+        let state = finished
+            .wait_while(lock.lock().unwrap(), |state| {
+                state.status == JobStatus::Running
+            })
+            .unwrap();
+        state.status
+    }
+
+    /// Requests the job to stop. The job checks for cancellation between glyphs, so it may keep
+    /// running briefly, and the atlas is not written once cancelled.
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Returns the font data once the job completed. The result can only be taken once.
+    pub fn take_result(&self) -> Option<FontData> {
+        let builder = self.state.0.lock().unwrap().builder.take()?;
+        Some(builder.package_font_data())
+    }
+}
+
+impl Drop for GenerationJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CancellationToken, GenerationJob, JobStatus};
+    use crate::msdf_impl::{args::Args, options::BuildOptions, Builder};
+    use std::ffi::OsStr;
+    use std::fs::remove_file;
+
+    const FONT_PATH: &str = "testing-resources/Roboto-Medium.ttf";
+
+    fn create_args() -> Args {
+        Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0)
+            .with_padding(10)
+    }
+
+    #[test]
+    fn completes_job_in_background() {
+        let atlas_path = std::env::temp_dir().join("msdf-atlas-completes-job.png");
+        let job = GenerationJob::start(
+            FONT_PATH.into(),
+            atlas_path.clone(),
+            "ABC".to_string(),
+            create_args(),
+            BuildOptions::default(),
+        );
+
+        assert_eq!(job.wait(), JobStatus::Completed);
+        assert_eq!(job.status(), JobStatus::Completed);
+        assert!(atlas_path.exists());
+
+        let font_data = job.take_result().unwrap();
+        assert_eq!(font_data.glyphs().len(), 3);
+        assert!(
+            job.take_result().is_none(),
+            "The result can only be taken once."
+        );
+        let _ = remove_file(atlas_path);
+    }
+
+    #[test]
+    fn cancelled_builder_skips_atlas() {
+        let atlas_path = std::env::temp_dir().join("msdf-atlas-cancelled-builder.png");
+        let _ = remove_file(&atlas_path);
+
+        let cancellation = CancellationToken::default();
+        cancellation.cancel();
+        let mut builder = Builder::from_font_path_with_options(
+            OsStr::new(FONT_PATH),
+            "ABC".to_string(),
+            &create_args(),
+            BuildOptions::default().with_cancellation(cancellation),
+        );
+        assert!(builder.glyph_bounding_boxes.is_empty());
+
+        builder.prepare_workload(1).build_atlas(&atlas_path);
+        assert!(
+            !atlas_path.exists(),
+            "A cancelled build should not be saved."
+        );
+        assert!(!builder.is_atlas_written());
+    }
+
+    #[test]
+    fn cancelling_after_the_atlas_is_written_keeps_it() {
+        let atlas_path = std::env::temp_dir().join("msdf-atlas-cancelled-late.png");
+        let cancellation = CancellationToken::default();
+        let mut builder = Builder::from_font_path_with_options(
+            OsStr::new(FONT_PATH),
+            "ABC".to_string(),
+            &create_args(),
+            BuildOptions::default().with_cancellation(cancellation.clone()),
+        );
+        builder.prepare_workload(1).build_atlas(&atlas_path);
+        cancellation.cancel();
+        assert!(
+            builder.is_atlas_written(),
+            "The atlas was written before the build was cancelled."
+        );
+        let _ = remove_file(atlas_path);
+    }
+
+    #[test]
+    fn dropping_the_job_cancels_it() {
+        let atlas_path = std::env::temp_dir().join("msdf-atlas-dropped-job.png");
+        let job = GenerationJob::start(
+            FONT_PATH.into(),
+            atlas_path.clone(),
+            "ABC".to_string(),
+            create_args(),
+            BuildOptions::default(),
+        );
+        let cancellation = job.cancellation.clone();
+        drop(job);
+        assert!(cancellation.is_cancelled());
+        let _ = remove_file(atlas_path);
+    }
+}
@@ -0,0 +1,256 @@
+use log::debug;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use ttf_parser::gpos::{PairAdjustment, PositioningSubtable};
+use ttf_parser::kern::Format;
+use ttf_parser::{Face, GlyphId, Tag};
+
+/// The horizontal adjustment applied between two glyphs when one follows the other.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KerningPair {
+    /// The unicode of the glyph on the left.
+    pub left: i32,
+    /// The unicode of the glyph on the right.
+    pub right: i32,
+    /// The adjustment to the left glyph's advance in font units.
+    pub advance: f32,
+}
+
+/// The accumulated advance adjustment of each pair of unicodes.
+type Adjustments = HashMap<(char, char), f32>;
+
+/// Collects the kerning between every pair of generated glyphs, sorted by the left and then the
+/// right unicode. Pair adjustments from the GPOS table are preferred, and the legacy kern table is
+/// only read when the font has no GPOS pair adjustments.
+///
+/// # Arguments
+///
+/// * `face` - The font face to read the kerning from
+/// * `glyphs` - The unicode and glyph index of every generated glyph
+pub fn collect_kerning_pairs(face: &Face, glyphs: &[(char, GlyphId)]) -> Vec<KerningPair> {
+    let mut adjustments = collect_gpos_pairs(face, glyphs);
+    if adjustments.is_empty() {
+        adjustments = collect_kern_pairs(face, glyphs);
+    }
+
+    let mut pairs: Vec<KerningPair> = adjustments
+        .into_iter()
+        .filter(|(_, advance)| *advance != 0.0)
+        .map(|((left, right), advance)| KerningPair {
+            left: left as i32,
+            right: right as i32,
+            advance,
+        })
+        .collect();
+    pairs.sort_unstable_by_key(|pair| (pair.left, pair.right));
+    debug!("total kerning pairs stored: {}", pairs.len());
+    pairs
+}
+
+/// The chars of every generated glyph index, since several chars may share a glyph.
+type GlyphChars = HashMap<GlyphId, Vec<char>>;
+
+/// Groups the generated chars by their glyph index.
+fn group_by_glyph(glyphs: &[(char, GlyphId)]) -> GlyphChars {
+    let mut chars = GlyphChars::new();
+    for (c, glyph_index) in glyphs {
+        chars.entry(*glyph_index).or_default().push(*c);
+    }
+    chars
+}
+
+/// Reads the horizontal kerning subtables of the kern table. Subtables with a state machine or
+/// variations are skipped.
+fn collect_kern_pairs(face: &Face, glyphs: &[(char, GlyphId)]) -> Adjustments {
+    let mut pairs = Adjustments::new();
+    let table = match face.tables().kern {
+        Some(table) => table,
+        None => return pairs,
+    };
+
+    let chars = group_by_glyph(glyphs);
+    for subtable in table.subtables {
+        if !subtable.horizontal || subtable.variable || subtable.has_state_machine {
+            continue;
+        }
+
+        match &subtable.format {
+            Format::Format0(ordered) => {
+                for pair in ordered.pairs {
+                    if let (Some(lefts), Some(rights)) =
+                        (chars.get(&pair.left()), chars.get(&pair.right()))
+                    {
+                        push_pairs(&mut pairs, lefts, rights, pair.value);
+                    }
+                }
+            }
+            // The class tables of the other formats are not exposed, so every pair is looked up.
+            _ => {
+                for (left_index, lefts) in &chars {
+                    for (right_index, rights) in &chars {
+                        if let Some(advance) = subtable.glyphs_kerning(*left_index, *right_index) {
+                            push_pairs(&mut pairs, lefts, rights, advance);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// Reads the pair adjustment subtables of the lookups the `kern` feature applies. Only the first
+/// subtable that covers a pair is applied, following how a shaper resolves a lookup.
+fn collect_gpos_pairs(face: &Face, glyphs: &[(char, GlyphId)]) -> Adjustments {
+    let mut pairs = Adjustments::new();
+    let table = match face.tables().gpos {
+        Some(table) => table,
+        None => return pairs,
+    };
+
+    // Each script and language system may list its own kern feature sharing the same lookups,
+    // so every lookup is only applied once.
+    let lookup_indices: BTreeSet<u16> = table
+        .features
+        .into_iter()
+        .filter(|feature| feature.tag == Tag::from_bytes(b"kern"))
+        .flat_map(|feature| feature.lookup_indices)
+        .collect();
+
+    let chars = group_by_glyph(glyphs);
+    for lookup in lookup_indices
+        .into_iter()
+        .filter_map(|index| table.lookups.get(index))
+    {
+        // A class based subtable covers every pair of its left glyphs, while a glyph based
+        // subtable only covers the pairs it lists.
+        let mut covered_lefts: HashSet<GlyphId> = HashSet::new();
+        let mut covered_pairs: HashSet<(GlyphId, GlyphId)> = HashSet::new();
+
+        for subtable in lookup.subtables.into_iter::<PositioningSubtable>() {
+            match subtable {
+                PositioningSubtable::Pair(PairAdjustment::Format1 { coverage, sets }) => {
+                    for (left_index, lefts) in &chars {
+                        if covered_lefts.contains(left_index) {
+                            continue;
+                        }
+                        let set = match coverage.get(*left_index).and_then(|i| sets.get(i)) {
+                            Some(set) => set,
+                            None => continue,
+                        };
+
+                        for (right_index, rights) in &chars {
+                            if let Some((record, _)) = set.get(*right_index) {
+                                if covered_pairs.insert((*left_index, *right_index)) {
+                                    push_pairs(&mut pairs, lefts, rights, record.x_advance);
+                                }
+                            }
+                        }
+                    }
+                }
+                PositioningSubtable::Pair(PairAdjustment::Format2 {
+                    coverage,
+                    classes,
+                    matrix,
+                }) => {
+                    let mut left_classes: HashMap<u16, Vec<GlyphId>> = HashMap::new();
+                    let mut right_classes: HashMap<u16, Vec<GlyphId>> = HashMap::new();
+                    for glyph_index in chars.keys() {
+                        if !covered_lefts.contains(glyph_index) && coverage.contains(*glyph_index) {
+                            let class = classes.0.get(*glyph_index);
+                            left_classes.entry(class).or_default().push(*glyph_index);
+                        }
+                        let class = classes.1.get(*glyph_index);
+                        right_classes.entry(class).or_default().push(*glyph_index);
+                    }
+
+                    for (left_class, left_indices) in &left_classes {
+                        for (right_class, right_indices) in &right_classes {
+                            let advance = match matrix.get((*left_class, *right_class)) {
+                                Some((record, _)) if record.x_advance != 0 => record.x_advance,
+                                _ => continue,
+                            };
+
+                            for left_index in left_indices {
+                                for right_index in right_indices {
+                                    if !covered_pairs.contains(&(*left_index, *right_index)) {
+                                        let (lefts, rights) =
+                                            (&chars[left_index], &chars[right_index]);
+                                        push_pairs(&mut pairs, lefts, rights, advance);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    covered_lefts.extend(left_classes.into_values().flatten());
+                }
+                _ => {}
+            }
+        }
+    }
+    pairs
+}
+
+/// Accumulates the adjustment for every char of the left glyph followed by every char of the
+/// right glyph, since multiple subtables or lookups may kern the same pair.
+#[inline(always)]
+fn push_pairs(pairs: &mut Adjustments, lefts: &[char], rights: &[char], advance: i16) {
+    for left in lefts {
+        for right in rights {
+            *pairs.entry((*left, *right)).or_insert(0.0) += advance as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use ttf_parser::{Face, GlyphId};
+
+    use super::{collect_gpos_pairs, collect_kern_pairs};
+
+    const FONT_PATH: &str = "testing-resources/Roboto-Medium.ttf";
+
+    #[test]
+    fn reads_the_kern_feature() {
+        let font = fs::read(FONT_PATH).unwrap();
+        let face = Face::parse(&font, 0).unwrap();
+        let glyphs: Vec<(char, GlyphId)> = (' '..='~')
+            .filter_map(|c| face.glyph_index(c).map(|glyph_index| (c, glyph_index)))
+            .collect();
+
+        let pairs = collect_gpos_pairs(&face, &glyphs);
+        for (left, right) in [('A', 'V'), ('V', 'A'), ('T', 'o'), ('L', 'T')] {
+            assert!(
+                pairs.get(&(left, right)).copied().unwrap_or(0.0) < 0.0,
+                "{}{} should be kerned closer together.",
+                left,
+                right
+            );
+        }
+        assert_eq!(
+            pairs.get(&('H', 'H')).copied().unwrap_or(0.0),
+            0.0,
+            "HH should not be kerned."
+        );
+
+        for ((left, right), advance) in collect_kern_pairs(&face, &glyphs) {
+            let left_index = face.glyph_index(left).unwrap();
+            let right_index = face.glyph_index(right).unwrap();
+            let expected = face
+                .tables()
+                .kern
+                .unwrap()
+                .subtables
+                .into_iter()
+                .filter_map(|subtable| subtable.glyphs_kerning(left_index, right_index))
+                .map(|advance| advance as f32)
+                .sum::<f32>();
+            assert_eq!(
+                advance, expected,
+                "{}{} was kerned incorrectly.",
+                left, right
+            );
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use super::font_data::FontData;
+
+/// Horizontally aligns each line within the max width of the layout.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// The parameters used to lay out text.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LayoutArgs {
+    /// The height of an em in screen pixels.
+    pub font_size: f32,
+    /// The width in screen pixels that lines wrap at. Lines never wrap when this is 0.
+    pub max_width: f32,
+    pub alignment: TextAlignment,
+    /// The distance between tab stops, measured in spaces.
+    pub tab_size: u32,
+}
+
+impl Default for LayoutArgs {
+    /// Lays out text at 32 pixels without wrapping, aligned to the left with tab stops every 4
+    /// spaces.
+    fn default() -> Self {
+        Self {
+            font_size: 32.0,
+            max_width: 0.0,
+            alignment: TextAlignment::Left,
+            tab_size: 4,
+        }
+    }
+}
+
+impl LayoutArgs {
+    /// The height of an em in screen pixels.
+    ///
+    /// # Arguments
+    ///
+    /// * `font_size` - The font size in screen pixels
+    #[allow(dead_code)]
+    pub fn with_font_size(mut self, font_size: f32) -> LayoutArgs {
+        self.font_size = font_size;
+        self
+    }
+
+    /// The width that lines wrap at. Lines break between words when possible, and otherwise
+    /// between characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_width` - The max width of a line in screen pixels, or 0 to never wrap
+    #[allow(dead_code)]
+    pub fn with_max_width(mut self, max_width: f32) -> LayoutArgs {
+        self.max_width = max_width;
+        self
+    }
+
+    /// How each line is aligned within the max width, or within the widest line when lines
+    /// do not wrap.
+    ///
+    /// # Arguments
+    ///
+    /// * `alignment` - The horizontal alignment
+    #[allow(dead_code)]
+    pub fn with_alignment(mut self, alignment: TextAlignment) -> LayoutArgs {
+        self.alignment = alignment;
+        self
+    }
+
+    /// The distance between tab stops.
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_size` - The distance between tab stops, measured in spaces
+    #[allow(dead_code)]
+    pub fn with_tab_size(mut self, tab_size: u32) -> LayoutArgs {
+        self.tab_size = tab_size;
+        self
+    }
+}
+
+/// A positioned glyph. The screen rect is in pixels where 0, 0 is the top left of the first line
+/// and y grows downwards. The uv rect is copied from the glyph, in the uv space it was generated
+/// with.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphQuad {
+    pub unicode: i32,
+    pub x_min: f32,
+    pub y_min: f32,
+    pub x_max: f32,
+    pub y_max: f32,
+    pub uv_x: f32,
+    pub uv_y: f32,
+    pub uv_z: f32,
+    pub uv_w: f32,
+}
+
+/// Returns the distance from the start of the line to the right edge of its last glyph.
+#[inline]
+fn line_width(line: &[GlyphQuad]) -> f32 {
+    line.iter()
+        .fold(0.0_f32, |width, quad| width.max(quad.x_max))
+}
+
+/// Lays out the text into positioned quads. Newlines start a new line, tabs advance to the next
+/// tab stop and pairs of glyphs are kerned. Whitespace and glyphs without a bitmap advance the
+/// pen without producing a quad, and chars without a glyph are skipped.
+///
+/// # Arguments
+///
+/// * `font_data` - The metrics and glyphs of the generated font
+/// * `text` - The text to lay out
+/// * `args` - Layout params
+pub fn layout_text(font_data: &FontData, text: &str, args: &LayoutArgs) -> Vec<GlyphQuad> {
+    let scale = args.font_size / font_data.units_per_em.max(1) as f32;
+    let line_advance = (font_data.ascender - font_data.descender) as f32 * scale;
+    let space_advance = font_data
+        .get_glyph(' ' as i32)
+        .map_or(0.0, |glyph| glyph.advance * scale);
+    let tab_stop = space_advance * args.tab_size as f32;
+    let wraps = args.max_width > 0.0;
+
+    // Each line is positioned relative to the top left of the line.
+    let mut lines: Vec<Vec<GlyphQuad>> = Vec::with_capacity(1);
+    let mut line: Vec<GlyphQuad> = Vec::new();
+    let mut pen_x = 0.0;
+    let mut previous: Option<char> = None;
+    // The index of the first quad after the last whitespace, and the pen position it starts at.
+    let mut word_start: Option<(usize, f32)> = None;
+
+    for c in text.chars() {
+        if c == '\n' {
+            lines.push(std::mem::take(&mut line));
+            (pen_x, previous, word_start) = (0.0, None, None);
+            continue;
+        }
+
+        if c == '\t' {
+            if tab_stop > 0.0 {
+                pen_x = ((pen_x / tab_stop).floor() + 1.0) * tab_stop;
+            }
+            previous = None;
+            word_start = Some((line.len(), pen_x));
+            continue;
+        }
+
+        let glyph = match font_data.get_glyph(c as i32) {
+            Some(glyph) => glyph,
+            None => continue,
+        };
+
+        if let Some(left) = previous {
+            pen_x += font_data.get_kerning(left as i32, c as i32) * scale;
+        }
+        previous = Some(c);
+
+        let (metrics_x, metrics_y) = glyph.metrics();
+        if c.is_whitespace() || !glyph.has_bitmap || metrics_x <= 0.0 || metrics_y <= 0.0 {
+            pen_x += glyph.advance * scale;
+            word_start = Some((line.len(), pen_x));
+            continue;
+        }
+
+        let (bearings_x, bearings_y) = glyph.bearings();
+        let x_max = pen_x + (bearings_x + metrics_x) * scale;
+
+        if wraps && x_max > args.max_width && !line.is_empty() {
+            let mut next: Vec<GlyphQuad> = Vec::new();
+            let shift = match word_start {
+                // Move the current word onto the next line.
+                Some((start, origin)) if start > 0 && start < line.len() => {
+                    next = line.split_off(start);
+                    origin
+                }
+                // The word starts the line or was just started, so only move this glyph.
+                Some((start, origin)) if start == line.len() => origin,
+                // The word is wider than the line, so break it before this glyph.
+                _ => pen_x,
+            };
+
+            for quad in next.iter_mut() {
+                quad.x_min -= shift;
+                quad.x_max -= shift;
+            }
+            lines.push(std::mem::replace(&mut line, next));
+            pen_x -= shift;
+            word_start = Some((0, 0.0));
+        }
+
+        // The bearing is the distance from the ascender to the top of the glyph.
+        let x_min = pen_x + bearings_x * scale;
+        let y_min = -bearings_y * scale;
+        let (uv_x, uv_y, uv_z, uv_w) = glyph.uvs();
+        line.push(GlyphQuad {
+            unicode: glyph.unicode,
+            x_min,
+            y_min,
+            x_max: x_min + metrics_x * scale,
+            y_max: y_min + metrics_y * scale,
+            uv_x,
+            uv_y,
+            uv_z,
+            uv_w,
+        });
+        pen_x += glyph.advance * scale;
+    }
+    lines.push(line);
+
+    position_lines(lines, args, line_advance)
+}
+
+/// Aligns each line and moves it below the previous line, flattening the lines into one array
+/// of quads.
+///
+/// # Arguments
+///
+/// * `lines` - The quads of each line, positioned relative to the top left of the line
+/// * `args` - Layout params
+/// * `line_advance` - The distance between the top of each line in screen pixels
+pub(super) fn position_lines(
+    lines: Vec<Vec<GlyphQuad>>,
+    args: &LayoutArgs,
+    line_advance: f32,
+) -> Vec<GlyphQuad> {
+    let container_width = if args.max_width > 0.0 {
+        args.max_width
+    } else {
+        lines
+            .iter()
+            .fold(0.0_f32, |width, line| width.max(line_width(line)))
+    };
+
+    let mut quads: Vec<GlyphQuad> = Vec::with_capacity(lines.iter().map(Vec::len).sum());
+    for (i, line) in lines.into_iter().enumerate() {
+        let x_offset = match args.alignment {
+            TextAlignment::Left => 0.0,
+            TextAlignment::Center => (container_width - line_width(&line)) * 0.5,
+            TextAlignment::Right => container_width - line_width(&line),
+        };
+        let y_offset = i as f32 * line_advance;
+
+        quads.extend(line.into_iter().map(|mut quad| {
+            quad.x_min += x_offset;
+            quad.x_max += x_offset;
+            quad.y_min += y_offset;
+            quad.y_max += y_offset;
+            quad
+        }));
+    }
+    quads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layout_text, LayoutArgs, TextAlignment};
+    use crate::msdf_impl::{font_data::FontData, glyph_data::GlyphData, kerning::KerningPair};
+
+    /// A monospaced font with an em of 100 units where every glyph is 50 units wide.
+    fn font_data() -> FontData {
+        let glyphs: Vec<GlyphData> = " ABCDEFGHIJ"
+            .chars()
+            .map(|c| {
+                GlyphData::from_char(c)
+                    .with_advance(60)
+                    .with_metrics(50, 70)
+                    .with_bearings(5, -10)
+                    .with_bitmap(c != ' ')
+            })
+            .collect();
+        let kerning = vec![KerningPair {
+            left: 'A' as i32,
+            right: 'B' as i32,
+            advance: -20.0,
+        }];
+        FontData::new(60, 100, 80, -20, glyphs, kerning)
+    }
+
+    #[test]
+    fn wraps_words_and_aligns_lines() {
+        let font_data = font_data();
+        // 10 pixels per em, so each glyph advances by 6 pixels.
+        let args = LayoutArgs::default()
+            .with_font_size(10.0)
+            .with_max_width(20.0)
+            .with_alignment(TextAlignment::Right);
+
+        let quads = layout_text(&font_data, "CD EF\nG", &args);
+        assert_eq!(quads.len(), 5, "Spaces should not produce quads.");
+
+        let (e, f, g) = (quads[2], quads[3], quads[4]);
+        assert_eq!(e.unicode, 'E' as i32);
+        assert_eq!(
+            e.y_min,
+            quads[0].y_min + 10.0,
+            "EF should wrap onto the second line."
+        );
+        assert_eq!(
+            g.y_min,
+            e.y_min + 10.0,
+            "The newline should start a third line."
+        );
+        assert_eq!(f.x_max, 20.0, "Each line should be aligned to the right.");
+        assert_eq!(g.x_max, 20.0);
+
+        assert_eq!(
+            quads[0].y_min, 1.0,
+            "The glyph top should be 1 pixel below the ascender."
+        );
+        assert_eq!(quads[0].y_max, 8.0);
+    }
+
+    #[test]
+    fn applies_kerning_and_tab_stops() {
+        let font_data = font_data();
+        let args = LayoutArgs::default().with_font_size(10.0).with_tab_size(4);
+
+        let quads = layout_text(&font_data, "AB\tC", &args);
+        assert_eq!(quads[0].x_min, 0.5);
+        assert_eq!(
+            quads[1].x_min - quads[0].x_min,
+            4.0,
+            "B should be kerned 2 pixels closer to A."
+        );
+        assert_eq!(
+            quads[2].x_min, 24.5,
+            "C should start at the first tab stop after AB."
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::ffi::c_void;
+use std::sync::RwLock;
+
+/// The most verbose level of the records forwarded to the host.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl From<log::Level> for LogLevel {
+    fn from(value: log::Level) -> Self {
+        match value {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+/// Where records go when the host has not registered a callback.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFallback {
+    /// Drop the records.
+    Silent,
+    /// Print the records to stderr.
+    Stderr,
+}
+
+/// Receives the user data passed along with the callback, the level of the record, and the
+/// UTF8 encoded target and message with their lengths in bytes. The strings are not null
+/// terminated and are only valid during the call.
+pub type LogCallback = extern "C" fn(
+    user_data: *mut c_void,
+    level: LogLevel,
+    target: *const u8,
+    target_len: u32,
+    message: *const u8,
+    message_len: u32,
+);
+
+/// The destination of the records.
+struct LogSink {
+    callback: Option<LogCallback>,
+    /// Stored as an address, since the user data is only passed back to the callback.
+    user_data: usize,
+    fallback: LogFallback,
+    max_level: LogLevel,
+}
+
+/// Forwards every record to the callback registered by the host.
+struct HostLogger {
+    sink: RwLock<LogSink>,
+}
+
+static LOGGER: HostLogger = HostLogger {
+    sink: RwLock::new(LogSink {
+        callback: None,
+        user_data: 0,
+        fallback: LogFallback::Stderr,
+        max_level: LogLevel::Error,
+    }),
+};
+
+impl Log for HostLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // Copy the sink, so the callback can register another callback without deadlocking.
+        let (callback, user_data, fallback) = {
+            let sink = self.sink.read().unwrap();
+            (sink.callback, sink.user_data, sink.fallback)
+        };
+        match (callback, fallback) {
+            (Some(callback), _) => {
+                let (target, message) = (record.target(), record.args().to_string());
+                callback(
+                    user_data as *mut c_void,
+                    record.level().into(),
+                    target.as_ptr(),
+                    target.len() as u32,
+                    message.as_ptr(),
+                    message.len() as u32,
+                );
+            }
+            (None, LogFallback::Stderr) => {
+                eprintln!(
+                    "[{}] {}: {}",
+                    record.level(),
+                    record.target(),
+                    record.args()
+                );
+            }
+            (None, LogFallback::Silent) => {}
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger, unless the process already installed another logger.
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LOGGER.sink.read().unwrap().max_level.into());
+    }
+}
+
+/// Forwards the records up to the max level to the callback. Passing None for the callback
+/// sends the records to the fallback instead.
+///
+/// # Arguments
+///
+/// * `callback` - Receives each record, and must be safe to call from any thread
+/// * `user_data` - A pointer passed back to every invocation of the callback
+/// * `max_level` - The most verbose level to forward
+pub fn register_callback(
+    callback: Option<LogCallback>,
+    user_data: *mut c_void,
+    max_level: LogLevel,
+) {
+    let mut sink = LOGGER.sink.write().unwrap();
+    sink.callback = callback;
+    sink.user_data = user_data as usize;
+    drop(sink);
+    set_max_level(max_level);
+}
+
+/// Changes the most verbose level of the records which are forwarded.
+///
+/// # Arguments
+///
+/// * `max_level` - The most verbose level to forward
+pub fn set_max_level(max_level: LogLevel) {
+    init();
+    LOGGER.sink.write().unwrap().max_level = max_level;
+    log::set_max_level(max_level.into());
+}
+
+/// Changes where records go when no callback is registered.
+///
+/// # Arguments
+///
+/// * `fallback` - The destination of the records without a callback
+pub fn set_fallback(fallback: LogFallback) {
+    init();
+    LOGGER.sink.write().unwrap().fallback = fallback;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register_callback, LogLevel};
+    use log::{debug, error, warn};
+    use std::ffi::c_void;
+    use std::sync::Mutex;
+
+    const TARGET: &str = "forwards_records_to_callback";
+
+    static RECORDS: Mutex<Vec<(LogLevel, String)>> = Mutex::new(Vec::new());
+
+    extern "C" fn record(
+        _: *mut c_void,
+        level: LogLevel,
+        target: *const u8,
+        target_len: u32,
+        message: *const u8,
+        message_len: u32,
+    ) {
+        let (target, message) = unsafe {
+            (
+                std::slice::from_raw_parts(target, target_len as usize),
+                std::slice::from_raw_parts(message, message_len as usize),
+            )
+        };
+        // Other tests may log at the same time.
+        if target == TARGET.as_bytes() {
+            let message = String::from_utf8(message.to_vec()).unwrap();
+            RECORDS.lock().unwrap().push((level, message));
+        }
+    }
+
+    #[test]
+    fn forwards_records_to_callback() {
+        register_callback(Some(record), std::ptr::null_mut(), LogLevel::Warn);
+        error!(target: TARGET, "Failed to load {}", "A");
+        warn!(target: TARGET, "Skipped B");
+        debug!(target: TARGET, "Generated C");
+        register_callback(None, std::ptr::null_mut(), LogLevel::Error);
+        error!(target: TARGET, "Not forwarded");
+
+        let records = RECORDS.lock().unwrap();
+        assert_eq!(
+            *records,
+            vec![
+                (LogLevel::Error, "Failed to load A".to_string()),
+                (LogLevel::Warn, "Skipped B".to_string()),
+            ],
+            "Only records up to the max level should be forwarded."
+        );
+    }
+}
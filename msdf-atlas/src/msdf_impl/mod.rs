@@ -1,35 +1,73 @@
-use enums::ColorType;
+use containers::{write_dds, write_ktx2, ContainerWriter};
+use enums::{AtlasPrecision, ColorType, FillRule, TextureFormat};
+use field_cache::FieldCache;
+use font_asset::{hash_atlas, AtlasPixels, FontAsset, GenerationSettings, FONT_ASSET_EXTENSION};
 use font_data::FontData;
-use image::{ImageBuffer, Rgb, Rgba};
-use log::{debug, LevelFilter};
+use image::{
+    EncodableLayout, ImageBuffer, ImageFormat, Pixel, PixelWithColorType, Primitive, Rgb, Rgba,
+};
+use jobs::CancellationToken;
+use kerning::{collect_kerning_pairs, KerningPair};
+use log::{debug, error};
 use mint::Vector2;
 use msdf::{GlyphLoader, MSDFConfig, Projection, SDFTrait};
+use options::BuildOptions;
+use outline::Outline;
+use overrides::{GlyphOverrides, GlyphSettings};
+use progress::{ProgressPhase, ProgressReporter};
 use raw_img::{RawImage, RawImageView};
-use rayon::ThreadPoolBuilder;
-use simple_logging::log_to_file;
+use rayon::prelude::*;
+use scanline::FlatOutline;
+#[cfg(feature = "shaping")]
+use shaping::{append_shaped_glyphs, shape_text, ShapedRun};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Error, ErrorKind, Read};
 use std::path::Path;
 use std::str::Chars;
-use std::sync::{Arc, Mutex};
-use std::{fs::File, io::Read};
+use std::sync::atomic::{AtomicU32, Ordering};
 use ttf_parser::{Face, GlyphId, Rect};
 
 use crate::msdf_impl::args::Args;
 use crate::msdf_impl::glyph_data::GlyphData;
 
-use self::byte_buffer::ByteBuffer;
-
 pub mod args;
 pub mod byte_buffer;
+pub mod containers;
 pub mod enums;
+pub mod field_cache;
+pub mod font_asset;
 pub mod font_data;
+pub mod glyph_cache;
 pub mod glyph_data;
+#[cfg(test)]
+mod golden;
+pub mod incremental;
+pub mod jobs;
+pub mod kerning;
+pub mod layout;
+pub mod logging;
+pub mod options;
+pub mod outline;
+pub mod overrides;
+pub mod packer;
+pub mod preview;
+pub mod progress;
+pub mod quality;
 pub mod raw_img;
+mod sampling;
+pub mod scanline;
+#[cfg(feature = "shaping")]
+pub mod shaping;
 pub mod utils;
+pub mod validation;
 
 #[cfg(test)]
 use once_cell::sync::Lazy;
+#[cfg(test)]
+use std::sync::Mutex;
 
 #[cfg(test)]
 #[allow(dead_code)]
@@ -76,347 +114,785 @@ fn flush_chars() {
 #[cfg(not(test))]
 fn flush_chars() {}
 
-#[cfg(test)]
-fn config_log_file() {
-    let _ = log_to_file("font-metrics.log", LevelFilter::Debug);
-}
+/// The largest width or height of an atlas generated from untrusted input, which is also the
+/// largest texture most GPUs support.
+const MAX_ATLAS_DIMENSION: u32 = 16384;
 
-#[cfg(not(test))]
-fn config_log_file() {
-    let _ = log_to_file("font-metrics.log", LevelFilter::Error);
-}
+/// The generated field of a glyph, with 4 f32 channels per pixel.
+type GlyphImage = ImageBuffer<Rgba<f32>, Vec<f32>>;
 
+/// Places every glyph in the atlas up front, and only generates the fields while the atlas is
+/// composed. Each field is written into the atlas and dropped as soon as it is generated, so the
+/// memory used by the fields grows with the number of threads rather than the number of glyphs.
 pub struct Builder {
     pub glyph_buffer: Vec<GlyphData>,
     pub atlas_offsets: Vec<(i32, i32)>,
-    pub glyph_images: Vec<ImageBuffer<Rgba<f32>, Vec<f32>>>,
+    /// The glyphs to generate, in the same order as the atlas offsets.
+    pub glyph_bounding_boxes: Vec<GlyphBoundingBoxData>,
     pub thread_metadata: Vec<ThreadMetadata>,
     pub atlas_dimensions: (u32, u32),
 
+    /// The contents of the font file, which the fields are generated from.
+    font: Vec<u8>,
+    args: Args,
+    field_cache: Option<FieldCache>,
+
+    precision: AtlasPrecision,
+    texture_format: TextureFormat,
+    settings: GenerationSettings,
+    kerning: Vec<KerningPair>,
     ascender: i32,
     descender: i32,
     line_height: i32,
     units_per_em: u32,
+    progress: ProgressReporter,
+    cancellation: CancellationToken,
+    atlas_written: bool,
 }
 
 impl Builder {
     pub fn from_font_path(font_path: &OsStr, chars_to_generate: String, args: &Args) -> Self {
-        config_log_file();
-        let lossy_string = font_path.to_string_lossy();
-        let chars = chars_to_generate.chars();
-        let thread_metadata = Vec::with_capacity(8);
+        Self::from_font_path_with_options(
+            font_path,
+            chars_to_generate,
+            args,
+            BuildOptions::default(),
+        )
+    }
 
-        let glyph_capacity = chars_to_generate.len();
+    /// Generates the glyphs like from_font_path, with optional per glyph overrides, a field
+    /// cache, progress reports and cancellation. The generating and copying phases are reported
+    /// from the worker threads while the atlas is composed.
+    ///
+    /// # Arguments
+    ///
+    /// * `font_path` - The path to the font
+    /// * `chars_to_generate` - The characters to generate the glyphs for
+    /// * `args` - Generator params
+    /// * `options` - The optional settings of the build
+    pub fn from_font_path_with_options(
+        font_path: &OsStr,
+        chars_to_generate: String,
+        args: &Args,
+        options: BuildOptions,
+    ) -> Self {
+        logging::init();
+        match read_font_file(font_path) {
+            Some(buffer) => {
+                let glyphs = resolve_glyph_indices(
+                    &Face::parse(&buffer, 0).unwrap(),
+                    chars_to_generate.chars(),
+                );
+                Self::from_font(buffer, &glyphs, args, options)
+            }
+            None => Self::empty(args),
+        }
+    }
 
-        let mut atlas_offsets: Vec<(i32, i32)> = Vec::with_capacity(glyph_capacity);
-        let mut glyph_images: Vec<ImageBuffer<Rgba<f32>, Vec<f32>>> =
-            Vec::with_capacity(glyph_capacity);
-
-        if lossy_string.ends_with(".otf") || lossy_string.ends_with(".ttf") {
-            let mut buffer: Vec<u8> = Vec::new();
-            let mut file = File::options()
-                .read(true)
-                .write(false)
-                .open(font_path)
-                .unwrap();
-            let _ = file.read_to_end(&mut buffer);
-
-            let face = Face::parse(&buffer, 0).unwrap();
-            let ascender = face.ascender() as i32;
-            let descender = face.descender() as i32;
-            let line_height = ascender + descender;
-            let units_per_em = face.units_per_em() as u32;
-
-            let capacity = chars_to_generate.len();
-
-            let mut glyph_bounding_boxes: Vec<GlyphBoundingBoxData> = Vec::with_capacity(capacity);
-            let mut glyph_buffer: Vec<GlyphData> = Vec::with_capacity(capacity);
-
-            store_and_sort_by_area(&mut glyph_bounding_boxes, &face, chars);
-
-            let (max_width, max_height, line_heights) =
-                calculate_minimum_atlas_dimensions(&glyph_bounding_boxes, args);
-
-            let mut x_offset: i32 = 0;
-            let mut y_offset: i32 = 0;
-            let mut current_line_no = 0;
-
-            // TODO: It's very possible to mutlthread this
-            for glyph_bounding_box in &glyph_bounding_boxes {
-                let glyph_index = glyph_bounding_box.glyph_index;
-
-                let (scaled_glyph_width_padding, _) =
-                    glyph_bounding_box.get_scaled_glyph_dimensions_with_padding(args);
-                let (scaled_glyph_width, scaled_glyph_height) =
-                    glyph_bounding_box.get_scaled_glyph_dimensions_no_padding(args);
-
-                let horizontal_advance = face.glyph_hor_advance(glyph_index).unwrap_or(0);
-                let bearing_x = face.glyph_hor_side_bearing(glyph_index).unwrap();
-                let bearing_y = glyph_bounding_box.calculate_bearings_y(ascender);
-
-                let (width, height) = glyph_bounding_box.get_metrics();
-
-                let opt_shape = face.load_shape(glyph_index);
-                if opt_shape.is_some() {
-                    let shape = opt_shape.unwrap();
-                    let radians = args.get_radians();
-
-                    let colored_shape = match args.color_type {
-                        ColorType::Simple => shape.color_edges_simple(radians),
-                        ColorType::InkTrap => shape.color_edges_ink_trap(radians),
-                        ColorType::Distance => shape.color_edges_by_distance(radians),
-                    };
-
-                    let translation = {
-                        let this = &glyph_bounding_box;
-                        Vector2 {
-                            x: (-1.0 * this.rect.x_min as f64),
-                            y: (-1.0 * this.rect.y_min as f64),
-                        }
-                    };
-                    let projection = Projection {
-                        scale: args.get_scale(),
-                        translation,
-                    };
-
-                    let msdf_config: MSDFConfig = Default::default();
-                    let msdf_data = colored_shape.generate_mtsdf(
-                        scaled_glyph_width as u32,
-                        scaled_glyph_height as u32,
-                        args.range as f64,
-                        &projection,
-                        &msdf_config,
-                    );
+    /// Generates the glyphs from the contents of a font file. Unlike from_font_path, which panics
+    /// on fonts it cannot parse, every problem with the font or the args is returned as an
+    /// error, so a malformed font cannot crash the host.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - The contents of the font file
+    /// * `chars_to_generate` - The characters to generate the glyphs for
+    /// * `args` - Generator params
+    pub fn from_font_bytes(font: &[u8], chars_to_generate: &str, args: &Args) -> io::Result<Self> {
+        logging::init();
+        validate_args(args)?;
+
+        let face = Face::parse(font, 0)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        let glyphs = resolve_glyph_indices(&face, chars_to_generate.chars());
+        if glyphs.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "None of the characters exist in the font.",
+            ));
+        }
+        for &(c, glyph_index) in &glyphs {
+            validate_glyph_bounds(&face, c, glyph_index, args)?;
+        }
 
-                    let glyph_image: ImageBuffer<Rgba<f32>, Vec<f32>> = msdf_data.to_image();
-                    glyph_images.push(glyph_image);
-                } else {
-                    debug!(
-                        "Skipped {} due to no shape being generated for msdf.",
-                        glyph_bounding_box.unicode
+        let builder = Self::from_font(font.to_vec(), &glyphs, args, BuildOptions::default());
+        let (width, height) = builder.atlas_dimensions;
+        if width > MAX_ATLAS_DIMENSION || height > MAX_ATLAS_DIMENSION {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "The atlas would be {}x{}, which is larger than {}x{}.",
+                    width, height, MAX_ATLAS_DIMENSION, MAX_ATLAS_DIMENSION
+                ),
+            ));
+        }
+        Ok(builder)
+    }
+
+    /// Shapes the text and generates the glyphs it needs along with the characters, so scripts
+    /// which substitute glyphs based on their context, such as Arabic, Devanagari and Thai, can
+    /// be rendered. Glyphs which are not mapped to a single unicode, like ligatures, are stored
+    /// with a unicode of 0 and must be looked up by their glyph index. A font which cannot be
+    /// read or parsed produces a builder without any glyphs.
+    ///
+    /// # Arguments
+    ///
+    /// * `font_path` - The path to the font
+    /// * `chars_to_generate` - Additional characters to generate the glyphs for
+    /// * `text` - The text to shape, where each newline starts a new run
+    /// * `args` - Generator params
+    /// * `options` - The optional settings of the build
+    #[cfg(feature = "shaping")]
+    pub fn from_font_path_with_shaping(
+        font_path: &OsStr,
+        chars_to_generate: String,
+        text: &str,
+        args: &Args,
+        options: BuildOptions,
+    ) -> (Self, Vec<ShapedRun>) {
+        logging::init();
+        let buffer = match read_font_file(font_path) {
+            Some(buffer) => buffer,
+            None => return (Self::empty(args), Vec::new()),
+        };
+
+        let (runs, glyphs) = {
+            let face = match rustybuzz::Face::from_slice(&buffer, 0) {
+                Some(face) => face,
+                None => {
+                    error!(
+                        "Failed to parse the font at {}.",
+                        font_path.to_string_lossy()
                     );
+                    return (Self::empty(args), Vec::new());
                 }
+            };
+            let runs = shape_text(&face, text);
+            let mut glyphs = resolve_glyph_indices(&face, chars_to_generate.chars());
+            append_shaped_glyphs(&mut glyphs, &face, text, &runs);
+            (runs, glyphs)
+        };
 
-                let next_width = x_offset + scaled_glyph_width_padding;
-                if next_width >= max_width as i32 {
-                    y_offset += line_heights[current_line_no];
-                    current_line_no += 1;
-                    x_offset = 0;
-                }
+        (Self::from_font(buffer, &glyphs, args, options), runs)
+    }
 
-                let uv_start = Vector2 {
-                    x: x_offset,
-                    y: y_offset,
-                };
-
-                let uv_end = Vector2 {
-                    x: x_offset + scaled_glyph_width,
-                    y: y_offset + scaled_glyph_height,
-                };
-
-                let glyph_data = GlyphData::from_char(glyph_bounding_box.unicode)
-                    .with_uvs(
-                        uv_start,
-                        uv_end,
-                        Vector2 {
-                            x: max_width as i32,
-                            y: max_height as i32,
-                        },
-                        args.uv_space,
-                    )
-                    .with_advance(horizontal_advance)
-                    .with_bearings(bearing_x, bearing_y)
-                    .with_metrics(width, height);
-                glyph_buffer.push(glyph_data);
-
-                atlas_offsets.push((x_offset, y_offset));
-
-                x_offset += scaled_glyph_width_padding;
+    /// Measures every glyph and places it in a new atlas. The fields are generated later, when
+    /// the atlas is composed.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - The contents of the font file
+    /// * `glyphs` - The unicode and glyph index of every glyph to generate
+    /// * `args` - Generator params
+    /// * `options` - The optional settings of the build
+    fn from_font(
+        font: Vec<u8>,
+        glyphs: &[(char, GlyphId)],
+        args: &Args,
+        options: BuildOptions,
+    ) -> Self {
+        let BuildOptions {
+            overrides,
+            cache_directory,
+            progress,
+            cancellation,
+        } = options;
+        let field_cache = cache_directory.map(|directory| FieldCache::new(&directory, &font));
+        let face = &Face::parse(&font, 0).expect("The font is parsed before it is loaded.");
+        let thread_metadata = Vec::new();
+
+        let glyph_capacity = glyphs.len();
+
+        let mut atlas_offsets: Vec<(i32, i32)> = Vec::with_capacity(glyph_capacity);
+
+        let ascender = face.ascender() as i32;
+        let descender = face.descender() as i32;
+        let line_height = ascender + descender;
+        let units_per_em = face.units_per_em() as u32;
+
+        let capacity = glyphs.len();
+
+        let mut glyph_bounding_boxes: Vec<GlyphBoundingBoxData> = Vec::with_capacity(capacity);
+        let mut glyph_buffer: Vec<GlyphData> = Vec::with_capacity(capacity);
+
+        progress.report(ProgressPhase::Measuring, 0, glyphs.len() as u32);
+        store_and_sort_by_area(&mut glyph_bounding_boxes, face, glyphs, args, &overrides);
+        progress.report(
+            ProgressPhase::Measuring,
+            glyphs.len() as u32,
+            glyphs.len() as u32,
+        );
+
+        // Glyphs without a unicode are only positioned by the shaper, which applies its own
+        // kerning.
+        let glyph_indices: Vec<(char, GlyphId)> = glyph_bounding_boxes
+            .iter()
+            .filter(|glyph| glyph.unicode != '\0')
+            .map(|glyph| (glyph.unicode, glyph.glyph_index))
+            .collect();
+        let kerning = collect_kerning_pairs(face, &glyph_indices);
+
+        let (max_width, max_height, line_heights) =
+            calculate_minimum_atlas_dimensions(&glyph_bounding_boxes, args);
+
+        let mut x_offset: i32 = 0;
+        let mut y_offset: i32 = 0;
+        let mut current_line_no = 0;
+
+        for (i, glyph_bounding_box) in glyph_bounding_boxes.iter().enumerate() {
+            if cancellation.is_cancelled() {
+                debug!("Cancelled after placing {} glyphs.", i);
+                break;
             }
 
-            let dim = (max_width, max_height);
-
-            return Builder {
-                glyph_buffer,
-                atlas_offsets,
-                glyph_images,
-                thread_metadata,
-                atlas_dimensions: dim,
-                ascender,
-                descender,
-                line_height,
-                units_per_em,
+            let (scaled_glyph_width_padding, _) =
+                glyph_bounding_box.get_scaled_glyph_dimensions_with_padding(args);
+            let (scaled_glyph_width, scaled_glyph_height) =
+                glyph_bounding_box.get_scaled_glyph_dimensions_no_padding();
+
+            let next_width = x_offset + scaled_glyph_width_padding;
+            if next_width >= max_width as i32 {
+                y_offset += line_heights[current_line_no];
+                current_line_no += 1;
+                x_offset = 0;
+            }
+
+            let uv_start = Vector2 {
+                x: x_offset,
+                y: y_offset,
+            };
+
+            let uv_end = Vector2 {
+                x: x_offset + scaled_glyph_width,
+                y: y_offset + scaled_glyph_height,
             };
+
+            let glyph_data = glyph_bounding_box
+                .create_glyph_data(face, ascender)
+                .with_uvs(
+                    uv_start,
+                    uv_end,
+                    Vector2 {
+                        x: max_width as i32,
+                        y: max_height as i32,
+                    },
+                    args.uv_space,
+                );
+            glyph_buffer.push(glyph_data);
+
+            atlas_offsets.push((x_offset, y_offset));
+
+            x_offset += scaled_glyph_width_padding;
         }
+        // Only the glyphs placed before cancelling are generated.
+        glyph_bounding_boxes.truncate(atlas_offsets.len());
+
+        let dim = (max_width, max_height);
 
         Builder {
-            glyph_buffer: Vec::new(),
+            glyph_buffer,
             atlas_offsets,
-            glyph_images,
+            glyph_bounding_boxes,
             thread_metadata,
+            atlas_dimensions: dim,
+            font,
+            args: *args,
+            field_cache,
+            precision: args.precision,
+            texture_format: args.texture_format,
+            settings: GenerationSettings::from_args(args),
+            kerning,
+            ascender,
+            descender,
+            line_height,
+            units_per_em,
+            progress,
+            cancellation,
+            atlas_written: false,
+        }
+    }
+
+    /// Constructs a builder without any glyphs, used when the font could not be loaded.
+    fn empty(args: &Args) -> Self {
+        Builder {
+            glyph_buffer: Vec::new(),
+            atlas_offsets: Vec::new(),
+            glyph_bounding_boxes: Vec::new(),
+            thread_metadata: Vec::new(),
             atlas_dimensions: (0, 0),
+            font: Vec::new(),
+            args: *args,
+            field_cache: None,
+            precision: args.precision,
+            texture_format: args.texture_format,
+            settings: GenerationSettings::from_args(args),
+            kerning: Vec::new(),
             ascender: 0,
             descender: 0,
             line_height: 0,
             units_per_em: 0,
+            progress: ProgressReporter::default(),
+            cancellation: CancellationToken::default(),
+            atlas_written: false,
         }
     }
 
+    /// Splits the glyphs into contiguous slices, one per thread, so each slice covers about the
+    /// same number of pixels. Every thread gets at least one glyph, so fewer threads are used
+    /// when there are fewer glyphs than threads. The slices run on the current rayon thread
+    /// pool, so no more slices run at once than the pool has threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_count` - The number of threads to copy the glyphs with, where 0 uses every
+    ///   available core
     pub fn prepare_workload(&mut self, thread_count: usize) -> &mut Builder {
-        // TODO: Add a check if the thread_count > than the total # of glyph bounding boxes.
         let metadata = &mut self.thread_metadata;
         metadata.clear();
 
-        let total = self.glyph_images.len();
-        let thread_count = thread_count.min(metadata.capacity()) as u32;
-        let minimum_slice_size = total as u32 / thread_count;
+        let areas: Vec<u64> = self
+            .glyph_bounding_boxes
+            .iter()
+            .map(|glyph| {
+                let (width, height) = glyph.get_scaled_glyph_dimensions_no_padding();
+                width as u64 * height as u64
+            })
+            .collect();
+        let total = areas.len();
+        if total == 0 {
+            return self;
+        }
+
+        let thread_count = match thread_count {
+            0 => std::thread::available_parallelism().map_or(1, |count| count.get()),
+            thread_count => thread_count,
+        }
+        .min(total);
+        let total_area = areas.iter().sum::<u64>();
+
+        // Each slice ends once the area so far reaches its share of the total, or once the
+        // remaining glyphs are needed to give every remaining thread a glyph.
+        let mut starts: Vec<usize> = vec![0];
+        let mut area = 0;
+        for (i, glyph_area) in areas.iter().enumerate().take(total - 1) {
+            area += glyph_area;
+            let slices = starts.len();
+            let reached_share = area * thread_count as u64 >= total_area * slices as u64;
+            let needs_glyphs = total - (i + 1) == thread_count - slices;
+            if slices < thread_count && (reached_share || needs_glyphs) {
+                starts.push(i + 1);
+            }
+        }
+        starts.push(total);
 
-        let last = thread_count - 1;
-        for i in 0..last {
+        for slice in starts.windows(2) {
             metadata.push(ThreadMetadata {
-                start: minimum_slice_size * i,
-                work_unit: minimum_slice_size,
+                start: slice[0] as u32,
+                work_unit: (slice[1] - slice[0]) as u32,
             });
         }
 
-        let last_thread = minimum_slice_size * last;
-        metadata.push(ThreadMetadata {
-            start: last_thread,
-            work_unit: total as u32 - last_thread,
-        });
-
         self
     }
 
+    /// Composes the atlas and writes it to the path, picking the container from the extension.
+    /// Failures, such as an extension which cannot store the precision, are logged instead of
+    /// written.
+    ///
+    /// Every field is generated again on each call, unless the builder has a field cache, so
+    /// writing several formats is best done from one package_font_asset.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to write the atlas to
     pub fn build_atlas(&mut self, path: &Path) -> &mut Builder {
-        let thread_count = self.thread_metadata.len();
+        let extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+
+        let result = match extension.as_deref() {
+            _ if self.cancellation.is_cancelled() => Err(cancelled()),
+            Some(FONT_ASSET_EXTENSION) => {
+                let font_asset = self.package_font_asset();
+                if self.cancellation.is_cancelled() {
+                    Err(cancelled())
+                } else {
+                    font_asset.write(path)
+                }
+            }
+            Some("ktx2") => self.write_container(path, write_ktx2),
+            Some("dds") => self.write_container(path, write_dds),
+            _ => self.write_image(path),
+        };
+
+        self.atlas_written = result.is_ok();
+        match result {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::Interrupted => {
+                debug!("Skipped writing the atlas since the build was cancelled.");
+            }
+            Err(err) => error!("Failed to write the atlas to {}: {}", path.display(), err),
+        }
+        self
+    }
+
+    /// Was the atlas written by the last call to build_atlas? False if the build was cancelled
+    /// before the atlas was written or the atlas could not be written.
+    pub fn is_atlas_written(&self) -> bool {
+        self.atlas_written
+    }
+
+    /// Saves the atlas through the image crate, which picks the format from the path. Returns an
+    /// error without generating the atlas if the format cannot store the precision.
+    fn write_image(&self, path: &Path) -> io::Result<()> {
+        let format =
+            ImageFormat::from_path(path).map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+        if !self.precision.is_supported_by(format) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{:?} atlases cannot be stored with {:?} precision.",
+                    format, self.precision
+                ),
+            ));
+        }
+
+        match self.precision {
+            AtlasPrecision::Bits8 => self.write_atlas::<u8>(path, quantize_u8),
+            AtlasPrecision::Bits16 => self.write_atlas::<u16>(path, quantize_u16),
+            AtlasPrecision::Float32 => self.write_atlas::<f32>(path, |channel| channel),
+        }
+    }
+
+    /// Saves the atlas with a texture container writer, which packs the unclamped pixels into
+    /// the layout of the texture format.
+    fn write_container(&self, path: &Path, writer: ContainerWriter) -> io::Result<()> {
+        let (max_width, max_height) = self.atlas_dimensions;
+        let pixels = self.compose_atlas::<f32>(|channel| channel);
+        if self.cancellation.is_cancelled() {
+            return Err(cancelled());
+        }
+        writer(path, self.texture_format, max_width, max_height, &pixels)
+    }
+
+    /// Copies every glyph into the atlas and saves it, converting each channel with quantize.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to save the atlas to
+    /// * `quantize` - Converts a channel from the generated glyphs to the atlas' channel type
+    fn write_atlas<C>(&self, path: &Path, quantize: fn(f32) -> C) -> io::Result<()>
+    where
+        C: Primitive + Send,
+        Rgba<C>: PixelWithColorType + Pixel<Subpixel = C>,
+        [C]: EncodableLayout,
+    {
         let (max_width, max_height) = self.atlas_dimensions;
+        let pixels = self.compose_atlas(quantize);
+        if self.cancellation.is_cancelled() {
+            return Err(cancelled());
+        }
 
-        let mut pixels: Vec<[u8; 4]> = vec![[0, 0, 0, 0]; (max_width * max_height) as usize];
-        let raw_img = RawImage::new(&mut pixels, max_width, max_height);
+        let atlas: ImageBuffer<Rgba<C>, &[C]> =
+            ImageBuffer::from_raw(max_width, max_height, pixels.as_flattened())
+                .expect("The atlas should hold every pixel of its dimensions.");
+        atlas.save(path).map_err(Error::other)
+    }
 
-        let mut raw_image_views: Vec<Arc<Mutex<RawImageView>>> =
-            Vec::with_capacity(self.glyph_images.len());
+    /// Generates every glyph straight into a new array of pixels, converting each channel with
+    /// quantize. Each slice prepared by prepare_workload is generated as its own task on the
+    /// current rayon thread pool, or every glyph in a single task if no workload was prepared. Each field is dropped once it is
+    /// written, so only one field per thread is held at a time. Since the glyphs never overlap,
+    /// every glyph is written without a lock.
+    ///
+    /// The fields are generated again every time the atlas is composed, unless a field cache is
+    /// used.
+    ///
+    /// # Arguments
+    ///
+    /// * `quantize` - Converts a channel from the generated glyphs to the atlas' channel type
+    fn compose_atlas<C>(&self, quantize: impl Fn(f32) -> C + Sync) -> Vec<[C; 4]>
+    where
+        C: Primitive + Send,
+    {
+        let (max_width, max_height) = self.atlas_dimensions;
 
-        for metadata in &self.thread_metadata {
+        let mut pixels: Vec<[C; 4]> =
+            vec![[C::DEFAULT_MIN_VALUE; 4]; (max_width * max_height) as usize];
+        if self.glyph_bounding_boxes.is_empty() {
+            return pixels;
+        }
+        let face = Face::parse(&self.font, 0).expect("The font was parsed when it was loaded.");
+        let mut raw_img = RawImage::new(&mut pixels, max_width, max_height);
+
+        let rects: Vec<(u32, u32, u32, u32)> = self
+            .glyph_bounding_boxes
+            .iter()
+            .zip(&self.atlas_offsets)
+            .map(|(glyph, (x, y))| {
+                let (width, height) = glyph.get_scaled_glyph_dimensions_no_padding();
+                (*x as u32, *y as u32, width as u32, height as u32)
+            })
+            .collect();
+        let mut raw_image_views = raw_img
+            .split_disjoint(&rects)
+            .expect("The glyphs should fit in the atlas without overlapping.");
+
+        let total = self.glyph_bounding_boxes.len() as u32;
+        let single_slice = [ThreadMetadata {
+            start: 0,
+            work_unit: total,
+        }];
+        let thread_metadata = match self.thread_metadata.is_empty() {
+            true => &single_slice[..],
+            false => &self.thread_metadata[..],
+        };
+
+        // Pairs the views of every thread with the glyphs generated into them.
+        let mut slices: Vec<(&mut [RawImageView<C>], &[GlyphBoundingBoxData])> =
+            Vec::with_capacity(thread_metadata.len());
+        let mut remaining = raw_image_views.as_mut_slice();
+        for metadata in thread_metadata {
             let (start, end) = metadata.get_slice_offsets();
+            let (views, rest) = remaining.split_at_mut(end - start);
+            slices.push((views, &self.glyph_bounding_boxes[start..end]));
+            remaining = rest;
+        }
 
-            let glyph_images: &[ImageBuffer<Rgba<f32>, Vec<f32>>] = &self.glyph_images[start..end];
-            let atlas_offsets = &self.atlas_offsets[start..end];
-
-            for (local_idx, (offset_x, offset_y)) in atlas_offsets.iter().enumerate() {
-                let glyph_img = &glyph_images[local_idx];
-                let raw_img_view = RawImageView::new(
-                    &raw_img,
-                    *offset_x as u32,
-                    *offset_y as u32,
-                    glyph_img.width(),
-                    glyph_img.height(),
-                );
+        let (generated, copied) = (AtomicU32::new(0), AtomicU32::new(0));
+        let (progress, cancellation) = (self.progress, &self.cancellation);
+        progress.report(ProgressPhase::Generating, 0, total);
+        progress.report(ProgressPhase::Copying, 0, total);
 
-                let arc_img_view = Arc::new(Mutex::new(raw_img_view));
-                raw_image_views.push(arc_img_view);
-            }
-        }
+        slices.into_par_iter().for_each(|(views, glyphs)| {
+            for (view, glyph_bounding_box) in views.iter_mut().zip(glyphs) {
+                if cancellation.is_cancelled() {
+                    break;
+                }
 
-        let pool = ThreadPoolBuilder::new()
-            .num_threads(thread_count)
-            .build()
-            .unwrap();
-
-        let shared_target_views: Arc<Vec<Arc<Mutex<RawImageView<'_>>>>> = Arc::new(raw_image_views);
-        let shared_src_images: Arc<&Vec<ImageBuffer<Rgba<f32>, Vec<f32>>>> =
-            Arc::new(&self.glyph_images);
-
-        pool.scope(|s| {
-            for thread_id in 0..thread_count {
-                let metadata = &self.thread_metadata[thread_id];
-                let thread_target_view = shared_target_views.clone();
-                let thread_src_images = shared_src_images.clone();
-                s.spawn(move |_| {
-                    let (start, end) = metadata.get_slice_offsets();
-
-                    for it in start..end {
-                        let mut target_view = thread_target_view[it].lock().unwrap();
-                        let src_image = &thread_src_images[it];
-                        target_view.for_each_mut(&|x, y, p| {
-                            let pixel = src_image.get_pixel(x, y);
-                            *p = [
-                                (pixel[0].clamp(0.0, 1.0) * 255.0) as u8,
-                                (pixel[1].clamp(0.0, 1.0) * 255.0) as u8,
-                                (pixel[2].clamp(0.0, 1.0) * 255.0) as u8,
-                                (pixel[3].clamp(0.0, 1.0) * 255.0) as u8
-                            ];
-                        });
-                    }
-                });
+                let glyph_image = self.generate_field(&face, glyph_bounding_box);
+                let completed = generated.fetch_add(1, Ordering::Relaxed) + 1;
+                progress.report(ProgressPhase::Generating, completed, total);
+
+                if let Some(glyph_image) = glyph_image {
+                    view.write_from(&glyph_image, &quantize);
+                }
+                let completed = copied.fetch_add(1, Ordering::Relaxed) + 1;
+                progress.report(ProgressPhase::Copying, completed, total);
             }
         });
 
-        raw_img.process_as_byte_array(&|bytes| {
-            let atlas: ImageBuffer<Rgba<u8>, &[u8]> =
-                ImageBuffer::from_raw(max_width, max_height, bytes)
-                    .expect("Failed to create the image");
-            atlas.save(path).expect("Failed to save img");
-        });
+        pixels
+    }
 
-        self
+    /// Generates the field of the glyph, or loads it from the field cache if there is one.
+    /// Returns None if the glyph has no outline to generate.
+    ///
+    /// # Arguments
+    ///
+    /// * `face` - The font face parsed from the builder's font
+    /// * `glyph_bounding_box` - The glyph and the settings to generate it with
+    fn generate_field(
+        &self,
+        face: &Face,
+        glyph_bounding_box: &GlyphBoundingBoxData,
+    ) -> Option<GlyphImage> {
+        let glyph_image = match &self.field_cache {
+            Some(field_cache) => field_cache.get_or_generate(face, glyph_bounding_box, &self.args),
+            None => generate_glyph_image(face, glyph_bounding_box, &self.args),
+        };
+        if glyph_image.is_none() {
+            debug!(
+                "Skipped {} due to no shape being generated for msdf.",
+                glyph_bounding_box.unicode
+            );
+        }
+        glyph_image
     }
 
-    /// Constructs a new font data to send through an FFI.
-    pub fn package_font_data(&self) -> FontData {
+    /// Copies the glyphs sorted by their unicode.
+    fn sorted_glyph_data(&self) -> Vec<GlyphData> {
         // TODO: Don't really need to copy, find a way to just conver the original glyph_buffer
         let mut new_glyph_data = self.glyph_buffer.to_vec();
-        new_glyph_data.sort_unstable_by(|lhs, rhs| lhs.unicode.partial_cmp(&rhs.unicode).unwrap());
-        let glyph_data = ByteBuffer::from_vec_struct(new_glyph_data);
+        new_glyph_data.sort_unstable_by_key(|glyph| (glyph.unicode, glyph.glyph_id));
+        new_glyph_data
+    }
 
-        FontData {
+    /// Constructs a new font data to send through an FFI.
+    pub fn package_font_data(&self) -> FontData {
+        FontData::new(
+            self.line_height,
+            self.units_per_em,
+            self.ascender,
+            self.descender,
+            self.sorted_glyph_data(),
+            self.kerning.clone(),
+        )
+    }
+
+    /// Constructs a font asset bundling the metrics, glyphs, kerning and the composed atlas.
+    /// Every field is generated again on each call, unless the builder has a field cache.
+    pub fn package_font_asset(&self) -> FontAsset {
+        let data: Vec<u8> = match self.precision {
+            AtlasPrecision::Bits8 => self.compose_atlas::<u8>(quantize_u8).concat(),
+            AtlasPrecision::Bits16 => self
+                .compose_atlas::<u16>(quantize_u16)
+                .iter()
+                .flatten()
+                .flat_map(|channel| channel.to_le_bytes())
+                .collect(),
+            AtlasPrecision::Float32 => self
+                .compose_atlas::<f32>(|channel| channel)
+                .iter()
+                .flatten()
+                .flat_map(|channel| channel.to_le_bytes())
+                .collect(),
+        };
+
+        let (width, height) = self.atlas_dimensions;
+        let atlas_hash = hash_atlas(&data);
+        FontAsset {
             line_height: self.line_height,
             units_per_em: self.units_per_em,
             ascender: self.ascender,
             descender: self.descender,
-            glyph_data: Box::into_raw(Box::new(glyph_data)),
+            settings: self.settings,
+            glyphs: self.sorted_glyph_data(),
+            kerning: self.kerning.clone(),
+            atlas: AtlasPixels {
+                width,
+                height,
+                precision: self.precision,
+                data,
+            },
+            atlas_hash: Some(atlas_hash),
         }
     }
 }
 
+/// Generates the msdf of the glyph, returning None if the glyph has no outline to generate.
+///
+/// # Arguments
+///
+/// * `face` - The font face to load the glyph from
+/// * `glyph_bounding_box` - The glyph and the settings to generate it with
+/// * `args` - Generator params
+pub fn generate_glyph_image(
+    face: &Face,
+    glyph_bounding_box: &GlyphBoundingBoxData,
+    args: &Args,
+) -> Option<ImageBuffer<Rgba<f32>, Vec<f32>>> {
+    let glyph_index = glyph_bounding_box.glyph_index;
+    let (scaled_glyph_width, scaled_glyph_height) =
+        glyph_bounding_box.get_scaled_glyph_dimensions_no_padding();
+
+    let shape = if args.preprocess_shapes {
+        Outline::from_glyph(face, glyph_index)
+            .map(|outline| outline.preprocess(args.fill_rule).to_shape())
+    } else {
+        face.load_shape(glyph_index)
+    }?;
+
+    let settings = glyph_bounding_box.settings;
+    let (radians, seed) = (settings.radians, settings.coloring_seed);
+
+    let colored_shape = match settings.color_type {
+        ColorType::Simple => shape.color_edges_simple_with_seed(radians, seed),
+        ColorType::InkTrap => shape.color_edges_ink_trap_with_seed(radians, seed),
+        ColorType::Distance => shape.color_edges_by_distance_with_seed(radians, seed),
+    };
+
+    let translation = Vector2 {
+        x: -(glyph_bounding_box.rect.x_min as f64),
+        y: -(glyph_bounding_box.rect.y_min as f64),
+    };
+    let projection = Projection {
+        scale: settings.get_scale(),
+        translation,
+    };
+
+    let msdf_config: MSDFConfig = args.get_msdf_config();
+    let msdf_data = colored_shape.generate_mtsdf(
+        scaled_glyph_width as u32,
+        scaled_glyph_height as u32,
+        settings.range as f64,
+        &projection,
+        &msdf_config,
+    );
+
+    let mut glyph_image: ImageBuffer<Rgba<f32>, Vec<f32>> = msdf_data.to_image();
+
+    if args.fill_rule != FillRule::Disabled {
+        if let Some(outline) = FlatOutline::from_glyph(face, glyph_index) {
+            outline.correct_sign(&mut glyph_image, &projection, args.fill_rule);
+        }
+    }
+    Some(glyph_image)
+}
+
+/// The error returned when the atlas is not written because the build was cancelled.
+fn cancelled() -> Error {
+    Error::new(ErrorKind::Interrupted, "The build was cancelled.")
+}
+
+#[inline(always)]
+fn quantize_u8(channel: f32) -> u8 {
+    (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[inline(always)]
+fn quantize_u16(channel: f32) -> u16 {
+    (channel.clamp(0.0, 1.0) * 65535.0).round() as u16
+}
+
 /**
  * We know that font_size / fonts.units_per_em() will give us the scale.
  */
 
-/// Stores the Glyph's bounding box, the associated unicode, and its ID.
+/// Stores the Glyph's bounding box, the associated unicode, its ID, and the settings to
+/// generate it with.
 #[derive(Clone, Copy)]
 pub struct GlyphBoundingBoxData {
     rect: Rect,
     unicode: char,
     glyph_index: GlyphId,
+    settings: GlyphSettings,
 }
 
 impl GlyphBoundingBoxData {
-    pub fn new(unicode: char, glyph_index: GlyphId, rect: Rect) -> GlyphBoundingBoxData {
+    pub fn new(
+        unicode: char,
+        glyph_index: GlyphId,
+        rect: Rect,
+        settings: GlyphSettings,
+    ) -> GlyphBoundingBoxData {
         Self {
             rect,
             glyph_index,
             unicode,
+            settings,
         }
     }
 
+    /// Returns the area of the glyph after it is scaled.
     #[inline(always)]
     pub fn area(&self) -> i32 {
-        self.rect.width() as i32 * self.rect.height() as i32
+        let scale = self.settings.uniform_scale;
+        (self.rect.width() as f32 * self.rect.height() as f32 * scale * scale) as i32
     }
 
     #[inline(always)]
     pub fn get_scaled_glyph_dimensions_with_padding(&self, args: &Args) -> (i32, i32) {
-        let width = args.scale_dimension_with_padding(self.rect.width().into());
-        let height = args.scale_dimension_with_padding(self.rect.height().into());
-        (width, height)
+        let (width, height) = self.get_scaled_glyph_dimensions_no_padding();
+        (args.add_padding(width), args.add_padding(height))
     }
 
     #[inline(always)]
-    pub fn get_scaled_glyph_dimensions_no_padding(&self, args: &Args) -> (i32, i32) {
-        let width = (self.rect.width() as f32 * args.uniform_scale).round() as i32;
-        let height = (self.rect.height() as f32 * args.uniform_scale).round() as i32;
+    pub fn get_scaled_glyph_dimensions_no_padding(&self) -> (i32, i32) {
+        let scale = self.settings.uniform_scale;
+        let width = (self.rect.width() as f32 * scale).round() as i32;
+        let height = (self.rect.height() as f32 * scale).round() as i32;
         (width, height)
     }
 
@@ -429,24 +905,141 @@ impl GlyphBoundingBoxData {
     pub fn get_metrics(&self) -> (i16, i16) {
         (self.rect.width(), self.rect.height())
     }
+
+    /// Creates the glyph data with the advance, bearings and metrics of the glyph. The uvs are
+    /// set once the glyph is placed in the atlas.
+    ///
+    /// # Arguments
+    ///
+    /// * `face` - The font face the glyph was loaded from
+    /// * `ascender` - The ascender of the font, which the vertical bearing is relative to
+    pub fn create_glyph_data(&self, face: &Face, ascender: i32) -> GlyphData {
+        let horizontal_advance = face.glyph_hor_advance(self.glyph_index).unwrap_or(0);
+        // Glyphs past the end of the metrics have no side bearing in malformed fonts.
+        let bearing_x = face.glyph_hor_side_bearing(self.glyph_index).unwrap_or(0);
+        let bearing_y = self.calculate_bearings_y(ascender);
+        let (width, height) = self.get_metrics();
+
+        GlyphData::from_char(self.unicode)
+            .with_glyph_id(self.glyph_index.0 as u32)
+            .with_advance(horizontal_advance)
+            .with_bearings(bearing_x, bearing_y)
+            .with_metrics(width, height)
+    }
 }
 
-pub fn store_and_sort_by_area(rects: &mut Vec<GlyphBoundingBoxData>, face: &Face, chars: Chars) {
-    let mut row_map: HashMap<i16, Vec<GlyphBoundingBoxData>> = HashMap::new();
-    let mut unique_keys: Vec<i16> = Vec::with_capacity(5);
+/// Reads the font file, returning None if the path is not a ttf or otf font.
+fn read_font_file(font_path: &OsStr) -> Option<Vec<u8>> {
+    let lossy_string = font_path.to_string_lossy();
+    if !lossy_string.ends_with(".otf") && !lossy_string.ends_with(".ttf") {
+        return None;
+    }
+
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut file = File::options()
+        .read(true)
+        .write(false)
+        .open(font_path)
+        .unwrap();
+    let _ = file.read_to_end(&mut buffer);
+    Some(buffer)
+}
 
+/// Checks that the args can generate an atlas, since they may come from untrusted input.
+///
+/// # Arguments
+///
+/// * `args` - Generator params
+fn validate_args(args: &Args) -> io::Result<()> {
+    let invalid = |message: String| Err(Error::new(ErrorKind::InvalidInput, message));
+    if !args.uniform_scale.is_finite() || args.uniform_scale <= 0.0 {
+        return invalid("The uniform scale must be a positive number.".to_string());
+    }
+    if !args.range.is_finite() || args.range <= 0.0 {
+        return invalid("The range must be a positive number.".to_string());
+    }
+    let ratios = [
+        args.degrees,
+        args.min_deviation_ratio,
+        args.min_improve_ratio,
+    ];
+    if ratios.iter().any(|ratio| !ratio.is_finite()) {
+        return invalid("The angle and error correction ratios must be numbers.".to_string());
+    }
+    if args.max_atlas_width == 0 || args.max_atlas_width > MAX_ATLAS_DIMENSION {
+        return invalid(format!(
+            "The max atlas width must be between 1 and {}.",
+            MAX_ATLAS_DIMENSION
+        ));
+    }
+    if args.padding > MAX_ATLAS_DIMENSION {
+        return invalid(format!(
+            "The padding must be at most {}.",
+            MAX_ATLAS_DIMENSION
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that the glyph's bounding box is well formed and still fits in an atlas once scaled.
+///
+/// # Arguments
+///
+/// * `face` - The font face to read the bounding box from
+/// * `c` - The character of the glyph
+/// * `glyph_index` - The glyph to check
+/// * `args` - Generator params
+fn validate_glyph_bounds(
+    face: &Face,
+    c: char,
+    glyph_index: GlyphId,
+    args: &Args,
+) -> io::Result<()> {
+    let rect = match face.glyph_bounding_box(glyph_index) {
+        Some(rect) => rect,
+        None => return Ok(()),
+    };
+
+    let width = rect.x_max as i32 - rect.x_min as i32;
+    let height = rect.y_max as i32 - rect.y_min as i32;
+    let is_well_formed =
+        (0..=i16::MAX as i32).contains(&width) && (0..=i16::MAX as i32).contains(&height);
+    let scaled_size = (width.max(height) as f32 * args.uniform_scale).round() + args.padding as f32;
+    if !is_well_formed || scaled_size > MAX_ATLAS_DIMENSION as f32 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("The bounding box of {:?} is malformed or too large.", c),
+        ));
+    }
+    Ok(())
+}
+
+/// Looks up the glyph index of each char, skipping chars which do not exist in the font.
+pub fn resolve_glyph_indices(face: &Face, chars: Chars) -> Vec<(char, GlyphId)> {
+    let mut glyphs: Vec<(char, GlyphId)> = Vec::with_capacity(chars.size_hint().0);
     for c in chars {
-        let opt_glyph = face.glyph_index(c);
-        if opt_glyph.is_none() {
-            debug!(
+        match face.glyph_index(c) {
+            Some(glyph_index) => glyphs.push((c, glyph_index)),
+            None => debug!(
                 "Skipped unicode, {}, because it does not exist in the font!",
                 c
-            );
-            continue;
+            ),
         }
+    }
+    glyphs
+}
 
-        let glyph_index = opt_glyph.unwrap();
+pub fn store_and_sort_by_area(
+    rects: &mut Vec<GlyphBoundingBoxData>,
+    face: &Face,
+    glyphs: &[(char, GlyphId)],
+    args: &Args,
+    overrides: &GlyphOverrides,
+) {
+    let mut row_map: HashMap<i32, Vec<GlyphBoundingBoxData>> = HashMap::new();
+    let mut unique_keys: Vec<i32> = Vec::with_capacity(5);
 
+    for &(c, glyph_index) in glyphs {
         // let opt_bounding_box = face.glyph_bounding_box(glyph_index);
         let horizontal_advance = face.glyph_hor_advance(glyph_index);
         if horizontal_advance.is_none() {
@@ -461,20 +1054,24 @@ pub fn store_and_sort_by_area(rects: &mut Vec<GlyphBoundingBoxData>, face: &Face
             y_max: 0,
         });
 
-        let height = bounding_box.height();
+        let settings = overrides.settings_for(c, args);
+        let glyph = GlyphBoundingBoxData::new(c, glyph_index, bounding_box, settings);
 
-        if let std::collections::hash_map::Entry::Vacant(e) = row_map.entry(height) {
-            let mut values: Vec<GlyphBoundingBoxData> = Vec::with_capacity(10);
-            values.push(GlyphBoundingBoxData::new(c, glyph_index, bounding_box));
-            e.insert(values);
-            unique_keys.push(height);
-        } else {
-            let values = row_map.get_mut(&height).unwrap();
-            values.push(GlyphBoundingBoxData::new(c, glyph_index, bounding_box));
+        // Group by the scaled height, since overrides may scale glyphs differently.
+        let (_, height) = glyph.get_scaled_glyph_dimensions_no_padding();
+
+        match row_map.entry(height) {
+            Entry::Vacant(e) => {
+                let mut values: Vec<GlyphBoundingBoxData> = Vec::with_capacity(10);
+                values.push(glyph);
+                e.insert(values);
+                unique_keys.push(height);
+            }
+            Entry::Occupied(mut e) => e.get_mut().push(glyph),
         }
     }
     // Sort from highest to lowest along the unique heights
-    unique_keys.sort_unstable_by(|lhs, rhs| rhs.partial_cmp(lhs).unwrap());
+    unique_keys.sort_unstable_by(|lhs, rhs| rhs.cmp(lhs));
 
     // Iterate through and sort each vector
     for height in unique_keys {
@@ -515,9 +1112,9 @@ fn find_best_fit_width(
 ) -> i32 {
     let mut atlas_width = desired_width;
     for glyph in glyph_data {
-        let scaled_width = args.scale_dimension_with_padding(glyph.rect.width().into());
+        let (scaled_width, _) = glyph.get_scaled_glyph_dimensions_with_padding(args);
 
-        if scaled_width >= atlas_width {
+        while scaled_width >= atlas_width {
             atlas_width = get_next_power_of_2(atlas_width);
             debug!(
                 "Resized the atlas width from: {} -> {}",
@@ -535,7 +1132,7 @@ fn find_best_fit_width(
 /// * `glyph_data` - The generated glyphs to check against
 /// * `args` - Generator parameters
 #[inline]
-fn calculate_minimum_atlas_dimensions(
+pub fn calculate_minimum_atlas_dimensions(
     glyph_data: &Vec<GlyphBoundingBoxData>,
     args: &Args,
 ) -> (u32, u32, Vec<i32>) {
@@ -544,10 +1141,15 @@ fn calculate_minimum_atlas_dimensions(
 
     let mut line_count = 0;
     let mut line_heights: Vec<i32> = Vec::with_capacity(5);
+    if glyph_data.is_empty() {
+        return (0, 0, line_heights);
+    }
 
     let max_width = find_best_fit_width(args.max_atlas_width as i32, glyph_data, args);
-    let first_height =
-        args.scale_dimension_with_padding(glyph_data.first().unwrap().rect.height().into());
+    let (_, first_height) = glyph_data
+        .first()
+        .unwrap()
+        .get_scaled_glyph_dimensions_with_padding(args);
 
     line_heights.push(first_height);
     debug!("Pushed new height: {}", first_height);
@@ -592,9 +1194,21 @@ fn calculate_minimum_atlas_dimensions(
         atlas_height = get_next_power_of_2(atlas_height);
         debug!("New Height: {}", atlas_height);
     }
+
+    // Block compressed formats operate on 4x4 blocks of pixels.
+    let (max_width, atlas_height) = if args.block_align {
+        (align_to_block(max_width), align_to_block(atlas_height))
+    } else {
+        (max_width, atlas_height)
+    };
     (max_width as u32, atlas_height as u32, line_heights)
 }
 
+#[inline(always)]
+fn align_to_block(unit: i32) -> i32 {
+    (unit + 3) & !3
+}
+
 #[derive(Copy, Clone)]
 pub struct ThreadMetadata {
     pub start: u32,
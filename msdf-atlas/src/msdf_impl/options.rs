@@ -0,0 +1,157 @@
+use super::jobs::CancellationToken;
+use super::overrides::{GlyphOverride, GlyphOverrides};
+use super::progress::{ProgressCallback, ProgressReporter};
+use super::utils::convert_u16_to_os_string;
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+
+/// Optional settings for a build. The default generates every glyph with the settings in Args,
+/// without a field cache, progress reports or a way to cancel the build.
+#[derive(Default)]
+pub struct BuildOptions {
+    /// Per glyph settings which replace the settings from Args.
+    pub overrides: GlyphOverrides,
+    /// The directory the generated fields are cached in, if any. Settings which only change how
+    /// glyphs are placed, such as the padding, do not invalidate the cached fields.
+    pub cache_directory: Option<PathBuf>,
+    /// Receives the progress of each phase as glyphs are measured, generated and copied into
+    /// the atlas.
+    pub progress: ProgressReporter,
+    /// Stops generating and copying glyphs once cancelled. A cancelled build does not write its
+    /// atlas.
+    pub cancellation: CancellationToken,
+}
+
+impl BuildOptions {
+    /// Builder to replace the settings in Args for any glyph that has an override.
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - Per glyph settings which replace the settings from Args
+    pub fn with_overrides(mut self, overrides: GlyphOverrides) -> BuildOptions {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Builder to reuse the fields cached in the directory by earlier builds of the same font.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache_directory` - The directory the generated fields are cached in
+    pub fn with_cache_directory(mut self, cache_directory: &Path) -> BuildOptions {
+        self.cache_directory = Some(cache_directory.to_path_buf());
+        self
+    }
+
+    /// Builder to report the progress of the build.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - Receives the progress of the generation
+    pub fn with_progress(mut self, progress: ProgressReporter) -> BuildOptions {
+        self.progress = progress;
+        self
+    }
+
+    /// Builder to stop the build once the token is cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancellation` - Stops the generation once cancelled
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> BuildOptions {
+        self.cancellation = cancellation;
+        self
+    }
+}
+
+/// The optional settings of a build passed through the FFI. Zeroed options generate every glyph
+/// with the settings in Args, without a field cache or progress reports.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GenerationOptions {
+    /// A continuous array of per glyph overrides, or null.
+    pub overrides: *const GlyphOverride,
+    /// The number of elements in overrides.
+    pub override_count: u32,
+    /// A null terminated UTF16 path to the directory the fields are cached in, or null to not
+    /// cache the fields.
+    pub cache_directory: *const u16,
+    /// Receives the phase, completed count and total, or null to not report progress.
+    pub progress_callback: Option<ProgressCallback>,
+    /// A pointer passed back to every invocation of the progress callback.
+    pub user_data: *mut c_void,
+}
+
+impl GenerationOptions {
+    /// Copies the options into the build options used by the Builder.
+    ///
+    /// # Safety
+    ///
+    /// `overrides` must point to at least `override_count` elements, or be null. The cache
+    /// directory must be a null terminated UTF16 string, or be null.
+    pub unsafe fn to_build_options(&self) -> BuildOptions {
+        let overrides = if self.overrides.is_null() || self.override_count == 0 {
+            GlyphOverrides::default()
+        } else {
+            GlyphOverrides::from_slice(std::slice::from_raw_parts(
+                self.overrides,
+                self.override_count as usize,
+            ))
+        };
+        let cache_directory = if self.cache_directory.is_null() {
+            None
+        } else {
+            Some(PathBuf::from(convert_u16_to_os_string(
+                self.cache_directory,
+            )))
+        };
+
+        BuildOptions {
+            overrides,
+            cache_directory,
+            progress: ProgressReporter::new(self.progress_callback, self.user_data),
+            cancellation: CancellationToken::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GenerationOptions;
+    use crate::msdf_impl::{args::Args, overrides::GlyphOverride};
+    use std::path::Path;
+
+    #[test]
+    fn converts_generation_options() {
+        let zeroed = GenerationOptions {
+            overrides: std::ptr::null(),
+            override_count: 0,
+            cache_directory: std::ptr::null(),
+            progress_callback: None,
+            user_data: std::ptr::null_mut(),
+        };
+        let options = unsafe { zeroed.to_build_options() };
+        assert!(options.cache_directory.is_none());
+        assert!(!options.cancellation.is_cancelled());
+
+        let overrides = [GlyphOverride::from_char('B').with_range(320.0)];
+        let cache_directory: Vec<u16> = "fields\0".encode_utf16().collect();
+        let options = unsafe {
+            GenerationOptions {
+                overrides: overrides.as_ptr(),
+                override_count: 1,
+                cache_directory: cache_directory.as_ptr(),
+                ..zeroed
+            }
+            .to_build_options()
+        };
+        assert_eq!(
+            options.cache_directory.as_deref(),
+            Some(Path::new("fields"))
+        );
+
+        let args = Args::default().with_range(640.0);
+        assert_eq!(options.overrides.settings_for('B', &args).range, 320.0);
+        assert_eq!(options.overrides.settings_for('A', &args).range, 640.0);
+    }
+}
@@ -0,0 +1,853 @@
+use super::enums::FillRule;
+use super::scanline::is_filled;
+use log::debug;
+use msdf::{Shape, ShapeBuilder};
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// Segments whose points are all within this distance in font units are considered degenerate.
+const DEGENERATE_EPSILON: f32 = 1e-3;
+
+/// The number of line segments used to approximate each curve when measuring a contour.
+const CURVE_SUBDIVISIONS: u32 = 8;
+
+/// The number of line segments used to approximate each curve when finding where contours
+/// cross.
+const RESOLVE_SUBDIVISIONS: u32 = 16;
+
+/// Crossings within this fraction of a piece's length from its ends are moved onto the ends.
+const PARAMETER_EPSILON: f64 = 1e-5;
+
+/// How far the fill is sampled from either side of a piece in font units.
+const SIDE_OFFSET: f64 = 1e-2;
+
+type Point = (f32, f32);
+
+type Vector = (f64, f64);
+
+/// The bits of a point, used to join pieces which end exactly where another starts.
+type PointKey = (u32, u32);
+
+/// A segment of a contour. Each segment starts where the previous one ends, so only the
+/// control points and the end point are stored.
+#[derive(Clone, Copy)]
+enum Segment {
+    Line(Point),
+    Quad(Point, Point),
+    Cubic(Point, Point, Point),
+}
+
+impl Segment {
+    #[inline(always)]
+    fn end(&self) -> Point {
+        match *self {
+            Segment::Line(end) => end,
+            Segment::Quad(_, end) => end,
+            Segment::Cubic(_, _, end) => end,
+        }
+    }
+
+    /// Returns the point at t along the segment, where 0 is the start and 1 is the end.
+    fn point_at(&self, start: Point, t: f32) -> Point {
+        let mt = 1.0 - t;
+        match *self {
+            Segment::Line(end) => (mt * start.0 + t * end.0, mt * start.1 + t * end.1),
+            Segment::Quad(c, end) => (
+                mt * mt * start.0 + 2.0 * mt * t * c.0 + t * t * end.0,
+                mt * mt * start.1 + 2.0 * mt * t * c.1 + t * t * end.1,
+            ),
+            Segment::Cubic(c1, c2, end) => {
+                let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                (
+                    a * start.0 + b * c1.0 + c * c2.0 + d * end.0,
+                    a * start.1 + b * c1.1 + c * c2.1 + d * end.1,
+                )
+            }
+        }
+    }
+
+    /// Returns the part of the segment between two points along it, traversed from `from` to
+    /// `to`, which may run backwards.
+    fn between(&self, start: Point, from: f32, to: f32) -> Segment {
+        if from > to {
+            return self
+                .between(start, to, from)
+                .reversed(self.point_at(start, to));
+        }
+
+        // The front of the segment up to `to`, split again where `from` falls along the front.
+        let points = self.control_points(start);
+        let front = if to < 1.0 {
+            split_bezier(&points, to).0
+        } else {
+            points
+        };
+        let part = if from > 0.0 {
+            split_bezier(&front, from / to).1
+        } else {
+            front
+        };
+        match part[..] {
+            [_, end] => Segment::Line(end),
+            [_, c, end] => Segment::Quad(c, end),
+            [_, c1, c2, end] => Segment::Cubic(c1, c2, end),
+            _ => unreachable!(),
+        }
+    }
+
+    fn control_points(&self, start: Point) -> Vec<Point> {
+        match *self {
+            Segment::Line(end) => vec![start, end],
+            Segment::Quad(c, end) => vec![start, c, end],
+            Segment::Cubic(c1, c2, end) => vec![start, c1, c2, end],
+        }
+    }
+
+    /// Moves the end point of the segment, keeping its control points.
+    fn with_end(self, end: Point) -> Segment {
+        match self {
+            Segment::Line(_) => Segment::Line(end),
+            Segment::Quad(c, _) => Segment::Quad(c, end),
+            Segment::Cubic(c1, c2, _) => Segment::Cubic(c1, c2, end),
+        }
+    }
+
+    /// Is every point of the segment at the start of the segment?
+    fn is_degenerate(&self, start: Point) -> bool {
+        match *self {
+            Segment::Line(end) => approx_eq(start, end),
+            Segment::Quad(c, end) => approx_eq(start, c) && approx_eq(start, end),
+            Segment::Cubic(c1, c2, end) => {
+                approx_eq(start, c1) && approx_eq(start, c2) && approx_eq(start, end)
+            }
+        }
+    }
+
+    /// Returns the same segment traversed from its end to the given start.
+    fn reversed(&self, start: Point) -> Segment {
+        match *self {
+            Segment::Line(_) => Segment::Line(start),
+            Segment::Quad(c, _) => Segment::Quad(c, start),
+            Segment::Cubic(c1, c2, _) => Segment::Cubic(c2, c1, start),
+        }
+    }
+}
+
+/// A closed loop of segments.
+struct Contour {
+    start: Point,
+    segments: Vec<Segment>,
+}
+
+impl Contour {
+    /// Approximates the contour as a closed polygon.
+    fn flatten(&self) -> Vec<Point> {
+        let mut points = Vec::with_capacity(self.segments.len() * CURVE_SUBDIVISIONS as usize + 1);
+        points.push(self.start);
+
+        let mut current = self.start;
+        for segment in &self.segments {
+            match *segment {
+                Segment::Line(end) => points.push(end),
+                _ => {
+                    for i in 1..=CURVE_SUBDIVISIONS {
+                        let t = i as f32 / CURVE_SUBDIVISIONS as f32;
+                        points.push(segment.point_at(current, t));
+                    }
+                }
+            }
+            current = segment.end();
+        }
+        points
+    }
+
+    /// Returns the point each segment starts at.
+    fn segment_starts(&self) -> Vec<Point> {
+        let mut start = self.start;
+        self.segments
+            .iter()
+            .map(|segment| {
+                let segment_start = start;
+                start = segment.end();
+                segment_start
+            })
+            .collect()
+    }
+}
+
+/// A straight piece of a flattened segment, which remembers which part of the segment it
+/// approximates so the curves can be restored once the overlaps are resolved.
+#[derive(Clone, Copy)]
+struct Piece {
+    start: Point,
+    end: Point,
+    contour: usize,
+    segment: usize,
+    /// The position along the segment at the start of the piece.
+    t0: f32,
+    /// The position along the segment at the end of the piece.
+    t1: f32,
+}
+
+impl Piece {
+    fn reversed(&self) -> Piece {
+        Piece {
+            start: self.end,
+            end: self.start,
+            t0: self.t1,
+            t1: self.t0,
+            ..*self
+        }
+    }
+
+    #[inline(always)]
+    fn direction(&self) -> Vector {
+        difference(self.end, self.start)
+    }
+
+    /// Does the other piece continue along the same segment in the same direction?
+    fn is_continued_by(&self, other: &Piece) -> bool {
+        self.contour == other.contour
+            && self.segment == other.segment
+            && self.t1 == other.t0
+            && (self.t1 > self.t0) == (other.t1 > other.t0)
+    }
+}
+
+/// A glyph outline that can be cleaned up before msdfgen colors its edges.
+#[derive(Default)]
+pub struct Outline {
+    contours: Vec<Contour>,
+    current: Point,
+}
+
+impl Outline {
+    /// Reads the glyph's outline. Returns None if the glyph has no outline.
+    ///
+    /// # Arguments
+    ///
+    /// * `face` - The font face to read the outline from
+    /// * `glyph_index` - The glyph to read
+    pub fn from_glyph(face: &Face, glyph_index: GlyphId) -> Option<Self> {
+        let mut outline = Outline::default();
+        face.outline_glyph(glyph_index, &mut outline)?;
+        Some(outline)
+    }
+
+    /// Removes degenerate geometry, resolves self intersecting and overlapping contours into
+    /// contours which only enclose the filled area, and orients every contour consistently.
+    ///
+    /// # Arguments
+    ///
+    /// * `fill_rule` - The rule which decides whether the areas the contours overlap are
+    ///   filled, where Disabled uses the non-zero rule fonts are drawn with
+    pub fn preprocess(mut self, fill_rule: FillRule) -> Self {
+        let fill_rule = match fill_rule {
+            FillRule::Disabled => FillRule::NonZero,
+            fill_rule => fill_rule,
+        };
+        self.remove_degenerate_segments();
+        self.resolve_overlaps(fill_rule);
+        self.remove_degenerate_contours();
+        self
+    }
+
+    /// Removes zero length segments.
+    fn remove_degenerate_segments(&mut self) {
+        for contour in &mut self.contours {
+            let mut start = contour.start;
+            contour.segments.retain(|segment| {
+                if segment.is_degenerate(start) {
+                    false
+                } else {
+                    start = segment.end();
+                    true
+                }
+            });
+        }
+    }
+
+    /// Removes any contour which no longer encloses an area.
+    fn remove_degenerate_contours(&mut self) {
+        let total = self.contours.len();
+        self.contours
+            .retain(|contour| signed_area(&contour.flatten()).abs() > DEGENERATE_EPSILON);
+        if self.contours.len() != total {
+            debug!("Removed {} degenerate contours.", total - self.contours.len());
+        }
+    }
+
+    /// Keeps only the edges between the filled and empty areas, oriented clockwise around the
+    /// filled area and counter clockwise around holes, following the TrueType convention.
+    /// Contours are split where they cross or touch and joined back together along the edge of
+    /// the filled area, where the parts of a curve between the crossings keep the curve's
+    /// control points. Contours which cross nothing are joined back into the same contour,
+    /// unless both of their sides are filled or empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `fill_rule` - The rule which decides which areas are filled
+    fn resolve_overlaps(&mut self, fill_rule: FillRule) {
+        let pieces = self.flatten_pieces();
+        let crossings = find_crossings(&pieces);
+
+        let mut parts: Vec<Piece> = Vec::with_capacity(pieces.len());
+        let mut part_ends: HashSet<(PointKey, PointKey)> = HashSet::new();
+        for (piece, piece_crossings) in pieces.iter().zip(&crossings) {
+            for part in split_piece(piece, piece_crossings) {
+                let part = match boundary_side(&part, &pieces, fill_rule) {
+                    Some(true) => part.reversed(),
+                    Some(false) => part,
+                    None => continue,
+                };
+                // Coincident parts would otherwise trace the same edge twice.
+                if part_ends.insert((key(part.start), key(part.end))) {
+                    parts.push(part);
+                }
+            }
+        }
+
+        let total = self.contours.len();
+        self.contours = join_parts(&self.contours, &parts);
+        if self.contours.len() != total {
+            debug!("Resolved {} contours into {}.", total, self.contours.len());
+        }
+    }
+
+    /// Flattens every contour into pieces, where curves are split into several pieces.
+    fn flatten_pieces(&self) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+        for (contour_index, contour) in self.contours.iter().enumerate() {
+            let mut start = contour.start;
+            for (segment_index, segment) in contour.segments.iter().enumerate() {
+                let count = match segment {
+                    Segment::Line(_) => 1,
+                    _ => RESOLVE_SUBDIVISIONS,
+                };
+
+                let mut previous = start;
+                for i in 1..=count {
+                    let t = i as f32 / count as f32;
+                    let point = if i == count {
+                        segment.end()
+                    } else {
+                        segment.point_at(start, t)
+                    };
+                    pieces.push(Piece {
+                        start: previous,
+                        end: point,
+                        contour: contour_index,
+                        segment: segment_index,
+                        t0: (i - 1) as f32 / count as f32,
+                        t1: t,
+                    });
+                    previous = point;
+                }
+                start = segment.end();
+            }
+        }
+        pieces
+    }
+
+    /// Replays the outline into another builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The builder to replay the outline into
+    pub fn replay(&self, builder: &mut dyn OutlineBuilder) {
+        for contour in &self.contours {
+            builder.move_to(contour.start.0, contour.start.1);
+            for segment in &contour.segments {
+                match *segment {
+                    Segment::Line(end) => builder.line_to(end.0, end.1),
+                    Segment::Quad(c, end) => builder.quad_to(c.0, c.1, end.0, end.1),
+                    Segment::Cubic(c1, c2, end) => {
+                        builder.curve_to(c1.0, c1.1, c2.0, c2.1, end.0, end.1)
+                    }
+                }
+            }
+            builder.close();
+        }
+    }
+
+    /// Converts the outline into a shape msdfgen can color and generate.
+    pub fn to_shape(&self) -> Shape {
+        let mut builder = ShapeBuilder::default();
+        self.replay(&mut builder);
+        builder.build()
+    }
+
+    fn push_segment(&mut self, segment: Segment) {
+        if let Some(contour) = self.contours.last_mut() {
+            contour.segments.push(segment);
+        }
+        self.current = segment.end();
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.current = (x, y);
+        self.contours.push(Contour {
+            start: self.current,
+            segments: Vec::new(),
+        });
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push_segment(Segment::Line((x, y)));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.push_segment(Segment::Quad((x1, y1), (x, y)));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.push_segment(Segment::Cubic((x1, y1), (x2, y2), (x, y)));
+    }
+
+    fn close(&mut self) {
+        // Explicitly close the contour so that reversing it keeps the implied closing edge.
+        let start = match self.contours.last() {
+            Some(contour) => contour.start,
+            None => return,
+        };
+
+        if !approx_eq(self.current, start) {
+            self.push_segment(Segment::Line(start));
+        }
+    }
+}
+
+#[inline(always)]
+fn approx_eq(lhs: Point, rhs: Point) -> bool {
+    (lhs.0 - rhs.0).abs() <= DEGENERATE_EPSILON && (lhs.1 - rhs.1).abs() <= DEGENERATE_EPSILON
+}
+
+/// Returns the signed area of a closed polygon, which is positive when counter clockwise.
+fn signed_area(polygon: &[Point]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        area += a.0 * b.1 - b.0 * a.1;
+    }
+    area * 0.5
+}
+
+/// Finds where the pieces cross, touch or overlap each other, returning the positions along
+/// each piece and the points it has to be split at. Both pieces are split at the same point,
+/// so the parts can be joined by comparing their ends exactly.
+fn find_crossings(pieces: &[Piece]) -> Vec<Vec<(f32, Point)>> {
+    let mut crossings = vec![Vec::new(); pieces.len()];
+    for (i, a) in pieces.iter().enumerate() {
+        for (j, b) in pieces.iter().enumerate().skip(i + 1) {
+            if !bounds_overlap(a, b) {
+                continue;
+            }
+            for (split_b, position, point) in intersect(a, b) {
+                crossings[if split_b { j } else { i }].push((position as f32, point));
+            }
+        }
+    }
+    crossings
+}
+
+/// Returns where one of the pieces has to be split so neither crosses the other, as whether
+/// the second piece is split, the position along that piece and the point. Pieces are only
+/// split away from their ends, and pieces on the same line are split at each other's ends.
+fn intersect(a: &Piece, b: &Piece) -> Vec<(bool, f64, Point)> {
+    let mut splits = Vec::new();
+    let is_inside =
+        |position: f64| position > PARAMETER_EPSILON && position < 1.0 - PARAMETER_EPSILON;
+    let (r, s) = (a.direction(), b.direction());
+    let offset = difference(b.start, a.start);
+    let denominator = cross(r, s);
+
+    if denominator.abs() <= PARAMETER_EPSILON * length(r) * length(s) {
+        if cross(offset, r).abs() > DEGENERATE_EPSILON as f64 * length(r) {
+            return splits;
+        }
+        for point in [b.start, b.end] {
+            let position = dot(difference(point, a.start), r) / dot(r, r);
+            if is_inside(position) {
+                splits.push((false, position, point));
+            }
+        }
+        for point in [a.start, a.end] {
+            let position = dot(difference(point, b.start), s) / dot(s, s);
+            if is_inside(position) {
+                splits.push((true, position, point));
+            }
+        }
+        return splits;
+    }
+
+    let (u, v) = (
+        cross(offset, s) / denominator,
+        cross(offset, r) / denominator,
+    );
+    let range = -PARAMETER_EPSILON..=1.0 + PARAMETER_EPSILON;
+    if !range.contains(&u) || !range.contains(&v) {
+        return splits;
+    }
+
+    // A crossing at the end of either piece is moved onto that end, so the other piece is
+    // split exactly where the first one ends.
+    let point = if v <= PARAMETER_EPSILON {
+        b.start
+    } else if v >= 1.0 - PARAMETER_EPSILON {
+        b.end
+    } else if u <= PARAMETER_EPSILON {
+        a.start
+    } else if u >= 1.0 - PARAMETER_EPSILON {
+        a.end
+    } else {
+        (
+            (a.start.0 as f64 + r.0 * u) as f32,
+            (a.start.1 as f64 + r.1 * u) as f32,
+        )
+    };
+    if is_inside(u) {
+        splits.push((false, u, point));
+    }
+    if is_inside(v) {
+        splits.push((true, v, point));
+    }
+    splits
+}
+
+/// Splits the piece at each of its crossings, skipping parts without length.
+fn split_piece(piece: &Piece, crossings: &[(f32, Point)]) -> Vec<Piece> {
+    let mut crossings = crossings.to_vec();
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // The end keeps the exact position of the piece's end, so the next piece continues it.
+    let t = |position: f32| {
+        if position >= 1.0 {
+            piece.t1
+        } else {
+            piece.t0 + position * (piece.t1 - piece.t0)
+        }
+    };
+
+    let mut parts = Vec::with_capacity(crossings.len() + 1);
+    let (mut start, mut start_position) = (piece.start, 0.0);
+    for (position, point) in crossings.into_iter().chain(once((1.0, piece.end))) {
+        if key(point) != key(start) {
+            parts.push(Piece {
+                start,
+                end: point,
+                t0: t(start_position),
+                t1: t(position),
+                ..*piece
+            });
+        }
+        start = point;
+        start_position = position;
+    }
+    parts
+}
+
+/// Returns whether the piece has to be reversed so the filled area is on its right, or None
+/// when both sides of the piece are filled or both are empty.
+fn boundary_side(piece: &Piece, pieces: &[Piece], fill_rule: FillRule) -> Option<bool> {
+    let direction = piece.direction();
+    let piece_length = length(direction);
+    let offset = SIDE_OFFSET.min(piece_length * 0.25) / piece_length;
+    let normal = (-direction.1 * offset, direction.0 * offset);
+    let middle = (
+        (piece.start.0 as f64 + piece.end.0 as f64) * 0.5,
+        (piece.start.1 as f64 + piece.end.1 as f64) * 0.5,
+    );
+
+    let left = winding(pieces, (middle.0 + normal.0, middle.1 + normal.1));
+    let right = winding(pieces, (middle.0 - normal.0, middle.1 - normal.1));
+    match (is_filled(fill_rule, left), is_filled(fill_rule, right)) {
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Returns the winding number of the flattened outline around the point, which counter
+/// clockwise contours increase.
+fn winding(pieces: &[Piece], point: Vector) -> i32 {
+    let mut winding = 0;
+    for piece in pieces {
+        let (x0, y0) = (piece.start.0 as f64, piece.start.1 as f64);
+        let (x1, y1) = (piece.end.0 as f64, piece.end.1 as f64);
+        let side = (x1 - x0) * (point.1 - y0) - (point.0 - x0) * (y1 - y0);
+        if y0 <= point.1 && point.1 < y1 && side > 0.0 {
+            winding += 1;
+        } else if y1 <= point.1 && point.1 < y0 && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+/// Joins the parts into closed contours. Where several parts leave the same point, the
+/// sharpest turn to the right is taken, which keeps areas that only touch at a point in
+/// separate contours.
+fn join_parts(contours: &[Contour], parts: &[Piece]) -> Vec<Contour> {
+    let mut outgoing: HashMap<PointKey, Vec<usize>> = HashMap::new();
+    for (i, part) in parts.iter().enumerate() {
+        outgoing.entry(key(part.start)).or_default().push(i);
+    }
+
+    let mut is_joined = vec![false; parts.len()];
+    let mut joined = Vec::new();
+    for first in 0..parts.len() {
+        if is_joined[first] {
+            continue;
+        }
+        is_joined[first] = true;
+
+        let mut chain = vec![parts[first]];
+        let mut current = parts[first];
+        while key(current.end) != key(parts[first].start) {
+            let incoming = current.direction();
+            let next = outgoing
+                .get(&key(current.end))
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|i| !is_joined[*i])
+                .min_by(|a, b| {
+                    let turn = |i: usize| {
+                        let outgoing = parts[i].direction();
+                        cross(incoming, outgoing).atan2(dot(incoming, outgoing))
+                    };
+                    turn(*a).total_cmp(&turn(*b))
+                });
+            match next {
+                Some(next) => {
+                    is_joined[next] = true;
+                    current = parts[next];
+                    chain.push(current);
+                }
+                None => break,
+            }
+        }
+        joined.push(rebuild_contour(contours, &chain));
+    }
+    joined
+}
+
+/// Builds a contour from a chain of parts, where the consecutive parts of a segment are
+/// merged back into a single part of the segment.
+fn rebuild_contour(contours: &[Contour], chain: &[Piece]) -> Contour {
+    let mut contour = Contour {
+        start: chain[0].start,
+        segments: Vec::new(),
+    };
+
+    let mut current = contour.start;
+    let mut run_start = 0;
+    for (i, part) in chain.iter().enumerate() {
+        if chain
+            .get(i + 1)
+            .is_some_and(|next| part.is_continued_by(next))
+        {
+            continue;
+        }
+
+        let first = &chain[run_start];
+        let original = &contours[first.contour];
+        let segment_start = original.segment_starts()[first.segment];
+        let segment = original.segments[first.segment]
+            .between(segment_start, first.t0, part.t1)
+            .with_end(part.end);
+        if !segment.is_degenerate(current) {
+            contour.segments.push(segment);
+            current = part.end;
+        }
+        run_start = i + 1;
+    }
+
+    if !approx_eq(current, contour.start) {
+        contour.segments.push(Segment::Line(contour.start));
+    }
+    contour
+}
+
+/// Splits the curve with the control points at t, returning the control points of both
+/// halves.
+fn split_bezier(points: &[Point], t: f32) -> (Vec<Point>, Vec<Point>) {
+    let mut front = Vec::with_capacity(points.len());
+    let mut back = Vec::with_capacity(points.len());
+    let mut level = points.to_vec();
+    while let (Some(first), Some(last)) = (level.first(), level.last()) {
+        front.push(*first);
+        back.push(*last);
+        level = level
+            .windows(2)
+            .map(|pair| {
+                (
+                    pair[0].0 + (pair[1].0 - pair[0].0) * t,
+                    pair[0].1 + (pair[1].1 - pair[0].1) * t,
+                )
+            })
+            .collect();
+    }
+    back.reverse();
+    (front, back)
+}
+
+/// Do the bounding boxes of the pieces overlap?
+fn bounds_overlap(a: &Piece, b: &Piece) -> bool {
+    let (a_min, a_max) = (
+        (a.start.0.min(a.end.0), a.start.1.min(a.end.1)),
+        (a.start.0.max(a.end.0), a.start.1.max(a.end.1)),
+    );
+    let (b_min, b_max) = (
+        (b.start.0.min(b.end.0), b.start.1.min(b.end.1)),
+        (b.start.0.max(b.end.0), b.start.1.max(b.end.1)),
+    );
+    a_min.0 <= b_max.0 + DEGENERATE_EPSILON
+        && b_min.0 <= a_max.0 + DEGENERATE_EPSILON
+        && a_min.1 <= b_max.1 + DEGENERATE_EPSILON
+        && b_min.1 <= a_max.1 + DEGENERATE_EPSILON
+}
+
+#[inline(always)]
+fn key(point: Point) -> PointKey {
+    // Adding zero turns negative zeros into positive zeros, which compare equal.
+    ((point.0 + 0.0).to_bits(), (point.1 + 0.0).to_bits())
+}
+
+#[inline(always)]
+fn difference(lhs: Point, rhs: Point) -> Vector {
+    (lhs.0 as f64 - rhs.0 as f64, lhs.1 as f64 - rhs.1 as f64)
+}
+
+#[inline(always)]
+fn cross(lhs: Vector, rhs: Vector) -> f64 {
+    lhs.0 * rhs.1 - lhs.1 * rhs.0
+}
+
+#[inline(always)]
+fn dot(lhs: Vector, rhs: Vector) -> f64 {
+    lhs.0 * rhs.0 + lhs.1 * rhs.1
+}
+
+#[inline(always)]
+fn length(vector: Vector) -> f64 {
+    dot(vector, vector).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{signed_area, Outline, Segment};
+    use crate::msdf_impl::enums::FillRule;
+    use ttf_parser::OutlineBuilder;
+
+    fn square(outline: &mut Outline, min: f32, max: f32, clockwise: bool) {
+        outline.move_to(min, min);
+        if clockwise {
+            outline.line_to(min, max);
+            outline.line_to(max, max);
+            outline.line_to(max, min);
+        } else {
+            outline.line_to(max, min);
+            outline.line_to(max, max);
+            outline.line_to(min, max);
+        }
+        outline.close();
+    }
+
+    #[test]
+    fn preprocess_orients_and_cleans_contours() {
+        let mut outline = Outline::default();
+        // Both the outer contour and the hole are wound counter clockwise.
+        square(&mut outline, 0.0, 10.0, false);
+        square(&mut outline, 2.0, 8.0, false);
+
+        // A contour made entirely of zero length edges.
+        outline.move_to(20.0, 20.0);
+        outline.line_to(20.0, 20.0);
+        outline.close();
+
+        // Duplicate points in the middle of a contour.
+        outline.move_to(30.0, 30.0);
+        outline.line_to(30.0, 30.0);
+        outline.line_to(30.0, 40.0);
+        outline.line_to(40.0, 40.0);
+        outline.line_to(40.0, 40.0);
+        outline.line_to(40.0, 30.0);
+        outline.close();
+
+        // The even-odd rule keeps the inner square as a hole regardless of its direction.
+        let outline = outline.preprocess(FillRule::EvenOdd);
+        assert_eq!(
+            outline.contours.len(),
+            3,
+            "The degenerate contour should have been removed."
+        );
+        assert_eq!(
+            outline.contours[2].segments.len(),
+            4,
+            "The zero length segments should have been removed."
+        );
+
+        let outer = signed_area(&outline.contours[0].flatten());
+        let hole = signed_area(&outline.contours[1].flatten());
+        assert!(outer < 0.0, "The outer contour should be clockwise.");
+        assert!(hole > 0.0, "The hole should be counter clockwise.");
+    }
+
+    #[test]
+    fn preprocess_merges_overlapping_contours() {
+        let mut outline = Outline::default();
+        square(&mut outline, 0.0, 10.0, true);
+        square(&mut outline, 5.0, 15.0, true);
+
+        let outline = outline.preprocess(FillRule::NonZero);
+        assert_eq!(outline.contours.len(), 1, "The squares should be merged.");
+        assert_eq!(outline.contours[0].segments.len(), 8);
+        assert_eq!(signed_area(&outline.contours[0].flatten()), -175.0);
+
+        // Both triangles of a bow tie are filled, and only touch where the contour crosses.
+        let mut outline = Outline::default();
+        outline.move_to(0.0, 0.0);
+        outline.line_to(10.0, 10.0);
+        outline.line_to(10.0, 0.0);
+        outline.line_to(0.0, 10.0);
+        outline.close();
+
+        let outline = outline.preprocess(FillRule::NonZero);
+        assert_eq!(outline.contours.len(), 2, "The bow tie should be split.");
+        for contour in &outline.contours {
+            assert_eq!(signed_area(&contour.flatten()), -25.0);
+        }
+    }
+
+    #[test]
+    fn preprocess_keeps_curves_between_crossings() {
+        let mut outline = Outline::default();
+        square(&mut outline, -5.0, 5.0, false);
+        // A circle which the corner of the square cuts into.
+        outline.move_to(20.0, 10.0);
+        outline.quad_to(20.0, 20.0, 10.0, 20.0);
+        outline.quad_to(0.0, 20.0, 0.0, 10.0);
+        outline.quad_to(0.0, 0.0, 10.0, 0.0);
+        outline.quad_to(20.0, 0.0, 20.0, 10.0);
+        outline.close();
+
+        let outline = outline.preprocess(FillRule::NonZero);
+        assert_eq!(outline.contours.len(), 1);
+        let contour = &outline.contours[0];
+        let quads = contour
+            .segments
+            .iter()
+            .filter(|segment| matches!(segment, Segment::Quad(..)))
+            .count();
+        assert_eq!(
+            quads, 5,
+            "The ends of the curve outside the square should be kept as curves."
+        );
+        assert_eq!(contour.segments.len(), 9);
+        assert!(signed_area(&contour.flatten()) < 0.0);
+    }
+}
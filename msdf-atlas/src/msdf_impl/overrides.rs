@@ -0,0 +1,283 @@
+use crate::msdf_impl::{args::Args, enums::ColorType};
+use bitflags::bitflags;
+use log::warn;
+use mint::Vector2;
+use std::collections::HashMap;
+
+bitflags! {
+    /// Marks which settings of a GlyphOverride replace the settings from Args.
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct OverrideFlags : u32 {
+        const None = 0;
+        const ColorType = 1 << 0;
+        const ColoringSeed = 1 << 1;
+        const Degrees = 1 << 2;
+        const Range = 1 << 3;
+        const UniformScale = 1 << 4;
+    }
+}
+
+/// Replaces the settings from Args for a single glyph or an inclusive range of glyphs.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GlyphOverride {
+    /// The first unicode the override applies to.
+    pub unicode_start: i32,
+    /// The last unicode the override applies to, inclusive.
+    pub unicode_end: i32,
+    pub flags: OverrideFlags,
+    pub color_type: ColorType,
+    pub coloring_seed: u64,
+    pub degrees: f32,
+    pub range: f32,
+    pub uniform_scale: f32,
+}
+
+#[allow(dead_code)]
+impl GlyphOverride {
+    /// Constructs an override for the char which does not replace any settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The char to override the settings for
+    pub fn from_char(c: char) -> Self {
+        Self::from_range(c, c)
+    }
+
+    /// Constructs an override for every char between start and end, inclusive, which does not
+    /// replace any settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first char to override the settings for
+    /// * `end` - The last char to override the settings for
+    pub fn from_range(start: char, end: char) -> Self {
+        Self {
+            unicode_start: start as i32,
+            unicode_end: end as i32,
+            flags: OverrideFlags::None,
+            color_type: ColorType::Simple,
+            coloring_seed: 0,
+            degrees: 0.0,
+            range: 0.0,
+            uniform_scale: 0.0,
+        }
+    }
+
+    /// Replaces the coloring algorithm for the glyph.
+    ///
+    /// # Arguments
+    ///
+    /// * `color_type` - The type of coloring algorithm to use.
+    pub fn with_color_type(mut self, color_type: ColorType) -> GlyphOverride {
+        self.color_type = color_type;
+        self.flags |= OverrideFlags::ColorType;
+        self
+    }
+
+    /// Replaces the seed used to color the glyph's edges.
+    ///
+    /// # Arguments
+    ///
+    /// * `coloring_seed` - The seed for the coloring algorithm.
+    pub fn with_coloring_seed(mut self, coloring_seed: u64) -> GlyphOverride {
+        self.coloring_seed = coloring_seed;
+        self.flags |= OverrideFlags::ColoringSeed;
+        self
+    }
+
+    /// Replaces the angle that is considered a corner for the glyph.
+    ///
+    /// # Arguments
+    ///
+    /// * `degrees` - The angle that is considered an edge when generating the glyph.
+    pub fn with_angle(mut self, degrees: f32) -> GlyphOverride {
+        self.degrees = degrees;
+        self.flags |= OverrideFlags::Degrees;
+        self
+    }
+
+    /// Replaces the msdf range for the glyph.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The msdf range for the distance field.
+    pub fn with_range(mut self, range: f32) -> GlyphOverride {
+        self.range = range;
+        self.flags |= OverrideFlags::Range;
+        self
+    }
+
+    /// Replaces the scale of the glyph in the atlas.
+    ///
+    /// # Arguments
+    ///
+    /// * `uniform_scale` - Scale of the generated glyph. Recommended to use powers of 1 / 2^n.
+    pub fn with_uniform_scale(mut self, uniform_scale: f32) -> GlyphOverride {
+        self.uniform_scale = uniform_scale;
+        self.flags |= OverrideFlags::UniformScale;
+        self
+    }
+
+    /// Drops every overridden setting which cannot generate a glyph, so the glyph falls back to
+    /// the setting from Args. A scale or range which is not positive would otherwise corrupt the
+    /// atlas or msdfgen's field.
+    fn sanitized(mut self) -> Self {
+        let is_positive = |value: f32| value.is_finite() && value > 0.0;
+        let invalid = [
+            (OverrideFlags::Range, is_positive(self.range), "range"),
+            (
+                OverrideFlags::UniformScale,
+                is_positive(self.uniform_scale),
+                "uniform scale",
+            ),
+            (OverrideFlags::Degrees, self.degrees.is_finite(), "angle"),
+        ];
+        for (flag, is_valid, name) in invalid {
+            if self.flags.contains(flag) && !is_valid {
+                warn!(
+                    "Ignored the {} override for unicodes {} to {}, since it is not a valid number.",
+                    name, self.unicode_start, self.unicode_end
+                );
+                self.flags.remove(flag);
+            }
+        }
+        self
+    }
+
+    #[inline(always)]
+    fn contains(&self, unicode: i32) -> bool {
+        self.unicode_start <= unicode && unicode <= self.unicode_end
+    }
+
+    /// Replaces every setting marked by the flags.
+    fn apply(&self, settings: &mut GlyphSettings) {
+        let flags = self.flags;
+        if flags.contains(OverrideFlags::ColorType) {
+            settings.color_type = self.color_type;
+        }
+        if flags.contains(OverrideFlags::ColoringSeed) {
+            settings.coloring_seed = self.coloring_seed;
+        }
+        if flags.contains(OverrideFlags::Degrees) {
+            settings.radians = self.degrees.to_radians() as f64;
+        }
+        if flags.contains(OverrideFlags::Range) {
+            settings.range = self.range;
+        }
+        if flags.contains(OverrideFlags::UniformScale) {
+            settings.uniform_scale = self.uniform_scale;
+        }
+    }
+}
+
+/// The generation settings for a single glyph after applying any overrides.
+#[derive(Clone, Copy)]
+pub struct GlyphSettings {
+    pub color_type: ColorType,
+    pub coloring_seed: u64,
+    pub radians: f64,
+    pub range: f32,
+    pub uniform_scale: f32,
+}
+
+impl GlyphSettings {
+    /// Returns the scale as a Vector2
+    pub fn get_scale(&self) -> Vector2<f64> {
+        let scale = self.uniform_scale as f64;
+        Vector2 { x: scale, y: scale }
+    }
+}
+
+/// A lookup table of per glyph overrides. Overrides for a single unicode are looked up by
+/// their unicode, while ranges are applied in the order they were defined.
+#[derive(Default)]
+pub struct GlyphOverrides {
+    singles: HashMap<i32, GlyphOverride>,
+    ranges: Vec<GlyphOverride>,
+}
+
+impl GlyphOverrides {
+    /// Constructs the lookup table. If a unicode is covered by more than one override, an
+    /// override for just that unicode wins over any range, and later ranges win over earlier
+    /// ranges. A range or scale which is not positive, or an angle which is not a number, is
+    /// ignored with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - The overrides to look up
+    pub fn from_slice(overrides: &[GlyphOverride]) -> Self {
+        let mut lookup = GlyphOverrides::default();
+        for glyph_override in overrides {
+            let glyph_override = glyph_override.sanitized();
+            if glyph_override.unicode_start == glyph_override.unicode_end {
+                lookup
+                    .singles
+                    .insert(glyph_override.unicode_start, glyph_override);
+            } else {
+                lookup.ranges.push(glyph_override);
+            }
+        }
+        lookup
+    }
+
+    /// Returns the generation settings for the char, falling back to Args for any setting
+    /// that is not overridden.
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The char to look up
+    /// * `args` - Generator params
+    pub fn settings_for(&self, c: char, args: &Args) -> GlyphSettings {
+        let mut settings = GlyphSettings {
+            color_type: args.color_type,
+            coloring_seed: args.coloring_seed,
+            radians: args.get_radians(),
+            range: args.range,
+            uniform_scale: args.uniform_scale,
+        };
+
+        let unicode = c as i32;
+        self.ranges
+            .iter()
+            .filter(|glyph_override| glyph_override.contains(unicode))
+            .for_each(|glyph_override| glyph_override.apply(&mut settings));
+
+        if let Some(glyph_override) = self.singles.get(&unicode) {
+            glyph_override.apply(&mut settings);
+        }
+        settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GlyphOverride, GlyphOverrides};
+    use crate::msdf_impl::args::Args;
+
+    #[test]
+    fn ignores_invalid_overrides() {
+        let args = Args::default().with_uniform_scale(0.5).with_range(4.0);
+        let overrides = GlyphOverrides::from_slice(&[
+            GlyphOverride::from_char('a')
+                .with_uniform_scale(-1.0)
+                .with_range(0.0)
+                .with_angle(f32::NAN),
+            GlyphOverride::from_range('b', 'c')
+                .with_uniform_scale(f32::INFINITY)
+                .with_range(8.0),
+        ]);
+
+        let a = overrides.settings_for('a', &args);
+        assert_eq!((a.uniform_scale, a.range), (0.5, 4.0));
+        assert_eq!(a.radians, args.get_radians());
+
+        let b = overrides.settings_for('b', &args);
+        assert_eq!(
+            (b.uniform_scale, b.range),
+            (0.5, 8.0),
+            "Valid settings of the same override are kept."
+        );
+    }
+}
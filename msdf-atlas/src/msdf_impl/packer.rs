@@ -0,0 +1,405 @@
+/// Places rects into an atlas.
+pub trait Packer {
+    /// Places a rect, returning its top left corner, or None if it does not fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the rect
+    /// * `height` - The height of the rect
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)>;
+}
+
+/// A horizontal segment of the skyline. Everything below y is considered occupied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SkylineNode {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Packs rects into a fixed width atlas by tracking the top edge of the occupied space, placing
+/// each rect at the lowest position it fits.
+#[derive(Clone, Debug)]
+pub struct SkylinePacker {
+    width: u32,
+    height: u32,
+    nodes: Vec<SkylineNode>,
+}
+
+impl SkylinePacker {
+    /// Creates an empty packer.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the atlas
+    /// * `height` - The max height rects can be placed up to
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            nodes: vec![SkylineNode { x: 0, y: 0, width }],
+        }
+    }
+
+    /// Creates a packer where the rects are already occupied. Any free space below the bottom
+    /// edge of an occupied rect is not reused.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the atlas
+    /// * `height` - The max height rects can be placed up to
+    /// * `rects` - The x, y, width and height of every occupied rect
+    pub fn from_rects(width: u32, height: u32, rects: &[(u32, u32, u32, u32)]) -> Self {
+        let mut columns: Vec<u32> = vec![0; width as usize];
+        for &(x, y, rect_width, rect_height) in rects {
+            let end = (x + rect_width).min(width);
+            for column in &mut columns[x.min(width) as usize..end as usize] {
+                *column = (*column).max(y + rect_height);
+            }
+        }
+
+        let mut nodes: Vec<SkylineNode> = Vec::new();
+        for (x, &y) in columns.iter().enumerate() {
+            match nodes.last_mut() {
+                Some(node) if node.y == y => node.width += 1,
+                _ => nodes.push(SkylineNode {
+                    x: x as u32,
+                    y,
+                    width: 1,
+                }),
+            }
+        }
+
+        if nodes.is_empty() {
+            return Self::new(width, height);
+        }
+        Self {
+            width,
+            height,
+            nodes,
+        }
+    }
+
+    /// The width of the atlas.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the bottom edge of the lowest rect placed so far.
+    pub fn used_height(&self) -> u32 {
+        self.nodes.iter().map(|node| node.y).max().unwrap_or(0)
+    }
+
+    /// Places a rect at the lowest position it fits, preferring the leftmost position. Returns
+    /// the top left corner of the rect, or None if it does not fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the rect
+    /// * `height` - The height of the rect
+    pub fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width == 0 || height == 0 {
+            return Some((0, 0));
+        }
+
+        let mut best: Option<(usize, u32, u32)> = None;
+        for i in 0..self.nodes.len() {
+            if let Some(y) = self.fit(i, width, height) {
+                let x = self.nodes[i].x;
+                if best.is_none_or(|(_, best_x, best_y)| (y, x) < (best_y, best_x)) {
+                    best = Some((i, x, y));
+                }
+            }
+        }
+
+        let (i, x, y) = best?;
+        self.place(i, x, y + height, width);
+        Some((x, y))
+    }
+
+    /// Returns the y a rect would be placed at if its left edge starts at the node.
+    fn fit(&self, i: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.nodes[i].x;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width as i64;
+        for node in &self.nodes[i..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(node.y);
+            remaining -= node.width as i64;
+        }
+
+        (y + height <= self.height).then_some(y)
+    }
+
+    /// Adds a node for the top of the placed rect and shrinks the nodes it covers.
+    fn place(&mut self, i: usize, x: u32, y: u32, width: u32) {
+        self.nodes.insert(i, SkylineNode { x, y, width });
+
+        let right = x + width;
+        let j = i + 1;
+        while j < self.nodes.len() {
+            let node = self.nodes[j];
+            if node.x >= right {
+                break;
+            }
+
+            let node_right = node.x + node.width;
+            if node_right <= right {
+                self.nodes.remove(j);
+            } else {
+                self.nodes[j].x = right;
+                self.nodes[j].width = node_right - right;
+                break;
+            }
+        }
+
+        // Merge neighbouring nodes at the same height.
+        let mut j = 0;
+        while j + 1 < self.nodes.len() {
+            if self.nodes[j].y == self.nodes[j + 1].y {
+                self.nodes[j].width += self.nodes[j + 1].width;
+                self.nodes.remove(j + 1);
+            } else {
+                j += 1;
+            }
+        }
+    }
+}
+
+impl Packer for SkylinePacker {
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        SkylinePacker::insert(self, width, height)
+    }
+}
+
+/// The x, y, width and height of a free rect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FreeRect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl FreeRect {
+    #[inline(always)]
+    fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    #[inline(always)]
+    fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    fn intersects(&self, other: &FreeRect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    fn contains(&self, other: &FreeRect) -> bool {
+        self.x <= other.x
+            && self.y <= other.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+}
+
+/// Packs rects into a fixed size atlas by tracking every maximal free rect, placing each rect
+/// at the lowest position it fits. Unlike the skyline, any free space can be reused, including
+/// holes left below other rects, so a packer rebuilt from the remaining rects reclaims the
+/// space of every removed rect.
+#[derive(Clone, Debug)]
+pub struct MaxRectsPacker {
+    free_rects: Vec<FreeRect>,
+}
+
+impl MaxRectsPacker {
+    /// Creates an empty packer.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the atlas
+    /// * `height` - The height of the atlas
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut free_rects = Vec::new();
+        if width > 0 && height > 0 {
+            free_rects.push(FreeRect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            });
+        }
+        Self { free_rects }
+    }
+
+    /// Creates a packer where the rects are already occupied. All of the space around them
+    /// can be reused.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the atlas
+    /// * `height` - The height of the atlas
+    /// * `rects` - The x, y, width and height of every occupied rect
+    pub fn from_rects(width: u32, height: u32, rects: &[(u32, u32, u32, u32)]) -> Self {
+        let mut packer = Self::new(width, height);
+        for &(x, y, width, height) in rects {
+            if width > 0 && height > 0 {
+                packer.occupy(FreeRect {
+                    x,
+                    y,
+                    width,
+                    height,
+                });
+            }
+        }
+        packer
+    }
+
+    /// Places a rect at the lowest position it fits, preferring the leftmost position. Returns
+    /// the top left corner of the rect, or None if it does not fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the rect
+    /// * `height` - The height of the rect
+    pub fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width == 0 || height == 0 {
+            return Some((0, 0));
+        }
+
+        let (x, y) = self
+            .free_rects
+            .iter()
+            .filter(|free| free.width >= width && free.height >= height)
+            .map(|free| (free.y, free.x))
+            .min()
+            .map(|(y, x)| (x, y))?;
+        self.occupy(FreeRect {
+            x,
+            y,
+            width,
+            height,
+        });
+        Some((x, y))
+    }
+
+    /// Splits every free rect overlapping the occupied rect into the free rects around it, then
+    /// removes the free rects which are inside of another.
+    fn occupy(&mut self, occupied: FreeRect) {
+        let mut split: Vec<FreeRect> = Vec::with_capacity(self.free_rects.len() + 4);
+        for free in self.free_rects.drain(..) {
+            if !free.intersects(&occupied) {
+                split.push(free);
+                continue;
+            }
+
+            if occupied.x > free.x {
+                split.push(FreeRect {
+                    width: occupied.x - free.x,
+                    ..free
+                });
+            }
+            if occupied.right() < free.right() {
+                split.push(FreeRect {
+                    x: occupied.right(),
+                    width: free.right() - occupied.right(),
+                    ..free
+                });
+            }
+            if occupied.y > free.y {
+                split.push(FreeRect {
+                    height: occupied.y - free.y,
+                    ..free
+                });
+            }
+            if occupied.bottom() < free.bottom() {
+                split.push(FreeRect {
+                    y: occupied.bottom(),
+                    height: free.bottom() - occupied.bottom(),
+                    ..free
+                });
+            }
+        }
+
+        // Keeps the first of any identical rects, so duplicates are only removed once.
+        self.free_rects = split
+            .iter()
+            .enumerate()
+            .filter(|(i, free)| {
+                !split
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| *i != j && other.contains(free) && (other != *free || j < *i))
+            })
+            .map(|(_, free)| *free)
+            .collect();
+    }
+}
+
+impl Packer for MaxRectsPacker {
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        MaxRectsPacker::insert(self, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaxRectsPacker, SkylinePacker};
+
+    #[test]
+    fn packs_rects_without_overlap() {
+        let mut packer = SkylinePacker::new(10, 10);
+        assert_eq!(packer.insert(6, 4), Some((0, 0)));
+        assert_eq!(packer.insert(4, 2), Some((6, 0)));
+        assert_eq!(packer.insert(4, 2), Some((6, 2)));
+        assert_eq!(packer.insert(10, 4), Some((0, 4)));
+        assert_eq!(packer.insert(1, 3), None, "Only 2 rows are left.");
+        assert_eq!(packer.insert(1, 2), Some((0, 8)));
+        assert_eq!(packer.used_height(), 10);
+    }
+
+    #[test]
+    fn starts_above_occupied_rects() {
+        let mut packer = SkylinePacker::from_rects(8, 8, &[(0, 0, 4, 6), (4, 0, 4, 2)]);
+        assert_eq!(packer.insert(4, 4), Some((4, 2)));
+        assert_eq!(packer.insert(8, 2), Some((0, 6)));
+        assert_eq!(packer.insert(1, 1), None);
+    }
+
+    #[test]
+    fn reuses_holes_below_occupied_rects() {
+        // A free row between two occupied rows, which the skyline cannot reach.
+        let occupied = [(0, 0, 10, 2), (0, 4, 10, 6)];
+        assert_eq!(
+            SkylinePacker::from_rects(10, 10, &occupied).insert(10, 2),
+            None
+        );
+
+        let mut packer = MaxRectsPacker::from_rects(10, 10, &occupied);
+        assert_eq!(packer.insert(4, 2), Some((0, 2)));
+        assert_eq!(packer.insert(6, 2), Some((4, 2)));
+        assert_eq!(packer.insert(1, 1), None);
+    }
+
+    #[test]
+    fn packs_max_rects_without_overlap() {
+        let mut packer = MaxRectsPacker::new(10, 10);
+        assert_eq!(packer.insert(6, 4), Some((0, 0)));
+        assert_eq!(packer.insert(4, 2), Some((6, 0)));
+        assert_eq!(packer.insert(4, 2), Some((6, 2)));
+        assert_eq!(packer.insert(10, 4), Some((0, 4)));
+        assert_eq!(packer.insert(1, 3), None, "Only 2 rows are left.");
+        assert_eq!(packer.insert(10, 2), Some((0, 8)));
+        assert_eq!(packer.insert(1, 1), None);
+    }
+}
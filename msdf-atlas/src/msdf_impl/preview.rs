@@ -0,0 +1,109 @@
+use super::enums::UVSpace;
+use super::font_asset::AtlasPixels;
+use super::font_data::FontData;
+use super::layout::{layout_text, LayoutArgs};
+use super::sampling::{median, sample_bilinear};
+use image::{GrayImage, ImageResult};
+use std::path::Path;
+
+/// The parameters used to render a preview of the atlas.
+#[derive(Clone, Copy)]
+pub struct PreviewArgs {
+    /// The height of an em in screen pixels.
+    pub font_size: f32,
+    /// The distance field range in screen pixels, which controls how sharp the edges are.
+    pub screen_px_range: f32,
+    /// The uv space the glyphs were generated with.
+    pub uv_space: UVSpace,
+}
+
+/// Renders the text with the software equivalent of an msdf shader, so a generated font can be
+/// inspected without a GPU. Covered pixels are white and the background is black.
+///
+/// # Arguments
+///
+/// * `font_data` - The metrics and glyphs of the generated font
+/// * `atlas` - The atlas the glyphs were generated into
+/// * `text` - The text to render, where each newline starts a new line
+/// * `args` - Preview params
+pub fn render_preview(
+    font_data: &FontData,
+    atlas: &AtlasPixels,
+    text: &str,
+    args: &PreviewArgs,
+) -> GrayImage {
+    let layout_args = LayoutArgs::default().with_font_size(args.font_size);
+    let quads = layout_text(font_data, text, &layout_args);
+    let margin = args.screen_px_range.ceil();
+
+    let (max_x, max_y) = quads.iter().fold((0.0_f32, 0.0_f32), |(x, y), quad| {
+        (x.max(quad.x_max), y.max(quad.y_max))
+    });
+    let width = (max_x + margin * 2.0).ceil().max(1.0) as u32;
+    let height = (max_y + margin * 2.0).ceil().max(1.0) as u32;
+    let mut preview = GrayImage::new(width, height);
+
+    for quad in quads {
+        let (quad_width, quad_height) = (quad.x_max - quad.x_min, quad.y_max - quad.y_min);
+        let (left, top) = (quad.x_min + margin, quad.y_min + margin);
+        let (u_min, v_min, u_max, v_max) = args
+            .uv_space
+            .to_texture_rect((quad.uv_x, quad.uv_y, quad.uv_z, quad.uv_w));
+
+        let x_start = left.floor().max(0.0) as u32;
+        let y_start = top.floor().max(0.0) as u32;
+        let x_end = ((left + quad_width).ceil() as u32).min(width);
+        let y_end = ((top + quad_height).ceil() as u32).min(height);
+
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+                // Sample at the center of the screen pixel.
+                let s = (x as f32 + 0.5 - left) / quad_width;
+                let t = (y as f32 + 0.5 - top) / quad_height;
+                if !(0.0..=1.0).contains(&s) || !(0.0..=1.0).contains(&t) {
+                    continue;
+                }
+
+                let u = u_min + (u_max - u_min) * s;
+                let v = v_min + (v_max - v_min) * t;
+                let [r, g, b, _] = sample_atlas(atlas, u, v);
+
+                let distance = args.screen_px_range * (median(r, g, b) - 0.5);
+                let opacity = (distance + 0.5).clamp(0.0, 1.0);
+
+                let pixel = preview.get_pixel_mut(x, y);
+                pixel[0] = pixel[0].max((opacity * 255.0).round() as u8);
+            }
+        }
+    }
+    preview
+}
+
+/// Renders the text like render_preview and saves it as an image.
+///
+/// # Arguments
+///
+/// * `path` - The path to save the preview to, which determines the image format
+/// * `font_data` - The metrics and glyphs of the generated font
+/// * `atlas` - The atlas the glyphs were generated into
+/// * `text` - The text to render, where each newline starts a new line
+/// * `args` - Preview params
+pub fn write_preview(
+    path: &Path,
+    font_data: &FontData,
+    atlas: &AtlasPixels,
+    text: &str,
+    args: &PreviewArgs,
+) -> ImageResult<()> {
+    render_preview(font_data, atlas, text, args).save(path)
+}
+
+/// Samples the atlas at the normalized uv like a texture with linear filtering, clamping to the
+/// edges of the atlas.
+fn sample_atlas(atlas: &AtlasPixels, u: f32, v: f32) -> [f32; 4] {
+    let x = u * atlas.width as f32 - 0.5;
+    let y = v * atlas.height as f32 - 0.5;
+    sample_bilinear(atlas.width, atlas.height, x, y, |x, y| {
+        atlas.get_pixel(x, y)
+    })
+}
@@ -0,0 +1,67 @@
+use std::ffi::c_void;
+
+/// The stage of the atlas generation being reported.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressPhase {
+    /// Measuring the bounding box of every glyph.
+    Measuring,
+    /// Generating the msdf of every glyph.
+    Generating,
+    /// Copying the generated glyphs into the atlas.
+    Copying,
+}
+
+/// Receives the user data passed along with the callback, the phase, the number of glyphs
+/// completed in the phase and the total number of glyphs in the phase.
+pub type ProgressCallback =
+    extern "C" fn(user_data: *mut c_void, phase: ProgressPhase, completed: u32, total: u32);
+
+/// Reports the progress of the atlas generation to an optional callback.
+#[derive(Clone, Copy)]
+pub struct ProgressReporter {
+    callback: Option<ProgressCallback>,
+    user_data: *mut c_void,
+}
+
+// The user data is never read on the Rust side, it is only passed back to the callback, which
+// must be safe to call from any thread.
+unsafe impl Send for ProgressReporter {}
+unsafe impl Sync for ProgressReporter {}
+
+impl Default for ProgressReporter {
+    fn default() -> Self {
+        Self {
+            callback: None,
+            user_data: std::ptr::null_mut(),
+        }
+    }
+}
+
+impl ProgressReporter {
+    /// Creates a reporter which invokes the callback with the user data.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - The callback to invoke, or None to not report progress
+    /// * `user_data` - A pointer passed back to every invocation of the callback
+    pub fn new(callback: Option<ProgressCallback>, user_data: *mut c_void) -> Self {
+        Self {
+            callback,
+            user_data,
+        }
+    }
+
+    /// Invokes the callback, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - The stage of the generation
+    /// * `completed` - The number of glyphs completed in the phase
+    /// * `total` - The total number of glyphs in the phase
+    pub fn report(&self, phase: ProgressPhase, completed: u32, total: u32) {
+        if let Some(callback) = self.callback {
+            callback(self.user_data, phase, completed, total);
+        }
+    }
+}
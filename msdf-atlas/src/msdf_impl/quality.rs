@@ -0,0 +1,454 @@
+use super::args::Args;
+use super::enums::FillRule;
+use super::overrides::GlyphOverrides;
+use super::sampling::{median, sample_bilinear};
+use super::scanline::FlatOutline;
+use super::{
+    generate_glyph_image, resolve_glyph_indices, store_and_sort_by_area, GlyphBoundingBoxData,
+    GlyphImage,
+};
+use mint::Vector2;
+use msdf::Projection;
+use rayon::prelude::*;
+use std::fmt::{Display, Formatter, Result};
+use ttf_parser::Face;
+
+/// The parameters used to measure the quality of the generated glyphs.
+#[derive(Clone, Debug)]
+pub struct QualityArgs {
+    /// The scales each field is rendered at, relative to the size it was generated at.
+    pub render_scales: Vec<f32>,
+    /// The number of samples along each axis of a pixel when rasterizing the outline.
+    pub samples: u32,
+    /// The coverage deviation above which a rendered pixel counts as an artifact.
+    pub artifact_threshold: f32,
+}
+
+impl Default for QualityArgs {
+    fn default() -> Self {
+        Self {
+            render_scales: vec![1.0, 2.0, 4.0],
+            samples: 8,
+            artifact_threshold: 0.5,
+        }
+    }
+}
+
+impl QualityArgs {
+    /// Renders each field at the scales.
+    ///
+    /// # Arguments
+    ///
+    /// * `render_scales` - The scales relative to the size the fields were generated at
+    pub fn with_render_scales(mut self, render_scales: &[f32]) -> QualityArgs {
+        self.render_scales = render_scales.to_vec();
+        self
+    }
+
+    /// Rasterizes the outlines with samples x samples points per pixel.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - The number of samples along each axis of a pixel
+    pub fn with_samples(mut self, samples: u32) -> QualityArgs {
+        self.samples = samples;
+        self
+    }
+
+    /// Counts every pixel deviating by more than the threshold as an artifact.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifact_threshold` - The coverage deviation between 0 and 1
+    pub fn with_artifact_threshold(mut self, artifact_threshold: f32) -> QualityArgs {
+        self.artifact_threshold = artifact_threshold;
+        self
+    }
+}
+
+/// How closely a glyph rendered from its field matches its outline at a single render scale.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphQuality {
+    pub unicode: char,
+    pub glyph_id: u16,
+    /// The scale the field was rendered at, relative to the size it was generated at.
+    pub render_scale: f32,
+    /// The mean absolute difference between the rendered and the rasterized coverage.
+    pub mean_error: f32,
+    /// The largest absolute difference between the rendered and the rasterized coverage.
+    pub max_error: f32,
+    /// The number of pixels deviating by more than the artifact threshold.
+    pub artifact_pixels: u32,
+    /// The number of pixels which were rendered.
+    pub pixel_count: u32,
+}
+
+/// The quality of every glyph at every render scale.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityReport {
+    /// The measurements sorted by unicode, glyph index and render scale.
+    pub glyphs: Vec<GlyphQuality>,
+}
+
+impl QualityReport {
+    /// Returns the glyphs exceeding either limit at any render scale, which likely need a
+    /// different ColorType or angle.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_mean_error` - The largest mean error allowed
+    /// * `max_artifact_pixels` - The largest number of artifact pixels allowed
+    pub fn flagged(&self, max_mean_error: f32, max_artifact_pixels: u32) -> Vec<(char, u16)> {
+        let mut flagged: Vec<(char, u16)> = self
+            .glyphs
+            .iter()
+            .filter(|glyph| {
+                glyph.mean_error > max_mean_error || glyph.artifact_pixels > max_artifact_pixels
+            })
+            .map(|glyph| (glyph.unicode, glyph.glyph_id))
+            .collect();
+        flagged.dedup();
+        flagged
+    }
+}
+
+impl Display for QualityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "Measured {} glyph renders.", self.glyphs.len())?;
+        for glyph in &self.glyphs {
+            writeln!(
+                f,
+                "  {:?} ({}) at {}x: mean error {:.4}, max error {:.4}, {} of {} pixels are artifacts.",
+                glyph.unicode,
+                glyph.glyph_id,
+                glyph.render_scale,
+                glyph.mean_error,
+                glyph.max_error,
+                glyph.artifact_pixels,
+                glyph.pixel_count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Generates the field of every glyph and measures how closely it renders the glyph's outline.
+/// The outline is rasterized with several samples per pixel as the ground truth, and the field
+/// is rendered like an msdf shader at each render scale. Returns None if the font cannot be
+/// parsed.
+///
+/// # Arguments
+///
+/// * `font` - The contents of the font file
+/// * `chars` - The characters to measure
+/// * `args` - Generator params
+/// * `overrides` - Per glyph settings which replace the settings from Args
+/// * `quality_args` - Measurement params
+pub fn analyze_font(
+    font: &[u8],
+    chars: &str,
+    args: &Args,
+    overrides: &GlyphOverrides,
+    quality_args: &QualityArgs,
+) -> Option<QualityReport> {
+    let face = Face::parse(font, 0).ok()?;
+    let glyphs = resolve_glyph_indices(&face, chars.chars());
+    let mut glyph_bounding_boxes = Vec::with_capacity(glyphs.len());
+    store_and_sort_by_area(&mut glyph_bounding_boxes, &face, &glyphs, args, overrides);
+
+    let mut measured: Vec<GlyphQuality> = glyph_bounding_boxes
+        .par_iter()
+        .flat_map_iter(|glyph_bounding_box| {
+            measure_glyph(&face, glyph_bounding_box, args, quality_args)
+        })
+        .collect();
+    measured.sort_by(|lhs, rhs| {
+        (lhs.unicode, lhs.glyph_id)
+            .cmp(&(rhs.unicode, rhs.glyph_id))
+            .then(lhs.render_scale.total_cmp(&rhs.render_scale))
+    });
+    Some(QualityReport { glyphs: measured })
+}
+
+/// Measures a single glyph at every render scale. Glyphs without an outline or without any
+/// pixels are skipped.
+///
+/// # Arguments
+///
+/// * `face` - The font face to load the glyph from
+/// * `glyph_bounding_box` - The glyph and the settings to generate it with
+/// * `args` - Generator params
+/// * `quality_args` - Measurement params
+fn measure_glyph(
+    face: &Face,
+    glyph_bounding_box: &GlyphBoundingBoxData,
+    args: &Args,
+    quality_args: &QualityArgs,
+) -> Vec<GlyphQuality> {
+    let field = generate_glyph_image(face, glyph_bounding_box, args);
+    let outline = FlatOutline::from_glyph(face, glyph_bounding_box.glyph_index);
+    let (field, outline) = match (field, outline) {
+        (Some(field), Some(outline)) if field.width() > 0 && field.height() > 0 => (field, outline),
+        _ => return Vec::new(),
+    };
+
+    let settings = glyph_bounding_box.settings;
+    let projection = Projection {
+        scale: settings.get_scale(),
+        translation: Vector2 {
+            x: -(glyph_bounding_box.rect.x_min as f64),
+            y: -(glyph_bounding_box.rect.y_min as f64),
+        },
+    };
+    // Glyphs are outlined with the non zero rule unless another rule was requested.
+    let fill_rule = match args.fill_rule {
+        FillRule::Disabled => FillRule::NonZero,
+        fill_rule => fill_rule,
+    };
+    let px_range = settings.range * settings.uniform_scale;
+
+    quality_args
+        .render_scales
+        .iter()
+        .map(|render_scale| GlyphQuality {
+            unicode: glyph_bounding_box.unicode,
+            glyph_id: glyph_bounding_box.glyph_index.0,
+            ..measure_field(
+                &field,
+                &outline,
+                &projection,
+                px_range,
+                *render_scale,
+                fill_rule,
+                quality_args,
+            )
+        })
+        .collect()
+}
+
+/// Renders the field at the render scale and compares it with the rasterized outline.
+///
+/// # Arguments
+///
+/// * `field` - The generated field of the glyph
+/// * `outline` - The outline the field was generated from
+/// * `projection` - The projection the field was generated with
+/// * `px_range` - The distance range of the field in field pixels
+/// * `render_scale` - The scale to render at, relative to the size of the field
+/// * `fill_rule` - The rule used to rasterize the outline
+/// * `quality_args` - Measurement params
+fn measure_field(
+    field: &GlyphImage,
+    outline: &FlatOutline,
+    projection: &Projection,
+    px_range: f32,
+    render_scale: f32,
+    fill_rule: FillRule,
+    quality_args: &QualityArgs,
+) -> GlyphQuality {
+    let width = (field.width() as f32 * render_scale).ceil() as u32;
+    let height = (field.height() as f32 * render_scale).ceil() as u32;
+    let render_projection = Projection {
+        scale: Vector2 {
+            x: projection.scale.x * render_scale as f64,
+            y: projection.scale.y * render_scale as f64,
+        },
+        translation: projection.translation,
+    };
+    // The raster is rounded up to whole pixels, so its rows are measured down from the top of
+    // the scaled field rather than from the bottom of the raster.
+    let top = field.height() as f64 * render_scale as f64;
+    let truth = outline.coverage(
+        width,
+        height,
+        top,
+        &render_projection,
+        quality_args.samples,
+        fill_rule,
+    );
+
+    let screen_px_range = px_range * render_scale;
+    let (mut total_error, mut max_error, mut artifact_pixels) = (0.0, 0.0_f32, 0);
+    for y in 0..height {
+        for x in 0..width {
+            // Sample the field at the center of the rendered pixel.
+            let field_x = (x as f32 + 0.5) / render_scale - 0.5;
+            let field_y = (y as f32 + 0.5) / render_scale - 0.5;
+            let [r, g, b, _] =
+                sample_bilinear(field.width(), field.height(), field_x, field_y, |x, y| {
+                    field.get_pixel(x, y).0
+                });
+            let rendered = (screen_px_range * (median(r, g, b) - 0.5) + 0.5).clamp(0.0, 1.0);
+
+            let error = (rendered - truth[(y * width + x) as usize]).abs();
+            total_error += error as f64;
+            max_error = max_error.max(error);
+            if error > quality_args.artifact_threshold {
+                artifact_pixels += 1;
+            }
+        }
+    }
+
+    let pixel_count = width * height;
+    GlyphQuality {
+        unicode: '\0',
+        glyph_id: 0,
+        render_scale,
+        mean_error: (total_error / pixel_count.max(1) as f64) as f32,
+        max_error,
+        artifact_pixels,
+        pixel_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze_font, measure_field, QualityArgs};
+    use crate::msdf_impl::{
+        args::Args, enums::FillRule, overrides::GlyphOverrides, scanline::FlatOutline, GlyphImage,
+    };
+    use image::{ImageBuffer, Rgba};
+    use mint::Vector2;
+    use msdf::Projection;
+    use ttf_parser::OutlineBuilder;
+
+    #[test]
+    fn detects_artifacts_in_field() {
+        // An 8x8 square in the middle of a 16x16 field.
+        let mut outline = FlatOutline::default();
+        outline.move_to(4.0, 4.0);
+        outline.line_to(4.0, 12.0);
+        outline.line_to(12.0, 12.0);
+        outline.line_to(12.0, 4.0);
+        outline.close();
+
+        let projection = Projection {
+            scale: Vector2 { x: 1.0, y: 1.0 },
+            translation: Vector2 { x: 0.0, y: 0.0 },
+        };
+        let px_range = 4.0;
+
+        // The exact signed distance to the square, positive inside.
+        let mut field: GlyphImage = ImageBuffer::from_fn(16, 16, |x, row| {
+            let (x, y) = (x as f32 + 0.5, 16.0 - row as f32 - 0.5);
+            let (dx, dy) = ((x - 8.0).abs() - 4.0, (y - 8.0).abs() - 4.0);
+            let outside = (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt();
+            let distance = -(outside + dx.max(dy).min(0.0));
+            let value = distance / px_range + 0.5;
+            Rgba([value, value, value, value])
+        });
+
+        let quality_args = QualityArgs::default();
+        let measure = |field: &GlyphImage, render_scale: f32| {
+            measure_field(
+                field,
+                &outline,
+                &projection,
+                px_range,
+                render_scale,
+                FillRule::NonZero,
+                &quality_args,
+            )
+        };
+        for render_scale in [1.0, 2.0] {
+            let quality = measure(&field, render_scale);
+            assert_eq!(quality.artifact_pixels, 0, "at {}x", render_scale);
+            assert!(quality.mean_error < 0.05, "at {}x", render_scale);
+        }
+
+        // A single channel field rounds the corners once magnified, which msdf avoids.
+        let magnified = measure(&field, 8.0);
+        assert!(magnified.artifact_pixels > 0);
+
+        // Punch a hole into the middle of the square.
+        for (x, y) in [(7, 7), (8, 7), (7, 8), (8, 8)] {
+            field.put_pixel(x, y, Rgba([0.0; 4]));
+        }
+        let quality = measure(&field, 2.0);
+        assert!(quality.artifact_pixels > 0);
+        assert!(quality.max_error > 0.9);
+    }
+
+    #[test]
+    fn aligns_the_outline_at_fractional_render_scales() {
+        // An 8x7 rect in a 16x15 field, whose height does not scale to whole pixels.
+        let mut outline = FlatOutline::default();
+        outline.move_to(4.0, 4.0);
+        outline.line_to(4.0, 11.0);
+        outline.line_to(12.0, 11.0);
+        outline.line_to(12.0, 4.0);
+        outline.close();
+
+        let projection = Projection {
+            scale: Vector2 { x: 1.0, y: 1.0 },
+            translation: Vector2 { x: 0.0, y: 0.0 },
+        };
+        let px_range = 4.0;
+        let field: GlyphImage = ImageBuffer::from_fn(16, 15, |x, row| {
+            let (x, y) = (x as f32 + 0.5, 15.0 - row as f32 - 0.5);
+            let (dx, dy) = ((x - 8.0).abs() - 4.0, (y - 7.5).abs() - 3.5);
+            let outside = (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt();
+            let distance = -(outside + dx.max(dy).min(0.0));
+            let value = distance / px_range + 0.5;
+            Rgba([value, value, value, value])
+        });
+
+        let quality = measure_field(
+            &field,
+            &outline,
+            &projection,
+            px_range,
+            1.5,
+            FillRule::NonZero,
+            &QualityArgs::default().with_artifact_threshold(0.25),
+        );
+        assert_eq!(quality.pixel_count, 24 * 23);
+        assert_eq!(
+            quality.artifact_pixels, 0,
+            "The outline should line up with the field."
+        );
+    }
+
+    #[test]
+    fn analyzes_every_glyph_at_every_scale() {
+        let font = std::fs::read("testing-resources/Roboto-Medium.ttf").unwrap();
+        let args = Args::default()
+            .with_uniform_scale(1.0 / 32.0)
+            .with_range(640.0);
+        let quality_args = QualityArgs::default()
+            .with_render_scales(&[1.0, 3.0])
+            .with_samples(4);
+
+        let report = analyze_font(
+            &font,
+            "Ao",
+            &args,
+            &GlyphOverrides::default(),
+            &quality_args,
+        )
+        .unwrap();
+        assert_eq!(report.glyphs.len(), 4);
+        assert_eq!(
+            report
+                .glyphs
+                .iter()
+                .map(|glyph| (glyph.unicode, glyph.render_scale))
+                .collect::<Vec<_>>(),
+            vec![('A', 1.0), ('A', 3.0), ('o', 1.0), ('o', 3.0)]
+        );
+        for glyph in &report.glyphs {
+            assert!((0.0..=1.0).contains(&glyph.mean_error));
+            assert!(glyph.artifact_pixels <= glyph.pixel_count);
+        }
+        assert_eq!(report.flagged(1.0, u32::MAX), Vec::new());
+
+        assert!(analyze_font(
+            b"not a font",
+            "A",
+            &args,
+            &GlyphOverrides::default(),
+            &quality_args
+        )
+        .is_none());
+    }
+}
@@ -1,30 +1,36 @@
+use image::ImageBuffer;
 use std::marker::PhantomData;
 use std::mem;
 use std::slice::{self, from_raw_parts};
 
+/// An RGBA pixel whose channels are stored as C.
 #[allow(dead_code)]
-type Rgbu8 = [u8; 4];
+type Rgba<C> = [C; 4];
 
 const RGB_CHANNELS: u32 = 4;
 
 #[allow(dead_code)]
-pub struct RawImage<'a> {
-    _phantom_data: PhantomData<&'a mut Rgbu8>,
-    data: *mut Rgbu8,
+pub struct RawImage<'a, C: Copy = u8> {
+    _phantom_data: PhantomData<&'a mut Rgba<C>>,
+    data: *mut Rgba<C>,
     pub width: u32,
     pub height: u32,
 }
 
 #[allow(dead_code)]
-impl<'a> RawImage<'a> {
-    /// Constructs a new raw image from a Vec<u8>.
+impl<'a, C: Copy> RawImage<'a, C> {
+    /// Constructs a new raw image from a slice of pixels.
     ///
     /// # Arguments
     ///
     /// * `data` - The data to convert to an image
     /// * `width` - The width of the image
     /// * `height` - The height of the image
-    pub fn new(data: &'a mut [Rgbu8], width: u32, height: u32) -> Self {
+    pub fn new(data: &'a mut [Rgba<C>], width: u32, height: u32) -> Self {
+        assert!(
+            data.len() >= width as usize * height as usize,
+            "The image needs at least width * height pixels."
+        );
         Self {
             _phantom_data: PhantomData,
             data: data.as_mut_ptr(),
@@ -33,17 +39,53 @@ impl<'a> RawImage<'a> {
         }
     }
 
-    /// Post processes an array given a closure.
+    /// Splits the image into a view for each rect, which can be written to from separate
+    /// threads. Returns None if a rect is out of bounds or overlaps another rect, since the
+    /// views would otherwise alias. Rects without any pixels never overlap.
     ///
     /// # Arguments
     ///
-    /// * `f` - A function taking an array of u8s.
-    pub fn process_as_byte_array(&self, f: &dyn Fn(&[u8])) {
+    /// * `rects` - The x, y, width and height of every view
+    pub fn split_disjoint(
+        &mut self,
+        rects: &[(u32, u32, u32, u32)],
+    ) -> Option<Vec<RawImageView<'_, C>>> {
+        let in_bounds = rects.iter().all(|&(x, y, width, height)| {
+            x as u64 + width as u64 <= self.width as u64
+                && y as u64 + height as u64 <= self.height as u64
+        });
+        if !in_bounds || !are_disjoint(rects) {
+            return None;
+        }
+
+        let views = rects
+            .iter()
+            .map(|&(x, y, width, height)| RawImageView {
+                _phantom_data: PhantomData,
+                // Empty views never read their pointer, so they all start at the origin.
+                data: if width == 0 || height == 0 {
+                    self.data
+                } else {
+                    unsafe { self.data.add(self.convert_xy_to_index(x, y)) }
+                },
+                stride: self.width as usize,
+                width,
+                height,
+            })
+            .collect();
+        Some(views)
+    }
+
+    /// Post processes the image as a flat array of channels given a closure.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - A function taking an array of channels.
+    pub fn process_as_channel_array(&self, f: &dyn Fn(&[C])) {
         unsafe {
-            let total_size =
-                (self.width * self.height * RGB_CHANNELS) as usize * mem::size_of::<u8>();
+            let total_size = (self.width * self.height * RGB_CHANNELS) as usize;
 
-            let base_ptr = self.data as *mut u8;
+            let base_ptr = self.data as *mut C;
             let raw_parts = from_raw_parts(base_ptr, total_size);
             f(raw_parts);
         }
@@ -57,161 +99,284 @@ impl<'a> RawImage<'a> {
     /// * `y` - The y position along the width
     #[inline(always)]
     fn convert_xy_to_index(&self, x: u32, y: u32) -> usize {
-        (y * self.width + x) as usize
+        y as usize * self.width as usize + x as usize
     }
 
     /// Converts the pointer to an array of u8.
     fn convert_to_u8(&self) -> &[u8] {
         unsafe {
             let byte_ptr = self.data as *mut u8;
-            let len = (self.width * self.height * RGB_CHANNELS) as usize;
+            let len = (self.width * self.height * RGB_CHANNELS) as usize * mem::size_of::<C>();
             slice::from_raw_parts(byte_ptr, len)
         }
     }
 }
 
-/// A RawImageView provides a slice within the RawImage that we can safely write to from
-/// multiple threads.
-pub struct RawImageView<'a> {
-    img: &'a RawImage<'a>,
-    offset_x: u32,
-    offset_y: u32,
+#[allow(dead_code)]
+impl RawImage<'_, u8> {
+    /// Post processes an array given a closure.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - A function taking an array of u8s.
+    pub fn process_as_byte_array(&self, f: &dyn Fn(&[u8])) {
+        self.process_as_channel_array(f);
+    }
+}
+
+/// Checks that no two rects with pixels overlap by sweeping the rects from top to bottom, only
+/// comparing rects whose rows intersect.
+fn are_disjoint(rects: &[(u32, u32, u32, u32)]) -> bool {
+    let mut sorted: Vec<&(u32, u32, u32, u32)> = rects
+        .iter()
+        .filter(|(_, _, width, height)| *width > 0 && *height > 0)
+        .collect();
+    sorted.sort_unstable_by_key(|(_, y, _, _)| *y);
+
+    let mut active: Vec<&(u32, u32, u32, u32)> = Vec::new();
+    for rect in sorted {
+        let &(x, y, width, _) = rect;
+        active.retain(|(_, active_y, _, active_height)| active_y + active_height > y);
+
+        let overlaps = active.iter().any(|(active_x, _, active_width, _)| {
+            x < active_x + active_width && *active_x < x + width
+        });
+        if overlaps {
+            return false;
+        }
+        active.push(rect);
+    }
+    true
+}
+
+/// A RawImageView is a rect within the RawImage that no other view can access, so it can be
+/// written to from another thread without a lock.
+pub struct RawImageView<'a, C: Copy = u8> {
+    _phantom_data: PhantomData<&'a mut Rgba<C>>,
+    /// The top left pixel of the view.
+    data: *mut Rgba<C>,
+    /// The number of pixels between the start of each row.
+    stride: usize,
     pub width: u32,
     pub height: u32,
 }
 
-unsafe impl Send for RawImageView<'_> {}
-// unsafe impl Sync for RawImageView<'_> {}
+// Views are only created by split_disjoint, so no two views can write to the same pixel.
+unsafe impl<C: Copy + Send> Send for RawImageView<'_, C> {}
 
 #[allow(dead_code)]
-impl<'a> RawImageView<'a> {
-    /// Constructs a RawImageView as a slice from the entire RawImage
+impl<C: Copy> RawImageView<'_, C> {
+    /// Returns the pixels of a row of the view.
     ///
     /// # Arguments
     ///
-    /// * `img` - a reference to the RawImage
-    /// * `offset_x` - The offset within the RawImage that marks the x start of this RawImageView
-    /// * `offset_y` - The offset within the Rawimage that marks the y start of this RawImageView
-    /// * `width` - The total width of this sliced image
-    /// * `height` - The total height of this sliced image
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// let mut pixels: Vec<[u8; 3]> = vec![[255, 255, 255]; 100];
-    /// let img = RawImage::new(&mut pixels, 10, 10);
-    /// ```
-    pub fn new(
-        img: &'a RawImage<'a>,
-        offset_x: u32,
-        offset_y: u32,
-        width: u32,
-        height: u32,
-    ) -> Self {
-        Self {
-            img,
-            offset_x,
-            offset_y,
-            width,
-            height,
+    /// * `y` - The row, starting from the top of the view
+    #[inline(always)]
+    pub fn row_mut(&mut self, y: u32) -> &mut [Rgba<C>] {
+        assert!(y < self.height, "The row {} is outside of the view.", y);
+        unsafe {
+            slice::from_raw_parts_mut(self.data.add(y as usize * self.stride), self.width as usize)
         }
     }
 
-    pub fn for_each_mut(&mut self, f: &dyn Fn(u32, u32, &mut Rgbu8)) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let p = self.get_pixel_mut_unchecked(x, y);
-                f(x, y, p);
+    /// Converts each channel of the image with quantize and writes it into the view row by row.
+    /// Pixels outside of the view are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - The generated glyph
+    /// * `quantize` - Converts a channel from the generated glyph to the image's channel type
+    pub fn write_from(
+        &mut self,
+        image: &ImageBuffer<image::Rgba<f32>, Vec<f32>>,
+        quantize: impl Fn(f32) -> C,
+    ) {
+        if image.width() == 0 {
+            return;
+        }
+
+        let row_size = image.width() as usize * RGB_CHANNELS as usize;
+        for (y, src) in image.as_raw().chunks_exact(row_size).enumerate() {
+            if y as u32 >= self.height {
+                break;
+            }
+            for (pixel, channels) in self.row_mut(y as u32).iter_mut().zip(src.chunks_exact(4)) {
+                *pixel = [
+                    quantize(channels[0]),
+                    quantize(channels[1]),
+                    quantize(channels[2]),
+                    quantize(channels[3]),
+                ];
             }
         }
     }
 
-    pub fn for_each(&mut self, f: &dyn Fn(u32, u32, &Rgbu8)) {
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(u32, u32, &mut Rgba<C>)) {
         for y in 0..self.height {
-            for x in 0..self.width {
-                let p = self.get_pixel_mut_unchecked(x, y);
-                f(x, y, p);
+            for (x, p) in self.row_mut(y).iter_mut().enumerate() {
+                f(x as u32, y, p);
             }
         }
     }
 
-    #[inline(always)]
-    pub fn get_pixel_mut_unchecked(&mut self, x: u32, y: u32) -> &mut Rgbu8 {
-        let index = self
-            .img
-            .convert_xy_to_index(x + self.offset_x, y + self.offset_y);
-        unsafe {
-            let p = self.img.data.add(index);
-            if p.is_null() {
-                panic!("The coordinates: {}, {} does not point to a valid memory address", x, y);
-            }
-            &mut *p
+    /// Writes the pixel at x, y within the view. Returns false without writing if the pixel is
+    /// outside of the view.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column, starting from the left of the view
+    /// * `y` - The row, starting from the top of the view
+    /// * `color` - The value of the pixel
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Rgba<C>) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
         }
+        unsafe { self.set_pixel_unchecked(x, y, color) };
+        true
     }
 
+    /// Returns the pixel at x, y within the view without checking the bounds.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column, starting from the left of the view
+    /// * `y` - The row, starting from the top of the view
+    ///
+    /// # Safety
+    ///
+    /// x must be less than the width and y less than the height of the view. Otherwise the pixel
+    /// belongs to another view or lies outside of the image.
     #[inline(always)]
-    pub fn set_pixel_unchecked(&mut self, x: u32, y: u32, color: Rgbu8) {
-        let index = self
-            .img
-            .convert_xy_to_index(x + self.offset_x, y + self.offset_y);
-        unsafe { self.img.data.add(index).write(color) };
+    pub unsafe fn get_pixel_mut_unchecked(&mut self, x: u32, y: u32) -> &mut Rgba<C> {
+        debug_assert!(x < self.width && y < self.height);
+        &mut *self.data.add(y as usize * self.stride + x as usize)
+    }
+
+    /// Writes the pixel at x, y within the view without checking the bounds.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column, starting from the left of the view
+    /// * `y` - The row, starting from the top of the view
+    /// * `color` - The value of the pixel
+    ///
+    /// # Safety
+    ///
+    /// x must be less than the width and y less than the height of the view. Otherwise the pixel
+    /// belongs to another view or lies outside of the image.
+    #[inline(always)]
+    pub unsafe fn set_pixel_unchecked(&mut self, x: u32, y: u32, color: Rgba<C>) {
+        *self.get_pixel_mut_unchecked(x, y) = color;
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::{Arc, Mutex};
+    use image::{ImageBuffer, Rgba};
+    use rayon::prelude::*;
 
-    use image::{ImageBuffer, Rgb};
-    use rayon::ThreadPoolBuilder;
-
-    use super::{RawImage, RawImageView};
+    use super::RawImage;
 
     #[test]
     fn raw_image_view_writes_to_same_img_multithread() {
         let mut pixels: Vec<[u8; 4]> = vec![[255, 255, 255, 255]; 100];
-        let img = RawImage::new(&mut pixels, 10, 10);
-        let pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
-
-        let v1 = RawImageView::new(&img, 0, 0, 5, 10);
-        let v2 = RawImageView::new(&img, 5, 0, 5, 10);
-
-        let mut images = Vec::with_capacity(2);
-        images.push(Arc::new(Mutex::new(v1)));
-        images.push(Arc::new(Mutex::new(v2)));
-
-        let arc_images = Arc::new(&images);
+        let mut img = RawImage::new(&mut pixels, 10, 10);
 
         let red = [255, 0, 0, 255];
         let blue = [0, 0, 255, 255];
-        pool.scope(|s| {
-            for i in 0..2 {
-                let v = arc_images.clone();
-                s.spawn(move |_| {
-                    let color = if i == 0 { red } else { blue };
-                    let m = &mut v[i].lock().unwrap();
-                    m.for_each_mut(&|_, _, p| {
-                        *p = color;
-                    });
-                });
-            }
+        let mut views = img.split_disjoint(&[(0, 0, 5, 10), (5, 0, 5, 10)]).unwrap();
+        views.par_iter_mut().enumerate().for_each(|(i, view)| {
+            let color = if i == 0 { red } else { blue };
+            view.for_each_mut(|_, _, p| *p = color);
         });
 
-        for (i, img) in images.iter().enumerate() {
-            let v = &mut img.lock().unwrap();
+        for (i, view) in views.iter_mut().enumerate() {
             let color = if i == 0 { red } else { blue };
-            let _ = &v.for_each(&|_, _, p| {
-                for (accessor, c) in p.iter().enumerate() {
-                    assert!(color[accessor] - c == 0);
-                }
-            });
+            view.for_each_mut(|_, _, p| assert_eq!(*p, color));
         }
+        drop(views);
 
         img.process_as_byte_array(&|bytes| {
-            let atlas: ImageBuffer<Rgb<u8>, &[u8]> =
-                ImageBuffer::from_raw(10, 10, bytes)
-                    .expect("Failed to create the image");
-            atlas.save("test.png").expect("Failed to save img");
+            let atlas: ImageBuffer<Rgba<u8>, &[u8]> =
+                ImageBuffer::from_raw(10, 10, bytes).expect("Failed to create the image");
+            assert_eq!(atlas.get_pixel(4, 9).0, red);
+            assert_eq!(atlas.get_pixel(5, 0).0, blue);
         });
     }
+
+    #[test]
+    fn rejects_overlapping_views() {
+        let mut pixels: Vec<[u8; 4]> = vec![[0; 4]; 100];
+        let mut img = RawImage::new(&mut pixels, 10, 10);
+
+        assert!(img.split_disjoint(&[(0, 0, 5, 5), (4, 4, 2, 2)]).is_none());
+        assert!(
+            img.split_disjoint(&[(8, 0, 3, 1)]).is_none(),
+            "Views must be within the image."
+        );
+        assert!(
+            img.split_disjoint(&[(0, 0, 5, 5), (5, 0, 5, 5), (0, 5, 10, 5), (3, 3, 0, 0)])
+                .is_some(),
+            "Touching and empty views do not overlap."
+        );
+    }
+
+    #[test]
+    fn set_pixel_stays_within_view() {
+        let mut pixels: Vec<[u8; 4]> = vec![[0; 4]; 100];
+        let mut img = RawImage::new(&mut pixels, 10, 10);
+
+        let mut views = img.split_disjoint(&[(2, 2, 3, 3)]).unwrap();
+        assert!(views[0].set_pixel(2, 2, [255; 4]));
+        assert!(!views[0].set_pixel(3, 0, [255; 4]));
+        assert!(!views[0].set_pixel(0, 3, [255; 4]));
+        drop(views);
+
+        let written: Vec<usize> = (0..pixels.len())
+            .filter(|i| pixels[*i] == [255; 4])
+            .collect();
+        assert_eq!(
+            written,
+            vec![44],
+            "Only the bottom right pixel of the view is written."
+        );
+    }
+
+    #[test]
+    fn unchecked_writes_stay_within_views() {
+        let mut pixels: Vec<[u8; 4]> = vec![[0; 4]; 100];
+        let mut img = RawImage::new(&mut pixels, 10, 10);
+
+        // Every pixel of two touching views is written without checks, which miri or a
+        // sanitizer would flag if the stride or offsets of a view were wrong.
+        let rects = [(1, 1, 4, 3), (5, 1, 3, 3)];
+        let mut views = img.split_disjoint(&rects).unwrap();
+        for (i, view) in views.iter_mut().enumerate() {
+            for y in 0..view.height {
+                for x in 0..view.width {
+                    unsafe { view.set_pixel_unchecked(x, y, [i as u8 + 1; 4]) };
+                }
+            }
+        }
+        drop(views);
+
+        for y in 0..10 {
+            for x in 0..10 {
+                let expected = match rects
+                    .iter()
+                    .position(|&(rx, ry, w, h)| x >= rx && x < rx + w && y >= ry && y < ry + h)
+                {
+                    Some(i) => [i as u8 + 1; 4],
+                    None => [0; 4],
+                };
+                assert_eq!(
+                    pixels[(y * 10 + x) as usize],
+                    expected,
+                    "Pixel ({}, {}) was written by the wrong view.",
+                    x,
+                    y
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,46 @@
+/// Returns the median of the three channels, which is the signed distance stored in a
+/// multi-channel distance field.
+#[inline(always)]
+pub fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+/// Samples a grid of pixels with linear filtering, clamping to its edges. Pixel centers lie on
+/// whole coordinates, so (0, 0) is the center of the top left pixel.
+///
+/// # Arguments
+///
+/// * `width` - The width of the grid
+/// * `height` - The height of the grid
+/// * `x` - The column to sample at
+/// * `y` - The row to sample at, starting from the top
+/// * `get_pixel` - Returns the pixel at a column and row within the grid
+pub fn sample_bilinear(
+    width: u32,
+    height: u32,
+    x: f32,
+    y: f32,
+    get_pixel: impl Fn(u32, u32) -> [f32; 4],
+) -> [f32; 4] {
+    if width == 0 || height == 0 {
+        return [0.0; 4];
+    }
+
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let (p00, p10) = (get_pixel(x0, y0), get_pixel(x1, y0));
+    let (p01, p11) = (get_pixel(x0, y1), get_pixel(x1, y1));
+
+    let mut sample = [0.0; 4];
+    for (i, channel) in sample.iter_mut().enumerate() {
+        let top = p00[i] + (p10[i] - p00[i]) * tx;
+        let bottom = p01[i] + (p11[i] - p01[i]) * tx;
+        *channel = top + (bottom - top) * ty;
+    }
+    sample
+}
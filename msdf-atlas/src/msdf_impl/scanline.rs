@@ -0,0 +1,312 @@
+use super::enums::FillRule;
+use super::sampling::median;
+use image::{ImageBuffer, Rgba};
+use msdf::Projection;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// The number of line segments used to approximate each curve in the outline.
+const CURVE_SUBDIVISIONS: u32 = 8;
+
+/// A single line segment of the flattened outline in font units.
+#[derive(Clone, Copy)]
+struct Edge {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+}
+
+/// A glyph outline flattened into line segments, used to determine which pixels are filled
+/// when correcting the sign of a generated distance field, and to rasterize the glyph when
+/// measuring the quality of the field.
+#[derive(Default)]
+pub struct FlatOutline {
+    edges: Vec<Edge>,
+    start: (f64, f64),
+    current: (f64, f64),
+}
+
+impl FlatOutline {
+    /// Flattens the glyph's outline. Returns None if the glyph has no outline.
+    ///
+    /// # Arguments
+    ///
+    /// * `face` - The font face to read the outline from
+    /// * `glyph_index` - The glyph to flatten
+    pub fn from_glyph(face: &Face, glyph_index: GlyphId) -> Option<Self> {
+        let mut outline = FlatOutline::default();
+        face.outline_glyph(glyph_index, &mut outline)?;
+        Some(outline)
+    }
+
+    /// Inverts every pixel in the field whose sign disagrees with the outline's fill.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The generated distance field, where values above 0.5 are inside the shape
+    /// * `projection` - The projection used to generate the field
+    /// * `fill_rule` - The rule used to determine whether a pixel is inside the outline
+    pub fn correct_sign(
+        &self,
+        field: &mut ImageBuffer<Rgba<f32>, Vec<f32>>,
+        projection: &Projection,
+        fill_rule: FillRule,
+    ) {
+        if fill_rule == FillRule::Disabled || self.edges.is_empty() {
+            return;
+        }
+
+        let (width, height) = field.dimensions();
+        let mut crossings: Vec<(f64, i32)> = Vec::with_capacity(self.edges.len());
+
+        for row in 0..height {
+            // Image rows grow downwards while the outline's y axis grows upwards.
+            let y = ((height - row) as f64 - 0.5) / projection.scale.y - projection.translation.y;
+            self.collect_crossings(y, &mut crossings);
+
+            let mut winding = 0;
+            let mut next = 0;
+            for column in 0..width {
+                let x = (column as f64 + 0.5) / projection.scale.x - projection.translation.x;
+                while next < crossings.len() && crossings[next].0 < x {
+                    winding += crossings[next].1;
+                    next += 1;
+                }
+
+                let filled = is_filled(fill_rule, winding);
+                let pixel = field.get_pixel_mut(column, row);
+                let distance = median(pixel[0], pixel[1], pixel[2]);
+                if distance != 0.5 && (distance > 0.5) != filled {
+                    pixel.0.iter_mut().for_each(|channel| *channel = 1.0 - *channel);
+                }
+            }
+        }
+    }
+
+    /// Rasterizes the outline into the coverage of every pixel, by testing a grid of samples
+    /// within each pixel against the fill rule. Rows start from the top, like image rows.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the raster in pixels
+    /// * `height` - The height of the raster in pixels
+    /// * `top` - The projected y of the top edge of the first row, which is the height of the
+    ///   projected space and may not be a whole number of pixels
+    /// * `projection` - Maps the outline from font units to pixels
+    /// * `samples` - The number of samples along each axis of a pixel
+    /// * `fill_rule` - The rule used to determine whether a sample is inside the outline
+    pub fn coverage(
+        &self,
+        width: u32,
+        height: u32,
+        top: f64,
+        projection: &Projection,
+        samples: u32,
+        fill_rule: FillRule,
+    ) -> Vec<f32> {
+        let mut coverage = vec![0.0; width as usize * height as usize];
+        let samples = samples.max(1);
+        let sample_weight = 1.0 / (samples * samples) as f32;
+        let mut crossings: Vec<(f64, i32)> = Vec::with_capacity(self.edges.len());
+
+        for row in 0..height {
+            for sample_y in 0..samples {
+                let offset_y = (sample_y as f64 + 0.5) / samples as f64;
+                let y =
+                    (top - row as f64 - offset_y) / projection.scale.y - projection.translation.y;
+                self.collect_crossings(y, &mut crossings);
+
+                let mut winding = 0;
+                let mut next = 0;
+                for column in 0..width {
+                    for sample_x in 0..samples {
+                        let offset_x = (sample_x as f64 + 0.5) / samples as f64;
+                        let x = (column as f64 + offset_x) / projection.scale.x
+                            - projection.translation.x;
+                        while next < crossings.len() && crossings[next].0 < x {
+                            winding += crossings[next].1;
+                            next += 1;
+                        }
+
+                        if is_filled(fill_rule, winding) {
+                            coverage[(row * width + column) as usize] += sample_weight;
+                        }
+                    }
+                }
+            }
+        }
+        coverage
+    }
+
+    /// Stores every intersection between the horizontal line at y and the outline, sorted
+    /// along the x axis, alongside the winding direction of the intersected edge.
+    fn collect_crossings(&self, y: f64, crossings: &mut Vec<(f64, i32)>) {
+        crossings.clear();
+        for edge in &self.edges {
+            let direction = if edge.y0 <= y && y < edge.y1 {
+                1
+            } else if edge.y1 <= y && y < edge.y0 {
+                -1
+            } else {
+                continue;
+            };
+
+            let t = (y - edge.y0) / (edge.y1 - edge.y0);
+            crossings.push((edge.x0 + t * (edge.x1 - edge.x0), direction));
+        }
+        crossings.sort_unstable_by(|lhs, rhs| lhs.0.total_cmp(&rhs.0));
+    }
+
+    fn push_edge(&mut self, x: f64, y: f64) {
+        let (x0, y0) = self.current;
+        if x0 != x || y0 != y {
+            self.edges.push(Edge { x0, y0, x1: x, y1: y });
+        }
+        self.current = (x, y);
+    }
+}
+
+impl OutlineBuilder for FlatOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = (x as f64, y as f64);
+        self.current = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push_edge(x as f64, y as f64);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        let (x1, y1, x, y) = (x1 as f64, y1 as f64, x as f64, y as f64);
+        for i in 1..=CURVE_SUBDIVISIONS {
+            let t = i as f64 / CURVE_SUBDIVISIONS as f64;
+            let mt = 1.0 - t;
+            self.push_edge(
+                mt * mt * x0 + 2.0 * mt * t * x1 + t * t * x,
+                mt * mt * y0 + 2.0 * mt * t * y1 + t * t * y,
+            );
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        let (x1, y1, x2, y2, x, y) = (
+            x1 as f64, y1 as f64, x2 as f64, y2 as f64, x as f64, y as f64,
+        );
+        for i in 1..=CURVE_SUBDIVISIONS {
+            let t = i as f64 / CURVE_SUBDIVISIONS as f64;
+            let mt = 1.0 - t;
+            self.push_edge(
+                mt * mt * mt * x0 + 3.0 * mt * mt * t * x1 + 3.0 * mt * t * t * x2 + t * t * t * x,
+                mt * mt * mt * y0 + 3.0 * mt * mt * t * y1 + 3.0 * mt * t * t * y2 + t * t * t * y,
+            );
+        }
+    }
+
+    fn close(&mut self) {
+        let (x, y) = self.start;
+        self.push_edge(x, y);
+    }
+}
+
+#[inline(always)]
+pub(super) fn is_filled(fill_rule: FillRule, winding: i32) -> bool {
+    match fill_rule {
+        FillRule::Disabled => false,
+        FillRule::NonZero => winding != 0,
+        FillRule::EvenOdd => winding % 2 != 0,
+        FillRule::Positive => winding > 0,
+        FillRule::Negative => winding < 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlatOutline;
+    use crate::msdf_impl::enums::FillRule;
+    use image::{ImageBuffer, Rgba};
+    use mint::Vector2;
+    use msdf::Projection;
+    use ttf_parser::OutlineBuilder;
+
+    #[test]
+    fn inverted_pixels_are_corrected() {
+        // A 4x4 square in the middle of an 8x8 field, wound in reverse.
+        let mut outline = FlatOutline::default();
+        outline.move_to(2.0, 2.0);
+        outline.line_to(2.0, 6.0);
+        outline.line_to(6.0, 6.0);
+        outline.line_to(6.0, 2.0);
+        outline.close();
+
+        let projection = Projection {
+            scale: Vector2 { x: 1.0, y: 1.0 },
+            translation: Vector2 { x: 0.0, y: 0.0 },
+        };
+
+        // Every pixel claims to be inside, which is only true for the square.
+        let mut field: ImageBuffer<Rgba<f32>, Vec<f32>> =
+            ImageBuffer::from_pixel(8, 8, Rgba([0.75, 0.75, 0.75, 0.75]));
+        outline.correct_sign(&mut field, &projection, FillRule::NonZero);
+
+        for (x, y, pixel) in field.enumerate_pixels() {
+            let inside = (2..6).contains(&x) && (2..6).contains(&y);
+            let expected = if inside { 0.75 } else { 0.25 };
+            assert_eq!(
+                pixel[0], expected,
+                "Pixel ({}, {}) did not match the fill of the outline.",
+                x, y
+            );
+        }
+    }
+
+    #[test]
+    fn corrects_the_rows_of_an_asymmetric_outline() {
+        // An L shape whose foot is at the bottom of an 8x8 field, so flipping the rows would
+        // move the foot to the top.
+        let mut outline = FlatOutline::default();
+        outline.move_to(1.0, 1.0);
+        outline.line_to(7.0, 1.0);
+        outline.line_to(7.0, 3.0);
+        outline.line_to(3.0, 3.0);
+        outline.line_to(3.0, 7.0);
+        outline.line_to(1.0, 7.0);
+        outline.close();
+
+        let projection = Projection {
+            scale: Vector2 { x: 1.0, y: 1.0 },
+            translation: Vector2 { x: 0.0, y: 0.0 },
+        };
+
+        // The filled columns of each image row, from the top.
+        let filled_columns = |row: u32| match row {
+            1..=4 => 1..3,
+            5 | 6 => 1..7,
+            _ => 0..0,
+        };
+
+        // Every pixel claims to be outside, so only the pixels of the L are corrected.
+        let mut field: ImageBuffer<Rgba<f32>, Vec<f32>> =
+            ImageBuffer::from_pixel(8, 8, Rgba([0.25, 0.25, 0.25, 0.25]));
+        outline.correct_sign(&mut field, &projection, FillRule::NonZero);
+
+        let corrected_rows: Vec<u32> = (0..8)
+            .filter(|row| (0..8).any(|x| field.get_pixel(x, *row)[0] == 0.75))
+            .collect();
+        assert_eq!(corrected_rows, vec![1, 2, 3, 4, 5, 6]);
+
+        for (x, y, pixel) in field.enumerate_pixels() {
+            let expected = if filled_columns(y).contains(&x) {
+                0.75
+            } else {
+                0.25
+            };
+            assert_eq!(
+                pixel[0], expected,
+                "Pixel ({}, {}) did not match the fill of the outline.",
+                x, y
+            );
+        }
+    }
+}
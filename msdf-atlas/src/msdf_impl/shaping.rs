@@ -0,0 +1,211 @@
+use super::font_data::FontData;
+use super::layout::{position_lines, GlyphQuad, LayoutArgs};
+use rustybuzz::{Direction, UnicodeBuffer};
+use std::collections::HashSet;
+use ttf_parser::{Face, GlyphId};
+
+/// A glyph positioned by the shaper. Advances and offsets are in font units, where y grows
+/// upwards.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    /// The index of the glyph in the font.
+    pub glyph_id: u32,
+    /// The byte offset into the text of the first char the glyph was shaped from.
+    pub cluster: u32,
+    pub x_advance: i32,
+    pub y_advance: i32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+/// The shaped glyphs of a single line in visual order, from left to right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapedRun {
+    /// Was the line shaped right to left, such as Arabic or Hebrew?
+    pub right_to_left: bool,
+    pub glyphs: Vec<ShapedGlyph>,
+}
+
+/// Shapes each line of the text with rustybuzz. The script, direction and language of each line
+/// are guessed from its contents.
+///
+/// # Arguments
+///
+/// * `face` - The font face to shape the text with
+/// * `text` - The text to shape, where each newline starts a new run
+pub fn shape_text(face: &rustybuzz::Face, text: &str) -> Vec<ShapedRun> {
+    let mut runs: Vec<ShapedRun> = Vec::new();
+    let mut line_start = 0;
+
+    for line in text.split('\n') {
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(line);
+        buffer.guess_segment_properties();
+        let right_to_left = buffer.direction() == Direction::RightToLeft;
+
+        let shaped = rustybuzz::shape(face, &[], buffer);
+        let glyphs = shaped
+            .glyph_infos()
+            .iter()
+            .zip(shaped.glyph_positions())
+            .map(|(info, position)| ShapedGlyph {
+                glyph_id: info.glyph_id,
+                cluster: line_start + info.cluster,
+                x_advance: position.x_advance,
+                y_advance: position.y_advance,
+                x_offset: position.x_offset,
+                y_offset: position.y_offset,
+            })
+            .collect();
+
+        runs.push(ShapedRun {
+            right_to_left,
+            glyphs,
+        });
+        line_start += line.len() as u32 + 1;
+    }
+    runs
+}
+
+/// Appends every glyph used by the runs which is not generated yet. A glyph keeps the unicode
+/// it was shaped from when the font maps that unicode to it, and otherwise has a unicode of 0.
+///
+/// # Arguments
+///
+/// * `glyphs` - The unicode and glyph index of every glyph to generate
+/// * `face` - The font face the runs were shaped with
+/// * `text` - The text the runs were shaped from
+/// * `runs` - The shaped runs
+pub fn append_shaped_glyphs(
+    glyphs: &mut Vec<(char, GlyphId)>,
+    face: &Face,
+    text: &str,
+    runs: &[ShapedRun],
+) {
+    let mut generated: HashSet<GlyphId> = glyphs.iter().map(|(_, glyph_id)| *glyph_id).collect();
+
+    for shaped in runs.iter().flat_map(|run| &run.glyphs) {
+        let glyph_id = GlyphId(shaped.glyph_id as u16);
+        if !generated.insert(glyph_id) {
+            continue;
+        }
+
+        let unicode = text
+            .get(shaped.cluster as usize..)
+            .and_then(|cluster| cluster.chars().next())
+            .filter(|c| face.glyph_index(*c) == Some(glyph_id))
+            .unwrap_or('\0');
+        glyphs.push((unicode, glyph_id));
+    }
+}
+
+/// Lays out the shaped runs into positioned quads, where each run is a line. Runs are aligned
+/// like layout_text, but are never wrapped since breaking a shaped run requires shaping it
+/// again. Glyphs without a bitmap and glyphs which were not generated advance the pen without
+/// producing a quad.
+///
+/// # Arguments
+///
+/// * `font_data` - The metrics and glyphs of the generated font
+/// * `runs` - The runs returned by the shaper
+/// * `args` - Layout params
+pub fn layout_shaped_runs(
+    font_data: &FontData,
+    runs: &[ShapedRun],
+    args: &LayoutArgs,
+) -> Vec<GlyphQuad> {
+    let scale = args.font_size / font_data.units_per_em.max(1) as f32;
+    let line_advance = (font_data.ascender - font_data.descender) as f32 * scale;
+
+    let glyphs_by_id = font_data.glyphs_by_id();
+    let mut lines: Vec<Vec<GlyphQuad>> = Vec::with_capacity(runs.len());
+    for run in runs {
+        let mut line: Vec<GlyphQuad> = Vec::with_capacity(run.glyphs.len());
+        let (mut pen_x, mut pen_y) = (0.0, 0.0);
+
+        for shaped in &run.glyphs {
+            let glyph = glyphs_by_id
+                .get(&shaped.glyph_id)
+                .filter(|glyph| glyph.has_bitmap);
+
+            if let Some(glyph) = glyph {
+                let (metrics_x, metrics_y) = glyph.metrics();
+                let (bearings_x, bearings_y) = glyph.bearings();
+
+                // The shaper's offsets grow upwards, while the quads grow downwards.
+                let x_min = pen_x + (shaped.x_offset as f32 + bearings_x) * scale;
+                let y_min = pen_y - (shaped.y_offset as f32 + bearings_y) * scale;
+                let (uv_x, uv_y, uv_z, uv_w) = glyph.uvs();
+                line.push(GlyphQuad {
+                    unicode: glyph.unicode,
+                    x_min,
+                    y_min,
+                    x_max: x_min + metrics_x * scale,
+                    y_max: y_min + metrics_y * scale,
+                    uv_x,
+                    uv_y,
+                    uv_z,
+                    uv_w,
+                });
+            }
+
+            pen_x += shaped.x_advance as f32 * scale;
+            pen_y -= shaped.y_advance as f32 * scale;
+        }
+        lines.push(line);
+    }
+
+    position_lines(lines, args, line_advance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layout_shaped_runs, ShapedGlyph, ShapedRun};
+    use crate::msdf_impl::{font_data::FontData, glyph_data::GlyphData, layout::LayoutArgs};
+
+    #[test]
+    fn positions_shaped_glyphs() {
+        // A ligature without a unicode and a mark placed above it.
+        let glyphs = vec![
+            GlyphData::from_unicode(0)
+                .with_glyph_id(7)
+                .with_metrics(100, 70)
+                .with_bearings(5, -10),
+            GlyphData::from_unicode(0)
+                .with_glyph_id(9)
+                .with_metrics(20, 10)
+                .with_bearings(0, -10),
+        ];
+        let font_data = FontData::new(60, 100, 80, -20, glyphs, Vec::new());
+
+        let shaped = |glyph_id, x_advance, x_offset, y_offset| ShapedGlyph {
+            glyph_id,
+            cluster: 0,
+            x_advance,
+            y_advance: 0,
+            x_offset,
+            y_offset,
+        };
+        let runs = vec![ShapedRun {
+            right_to_left: false,
+            glyphs: vec![shaped(7, 110, 0, 0), shaped(9, 0, -60, 90)],
+        }];
+
+        let args = LayoutArgs::default().with_font_size(10.0);
+        let quads = layout_shaped_runs(&font_data, &runs, &args);
+        assert_eq!(quads.len(), 2);
+
+        let (ligature, mark) = (quads[0], quads[1]);
+        assert_eq!(ligature.x_min, 0.5);
+        assert_eq!(ligature.y_min, 1.0);
+        assert_eq!(
+            mark.x_min, 5.0,
+            "The mark should be offset back from the pen."
+        );
+        assert_eq!(
+            mark.y_min, -8.0,
+            "The mark should be raised above the line."
+        );
+    }
+}
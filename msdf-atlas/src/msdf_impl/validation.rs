@@ -0,0 +1,239 @@
+use super::enums::AtlasPrecision;
+use super::font_asset::{hash_atlas, FontAsset};
+use super::glyph_data::GlyphData;
+use image::ImageResult;
+use std::fmt::{Display, Formatter, Result};
+use std::path::Path;
+
+/// A single problem found while validating an atlas against its metadata.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationIssue {
+    /// The glyph's uv rect is not a valid rect within the atlas.
+    UvOutOfBounds { unicode: i32 },
+    /// The pixels of both glyphs overlap in the atlas.
+    OverlappingGlyphs { first: i32, second: i32 },
+    /// The atlas does not have the dimensions stored in the metadata.
+    AtlasSizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// The atlas pixels do not hash to the hash stored in the metadata.
+    AtlasHashMismatch { expected: u64, actual: u64 },
+    /// The metadata was written before atlas hashes were stored, so the pixels could not be
+    /// verified.
+    MissingAtlasHash,
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ValidationIssue::UvOutOfBounds { unicode } => {
+                write!(f, "The uvs of {} lie outside of the atlas.", unicode)
+            }
+            ValidationIssue::OverlappingGlyphs { first, second } => {
+                write!(
+                    f,
+                    "The glyphs {} and {} overlap in the atlas.",
+                    first, second
+                )
+            }
+            ValidationIssue::AtlasSizeMismatch { expected, actual } => write!(
+                f,
+                "The atlas is {}x{}, but the metadata expects {}x{}.",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            ValidationIssue::AtlasHashMismatch { expected, actual } => write!(
+                f,
+                "The atlas hash is {:016x}, but the metadata expects {:016x}.",
+                actual, expected
+            ),
+            ValidationIssue::MissingAtlasHash => {
+                write!(f, "The metadata does not store an atlas hash.")
+            }
+        }
+    }
+}
+
+/// The result of validating an atlas against its metadata.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationReport {
+    /// The number of glyphs that were checked.
+    pub glyph_count: usize,
+    /// The hash of the atlas pixels that were checked.
+    pub atlas_hash: u64,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Did the atlas pass every check?
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(
+            f,
+            "Checked {} glyphs against atlas {:016x}: {} issues found.",
+            self.glyph_count,
+            self.atlas_hash,
+            self.issues.len()
+        )?;
+        for issue in &self.issues {
+            writeln!(f, "  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// A glyph's rect in atlas pixels.
+struct PixelRect {
+    unicode: i32,
+    x_min: u32,
+    y_min: u32,
+    x_max: u32,
+    y_max: u32,
+}
+
+impl PixelRect {
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.x_min == self.x_max || self.y_min == self.y_max
+    }
+}
+
+/// Validates that every glyph's uv rect lies inside the atlas, that no two rects overlap, and
+/// that the atlas pixels match the stored hash.
+///
+/// # Arguments
+///
+/// * `asset` - The font asset to validate
+pub fn validate_font_asset(asset: &FontAsset) -> ValidationReport {
+    let atlas_hash = hash_atlas(&asset.atlas.data);
+    let mut issues = validate_glyphs(asset);
+
+    match asset.atlas_hash {
+        Some(expected) if expected != atlas_hash => {
+            issues.push(ValidationIssue::AtlasHashMismatch {
+                expected,
+                actual: atlas_hash,
+            });
+        }
+        Some(_) => {}
+        None => issues.push(ValidationIssue::MissingAtlasHash),
+    }
+
+    ValidationReport {
+        glyph_count: asset.glyphs.len(),
+        atlas_hash,
+        issues,
+    }
+}
+
+/// Validates the glyphs in the font asset against an atlas exported as an image, such as the
+/// atlas written next to the metadata for Unity. The image must have been written with the same
+/// precision as the font asset.
+///
+/// # Arguments
+///
+/// * `asset` - The font asset storing the metadata
+/// * `atlas_path` - The path to the atlas image
+pub fn validate_atlas_image(asset: &FontAsset, atlas_path: &Path) -> ImageResult<ValidationReport> {
+    let image = image::open(atlas_path)?;
+    let data: Vec<u8> = match asset.atlas.precision {
+        AtlasPrecision::Bits8 => image.to_rgba8().into_raw(),
+        AtlasPrecision::Bits16 => image
+            .to_rgba16()
+            .into_raw()
+            .iter()
+            .flat_map(|channel| channel.to_le_bytes())
+            .collect(),
+        AtlasPrecision::Float32 => image
+            .to_rgba32f()
+            .into_raw()
+            .iter()
+            .flat_map(|channel| channel.to_le_bytes())
+            .collect(),
+    };
+
+    let atlas_hash = hash_atlas(&data);
+    let mut issues = validate_glyphs(asset);
+
+    let expected = (asset.atlas.width, asset.atlas.height);
+    let actual = (image.width(), image.height());
+    if expected != actual {
+        issues.push(ValidationIssue::AtlasSizeMismatch { expected, actual });
+    }
+
+    match asset.atlas_hash {
+        Some(expected) if expected != atlas_hash => {
+            issues.push(ValidationIssue::AtlasHashMismatch {
+                expected,
+                actual: atlas_hash,
+            });
+        }
+        Some(_) => {}
+        None => issues.push(ValidationIssue::MissingAtlasHash),
+    }
+
+    Ok(ValidationReport {
+        glyph_count: asset.glyphs.len(),
+        atlas_hash,
+        issues,
+    })
+}
+
+/// Checks that every uv rect lies inside the atlas and that no two rects overlap.
+fn validate_glyphs(asset: &FontAsset) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut rects: Vec<PixelRect> = Vec::with_capacity(asset.glyphs.len());
+
+    for glyph in &asset.glyphs {
+        match to_pixel_rect(glyph, asset) {
+            Some(rect) => rects.push(rect),
+            None => issues.push(ValidationIssue::UvOutOfBounds {
+                unicode: glyph.unicode,
+            }),
+        }
+    }
+
+    // Sweep down the atlas, so each rect is only compared against rects that start above its
+    // bottom edge.
+    rects.retain(|rect| !rect.is_empty());
+    rects.sort_unstable_by_key(|rect| rect.y_min);
+    for (i, rect) in rects.iter().enumerate() {
+        for other in rects[i + 1..]
+            .iter()
+            .take_while(|other| other.y_min < rect.y_max)
+        {
+            if other.x_min < rect.x_max && rect.x_min < other.x_max {
+                issues.push(ValidationIssue::OverlappingGlyphs {
+                    first: rect.unicode,
+                    second: other.unicode,
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// Converts the glyph's uvs back into atlas pixels by undoing the uv space. Returns None if the
+/// uvs do not form a rect inside the atlas.
+fn to_pixel_rect(glyph: &GlyphData, asset: &FontAsset) -> Option<PixelRect> {
+    let (x_min, y_min, x_max, y_max) = asset.settings.uv_space.to_texture_rect(glyph.uvs());
+
+    let in_bounds = |min: f32, max: f32| (0.0..=1.0).contains(&min) && min <= max && max <= 1.0;
+    if !in_bounds(x_min, x_max) || !in_bounds(y_min, y_max) {
+        return None;
+    }
+
+    let (width, height) = (asset.atlas.width as f32, asset.atlas.height as f32);
+    Some(PixelRect {
+        unicode: glyph.unicode,
+        x_min: (x_min * width).round() as u32,
+        y_min: (y_min * height).round() as u32,
+        x_max: (x_max * width).round() as u32,
+        y_max: (y_max * height).round() as u32,
+    })
+}